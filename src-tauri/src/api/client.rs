@@ -1,192 +1,334 @@
+use crate::api::failover::LineTracker;
+use crate::api::inbound::{load_verified_inbound_config, InboundConfig};
 use crate::api::interceptor::{
-    is_auth_required_url, save_auth_token, AuthInterceptor, Interceptor,
+    AuthInterceptor, EndpointMetrics, ErrorMappingInterceptor, FailoverInterceptor, Interceptor,
+    LoggingInterceptor, MetricsInterceptor, Pipeline, RequestContext, SessionInterceptor,
+    TokenCaptureInterceptor, TokenClearInterceptor, VersionGateInterceptor,
 };
+use crate::auth::{DbTokenStore, MemoryTokenStore, Session, TokenStore};
 use crate::config;
 use crate::database::Database;
-use reqwest::header::HeaderValue;
-use reqwest::{Client, Request, Response};
-use std::sync::Arc;
-use tauri::AppHandle;
-use tauri::Manager;
-use tracing::error;
+use crate::error::AppError;
+use crate::settings::{self, SettingKey};
+use crate::shutdown;
+use crate::utils::http::{build_client_with, subscribe_proxy, ProxyMode, ProxySettings};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderValue, RETRY_AFTER};
+use reqwest::{Client, Method, Request, Response, StatusCode};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
 
 /// HTTP 请求客户端，支持拦截器机制
 pub struct ApiClient {
-    client: Arc<Client>,
-    interceptors: Vec<Box<dyn Interceptor>>,
-    app_handle: Option<Arc<AppHandle>>,
+    client: RwLock<Arc<Client>>,
+    // 配置或代理设置变更后重建客户端
+    config_changes: Mutex<watch::Receiver<config::AppConfig>>,
+    proxy_changes: Mutex<watch::Receiver<ProxySettings>>,
+    pipeline: Pipeline,
+    metrics: Arc<MetricsInterceptor>,
+    session: Arc<Session>,
+    lines: Arc<LineTracker>,
+    version_gate: Option<Arc<VersionGateInterceptor>>,
 }
 
-impl ApiClient {
-    /// 创建 API 客户端实例
-    pub fn new(app_handle: Option<AppHandle>) -> Self {
-        // 从配置中读取是否验证SSL证书
-        let verify_ssl = config::CONFIG.read().unwrap().api.verify_ssl;
-        
-        // 创建客户端构建器并根据配置决定是否验证证书
-        let mut client_builder = Client::builder()
-            .timeout(config::get_request_timeout());
-            
-        // 如果不验证SSL证书，添加跳过验证选项
-        if !verify_ssl {
-            client_builder = client_builder.danger_accept_invalid_certs(true);
+// 请求失败时按相反顺序通知拦截器
+async fn notify_error(chain: &[Arc<dyn Interceptor>], ctx: &RequestContext, error: &AppError) {
+    for interceptor in chain.iter().rev() {
+        interceptor.on_error(ctx, error).await;
+    }
+}
+
+// 判断请求是否可以安全重试：按方法判断，POST 需要在配置中声明为幂等接口
+fn is_idempotent(method: &Method, path: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS => true,
+        Method::POST => config::is_idempotent_endpoint(path),
+        _ => false,
+    }
+}
+
+// 服务端错误和限流时重试
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+// 解析 Retry-After 响应头，支持秒数和 HTTP 日期两种格式
+fn parse_retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    // 日期已过时立即重试
+    Some((date.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default())
+}
+
+// 根据当前配置和代理设置构建HTTP客户端
+fn build_client(config: &config::AppConfig, proxy: &ProxySettings) -> Result<Client, String> {
+    build_client_with(
+        Duration::from_secs(config.timeouts.request_timeout_secs),
+        &config.api,
+        proxy,
+    )
+}
+
+// 创建启动时使用的客户端，代理或证书配置无效时依次回退，默认配置也无法创建时返回错误
+fn initial_client(config: &config::AppConfig, proxy: &ProxySettings) -> Result<Client, String> {
+    let e = match build_client(config, proxy) {
+        Ok(client) => return Ok(client),
+        Err(e) => e,
+    };
+    error!(target: "http_client", "创建HTTP客户端失败: {}", e);
+
+    let system_proxy = ProxySettings::default();
+    if proxy.mode == ProxyMode::Manual {
+        match build_client(config, &system_proxy) {
+            Ok(client) => {
+                warn!(target: "http_client", "代理设置无效，改用系统代理: {}", proxy.url());
+                return Ok(client);
+            }
+            Err(e) => error!(target: "http_client", "使用系统代理创建HTTP客户端失败: {}", e),
         }
-        
+    }
+
+    warn!(target: "http_client", "使用默认配置创建HTTP客户端，仍然校验服务端证书");
+    build_client(&config::AppConfig::default(), &system_proxy)
+}
+
+/// 当前线路设置变更时同步到线路列表，请求时不需要读取数据库
+fn follow_current_line(db: Database, lines: Arc<LineTracker>) {
+    shutdown::spawn(async move {
+        let mut changes = settings::subscribe();
+        loop {
+            match changes.recv().await {
+                Ok(change) if change.key == SettingKey::CurrentInbound => {
+                    let index = change.value.and_then(|value| value.parse().ok());
+                    lines.set_current(index.unwrap_or(0));
+                }
+                Ok(_) => {}
+                // 错过了部分通知，重新读取一次
+                Err(RecvError::Lagged(_)) => {
+                    lines.set_current(settings::current_inbound(&db).await);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+impl ApiClient {
+    /// 创建 API 客户端实例，无法创建HTTP客户端时返回错误
+    pub fn new(app_handle: Option<AppHandle>) -> Result<Self, String> {
+        let mut config_changes = config::subscribe();
+        let config = config::CONFIG.read().unwrap().clone();
+        config_changes.mark_unchanged();
+        let mut proxy_changes = subscribe_proxy();
+        let proxy = proxy_changes.borrow_and_update().clone();
+
         // 构建HTTP客户端
-        let client = Arc::new(
-            client_builder
-                .build()
-                .expect("Failed to create HTTP client"),
-        );
+        let client = Arc::new(initial_client(&config, &proxy)?);
 
-        let mut interceptors = Vec::new();
-        if let Some(handle) = &app_handle {
-            interceptors
-                .push(Box::new(AuthInterceptor::new(Arc::new(handle.clone())))
-                    as Box<dyn Interceptor>);
+        // 请求按注册顺序经过拦截器，响应按相反顺序经过
+        let metrics = Arc::new(MetricsInterceptor::default());
+        let mut pipeline = Pipeline::default();
+        pipeline.register("*", Arc::new(LoggingInterceptor));
+        pipeline.register("*", metrics.clone());
+        pipeline.register("*", Arc::new(ErrorMappingInterceptor));
+        let lines = Arc::new(LineTracker::default());
+        let mut inbound = InboundConfig::default();
+        if let Some(db) = app_handle
+            .as_ref()
+            .and_then(|handle| handle.try_state::<Database>())
+        {
+            let db = db.inner().clone();
+            // 只使用签名校验通过的线路，初始化线路配置后再同步
+            if let Some(config) = db.blocking().wait(load_verified_inbound_config(&db)) {
+                inbound = config;
+            }
+            lines.set_lines(inbound.inbound.clone());
+            lines.set_current(db.blocking().wait(settings::current_inbound(&db)));
+            follow_current_line(db, lines.clone());
         }
+        let mut version_gate = None;
+        let session = match app_handle {
+            Some(handle) => {
+                let tokens: Arc<dyn TokenStore> = Arc::new(DbTokenStore::new(handle.clone()));
+                let session = Arc::new(Session::new(tokens.clone()));
+                let gate = Arc::new(VersionGateInterceptor::new(
+                    handle.package_info().version.clone(),
+                    &inbound,
+                ));
+                version_gate = Some(gate.clone());
+                let handle = Arc::new(handle);
+                pipeline.register("*", gate);
+                pipeline.register("*", Arc::new(FailoverInterceptor::new(handle.clone(), lines.clone())));
+                pipeline.register("*", Arc::new(AuthInterceptor::new(handle.clone(), tokens.clone())));
+                pipeline.register("*", Arc::new(SessionInterceptor::new(handle, session.clone())));
+                let capture = Arc::new(TokenCaptureInterceptor::new(tokens.clone()));
+                pipeline.register("/login", capture.clone());
+                pipeline.register("/emailRegister", capture);
+                pipeline.register(
+                    "/user/updatePassword",
+                    Arc::new(TokenClearInterceptor::new(tokens)),
+                );
+                session
+            }
+            None => Arc::new(Session::new(Arc::new(MemoryTokenStore::default()))),
+        };
 
-        Self {
-            client,
-            interceptors,
-            app_handle: app_handle.map(Arc::new),
+        Ok(Self {
+            client: RwLock::new(client),
+            config_changes: Mutex::new(config_changes),
+            proxy_changes: Mutex::new(proxy_changes),
+            pipeline,
+            metrics,
+            session,
+            lines,
+            version_gate,
+        })
+    }
+
+    /// 线路配置更新后同步线路列表和版本状态
+    pub fn apply_inbound_config(&self, config: &InboundConfig) {
+        self.lines.set_lines(config.inbound.clone());
+        if let Some(gate) = &self.version_gate {
+            gate.update(config);
         }
     }
 
-    /// 获取基础URL，优先使用inbound配置
-    pub fn get_base_url(&self) -> String {
-        // use crate::api::inbound::get_current_inbound_url;
-
-        // // 如果有AppHandle，尝试获取当前线路URL
-        // if let Some(handle) = &self.app_handle {
-        //     if let Some(db) = handle.try_state::<crate::database::Database>() {
-        //         return get_current_inbound_url(&db);
-        //     }
-        // }
-
-        
-        // 回退到默认URL
-        config::get_default_api_url()
+    /// 线路健康状态
+    pub fn lines(&self) -> &Arc<LineTracker> {
+        &self.lines
     }
 
-    /// 发送 HTTP 请求
-    pub async fn send(&self, mut request: Request) -> Result<Response, reqwest::Error> {
-        let url = request.url().to_string();
-        let method = request.method().clone();
-        
-        // 在请求前添加拦截器处理（如果需要认证）
-        if is_auth_required_url(&url) {
-            for interceptor in &self.interceptors {
-                if interceptor.intercept(&mut request).is_err() {
-                    continue;
+    /// 认证令牌存储
+    pub fn tokens(&self) -> &Arc<dyn TokenStore> {
+        self.session.tokens()
+    }
+
+    /// 各接口的请求统计
+    pub fn metrics(&self) -> Vec<EndpointMetrics> {
+        self.metrics.snapshot()
+    }
+
+    /// 获取HTTP客户端，配置或代理设置变更后先重建
+    fn http(&self) -> Arc<Client> {
+        let mut changes = self.config_changes.lock().unwrap();
+        let mut proxy_changes = self.proxy_changes.lock().unwrap();
+        if changes.has_changed().unwrap_or(false) || proxy_changes.has_changed().unwrap_or(false) {
+            let config = changes.borrow_and_update().clone();
+            let proxy = proxy_changes.borrow_and_update().clone();
+            match build_client(&config, &proxy) {
+                Ok(client) => {
+                    *self.client.write().unwrap() = Arc::new(client);
+                    info!(target: "http_client", "配置已变更，HTTP客户端已重建");
+                }
+                Err(e) => {
+                    error!(target: "http_client", "重建HTTP客户端失败，继续使用旧客户端: {}", e);
                 }
             }
         }
+        self.client.read().unwrap().clone()
+    }
 
-        // 检查请求是否可克隆
-        let can_clone = request.try_clone().is_some();
-        
-        if can_clone {
-            // 第一次尝试
-            let cloned_request = request.try_clone().unwrap();
-            let result = self.client.execute(cloned_request).await;
-            
-            if let Ok(response) = result {
-                return self.process_response(response, &method.to_string(), &url).await;
-            }
-            
-            let err = result.unwrap_err();
-            error!(
-                target: "http_client",
-                "第一次请求失败 - 方法: {}, URL: {}, 错误: {}",
-                method, url, err
-            );
-            
-            // 第二次尝试
-            let cloned_request = request.try_clone().unwrap();
-            let result = self.client.execute(cloned_request).await;
-            
-            if let Ok(response) = result {
-                return self.process_response(response, &method.to_string(), &url).await;
+    /// 获取基础URL，优先使用当前选择的线路
+    pub fn get_base_url(&self) -> String {
+        match self.lines.current_line() {
+            Some(item) => format!("{}/api", item.url),
+            // 回退到默认URL
+            None => config::get_default_api_url(),
+        }
+    }
+
+    /// 发送 HTTP 请求，依次经过匹配路由的拦截器
+    pub async fn send(&self, mut request: Request) -> Result<Response, AppError> {
+        let mut ctx = RequestContext::new(&request);
+        let chain = self.pipeline.matching(&ctx.path);
+
+        for (i, interceptor) in chain.iter().enumerate() {
+            if let Err(e) = interceptor.on_request(&mut ctx, &mut request).await {
+                notify_error(&chain[..i], &ctx, &e).await;
+                return Err(e);
             }
-            
-            let err = result.unwrap_err();
-            error!(
-                target: "http_client",
-                "第二次请求失败 - 方法: {}, URL: {}, 错误: {}",
-                method, url, err
-            );
         }
-        
-        // 第三次尝试 (对于注册请求是第一次)
-        let result = self.client.execute(request).await;
-        
-        match result {
-            Ok(response) => {
-                self.process_response(response, &method.to_string(), &url).await
-            },
+
+        let mut response = match self.execute(request, &ctx).await {
+            Ok(response) => response,
             Err(e) => {
-                error!(
-                    target: "http_client",
-                    "请求失败 - 方法: {}, URL: {}, 错误: {}",
-                    method, url, e
-                );
-                Err(e)
+                let e = AppError::from(e);
+                notify_error(&chain, &ctx, &e).await;
+                return Err(e);
             }
+        };
+
+        for (i, interceptor) in chain.iter().enumerate().rev() {
+            response = match interceptor.on_response(&ctx, response).await {
+                Ok(response) => response,
+                Err(e) => {
+                    notify_error(&chain[..i], &ctx, &e).await;
+                    return Err(e);
+                }
+            };
         }
+        Ok(response)
     }
-    
-    /// 处理响应
-    async fn process_response(&self, response: Response, method: &str, url: &str) -> Result<Response, reqwest::Error> {
-        if self.app_handle.is_none() {
-            return Ok(response);
-        }
 
-        let handle = self.app_handle.as_ref().unwrap();
-        let db = handle.state::<Database>();
-        let status = response.status();
-        
-        let response_text = response.text().await.map_err(|e| {
-            error!(
-                target: "http_client",
-                "读取响应文本失败 - 方法: {}, URL: {}, 状态码: {}, 错误: {}",
-                method, url, status, e
-            );
-            e
-        })?;
-
-        if url.contains("/user/updatePassword") {
-            if let Ok(response_json) = serde_json::from_str::<serde_json::Value>(&response_text) {
-                if response_json["status"] == 200 {
-                    if let Err(e) = crate::api::interceptor::clear_auth_token(&db).await {
-                        error!(
+    // 执行请求，网络错误、5xx 和 429 按重试策略重试
+    async fn execute(&self, request: Request, ctx: &RequestContext) -> Result<Response, reqwest::Error> {
+        let client = self.http();
+        let policy = config::get_retry_policy();
+        let idempotent = is_idempotent(&ctx.method, &ctx.path);
+        let start = Instant::now();
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            let result = match request.try_clone() {
+                Some(cloned) => client.execute(cloned).await,
+                // 请求体无法克隆时只能发送一次
+                None => return client.execute(request).await,
+            };
+
+            let should_retry = match &result {
+                // 连接未建立时请求没有发出，任何请求都可以重试
+                Err(e) if e.is_connect() => true,
+                _ if !idempotent => false,
+                Ok(response) => is_retryable_status(response.status()),
+                Err(e) => e.is_timeout() || e.is_request(),
+            };
+            let retry_after = result.as_ref().ok().and_then(parse_retry_after);
+
+            if should_retry {
+                if let Some(delay) = policy.next_delay(attempt, start.elapsed(), retry_after) {
+                    match &result {
+                        Ok(response) => error!(
+                            target: "http_client",
+                            "请求失败，{:?}后重试 ({}/{}) - 方法: {}, URL: {}, 状态码: {}",
+                            delay, attempt, policy.max_attempts, ctx.method, ctx.url, response.status()
+                        ),
+                        Err(e) => error!(
                             target: "http_client",
-                            "清除认证令牌失败 - URL: {}, 错误: {}",
-                            url, e
-                        );
+                            "请求失败，{:?}后重试 ({}/{}) - 方法: {}, URL: {}, 错误: {}",
+                            delay, attempt, policy.max_attempts, ctx.method, ctx.url, e
+                        ),
                     }
+                    tokio::time::sleep(delay).await;
+                    continue;
                 }
             }
-        } else if let Err(e) = save_auth_token(&db, url, &response_text).await {
-            error!(
-                target: "http_client",
-                "保存认证令牌失败 - URL: {}, 错误: {}",
-                url, e
-            );
-        }
 
-        Ok(Response::from(
-            http::Response::builder()
-                .status(status)
-                .body(response_text)
-                .unwrap(),
-        ))
+            return result;
+        }
     }
 
     /// 创建 GET 请求
     pub fn get(&self, url: impl AsRef<str>) -> RequestBuilder {
         RequestBuilder {
-            inner: self.client.get(url.as_ref()),
+            inner: self.http().get(url.as_ref()),
             client: self,
         }
     }
@@ -194,7 +336,7 @@ impl ApiClient {
     /// 创建 POST 请求
     pub fn post(&self, url: impl AsRef<str>) -> RequestBuilder {
         RequestBuilder {
-            inner: self.client.post(url.as_ref()),
+            inner: self.http().post(url.as_ref()),
             client: self,
         }
     }
@@ -202,7 +344,7 @@ impl ApiClient {
     /// 创建 PUT 请求
     pub fn put(&self, url: impl AsRef<str>) -> RequestBuilder {
         RequestBuilder {
-            inner: self.client.put(url.as_ref()),
+            inner: self.http().put(url.as_ref()),
             client: self,
         }
     }
@@ -210,7 +352,7 @@ impl ApiClient {
     /// 创建 DELETE 请求
     pub fn delete(&self, url: impl AsRef<str>) -> RequestBuilder {
         RequestBuilder {
-            inner: self.client.delete(url.as_ref()),
+            inner: self.http().delete(url.as_ref()),
             client: self,
         }
     }
@@ -224,7 +366,7 @@ pub struct RequestBuilder<'a> {
 
 impl<'a> RequestBuilder<'a> {
     /// 发送请求
-    pub async fn send(self) -> Result<Response, reqwest::Error> {
+    pub async fn send(self) -> Result<Response, AppError> {
         // 在构建请求前获取内部构建器的调试信息
         let debug_info = format!("{:?}", self.inner);
 
@@ -236,7 +378,7 @@ impl<'a> RequestBuilder<'a> {
                     "构建HTTP请求失败 - 请求: {}, 错误: {}",
                     debug_info, e
                 );
-                return Err(e);
+                return Err(e.into());
             }
         };
         self.client.send(request).await
@@ -281,3 +423,41 @@ impl<'a> RequestBuilder<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response_with_retry_after(value: &str) -> Response {
+        Response::from(
+            http::Response::builder()
+                .status(503)
+                .header(RETRY_AFTER, value)
+                .body("")
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(
+            parse_retry_after(&response_with_retry_after("3")),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            parse_retry_after(&response_with_retry_after("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after(&response_with_retry_after("soon")), None);
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        assert!(is_idempotent(&Method::GET, "/public/info"));
+        assert!(is_idempotent(&Method::POST, "/api/checkUser"));
+        assert!(!is_idempotent(&Method::POST, "/api/checkUserPassword"));
+        assert!(!is_idempotent(&Method::POST, "/api/checkUser/reset"));
+        assert!(!is_idempotent(&Method::POST, "/login"));
+        assert!(!is_idempotent(&Method::POST, "/user/activate"));
+    }
+}
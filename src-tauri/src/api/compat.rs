@@ -0,0 +1,120 @@
+//! 客户端版本检查：根据远程配置中的最低版本和推荐版本判断是否需要更新
+
+use super::inbound::{self, InboundConfig};
+use crate::database::Database;
+use semver::Version;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Manager};
+
+/// 当前支持的远程配置格式版本，更高版本中新增的字段会被忽略
+pub const SUPPORTED_SCHEMA: u32 = 2;
+
+/// 是否需要更新
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateRequirement {
+    None,
+    // 低于推荐版本，提示更新
+    Recommended,
+    // 低于最低版本，必须更新后才能继续使用
+    Required,
+}
+
+/// 客户端版本状态，供前端决定是否强制更新
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionStatus {
+    pub current_version: String,
+    pub min_version: Option<String>,
+    pub recommended_version: Option<String>,
+    pub requirement: UpdateRequirement,
+    pub features: BTreeMap<String, bool>,
+}
+
+impl VersionStatus {
+    pub fn update_required(&self) -> bool {
+        self.requirement == UpdateRequirement::Required
+    }
+}
+
+// 解析版本号，允许带 v 前缀，无法解析时忽略该限制
+fn parse_version(value: &str) -> Option<Version> {
+    Version::parse(value.trim().trim_start_matches('v')).ok()
+}
+
+/// 比较当前版本和远程配置中的版本要求
+pub fn evaluate(current: &Version, config: &InboundConfig) -> VersionStatus {
+    let below = |required: &Option<String>| {
+        required
+            .as_deref()
+            .and_then(parse_version)
+            .map_or(false, |required| *current < required)
+    };
+    let requirement = if below(&config.min_version) {
+        UpdateRequirement::Required
+    } else if below(&config.recommended_version) {
+        UpdateRequirement::Recommended
+    } else {
+        UpdateRequirement::None
+    };
+
+    VersionStatus {
+        current_version: current.to_string(),
+        min_version: config.min_version.clone(),
+        recommended_version: config.recommended_version.clone(),
+        requirement,
+        features: config.features.clone(),
+    }
+}
+
+/// 根据已保存的远程配置计算当前版本状态
+pub async fn version_status(app_handle: &AppHandle) -> VersionStatus {
+    let config = match app_handle.try_state::<Database>() {
+        Some(db) => inbound::load_inbound_config(&db).await.unwrap_or_default(),
+        None => InboundConfig::default(),
+    };
+    evaluate(&app_handle.package_info().version, &config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: &str) -> InboundConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_unknown_fields_are_ignored() {
+        let config = config(
+            r#"{"schema":3,"inbound":[{"name":"a","url":"https://a.example.com","weight":5}],
+                "min_version":"1.8.0","announcement":{"title":"x"}}"#,
+        );
+        assert_eq!(config.schema, 3);
+        assert_eq!(config.inbound.len(), 1);
+        assert_eq!(config.min_version.as_deref(), Some("1.8.0"));
+
+        // 旧版配置只有线路
+        let legacy = self::config(r#"{"inbound":[]}"#);
+        assert_eq!(legacy.schema, 0);
+        assert!(legacy.features.is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_requirement() {
+        let current = Version::parse("1.8.4").unwrap();
+        let status = |json: &str| evaluate(&current, &config(json)).requirement;
+
+        assert_eq!(status(r#"{"inbound":[]}"#), UpdateRequirement::None);
+        assert_eq!(
+            status(r#"{"inbound":[],"min_version":"1.8.4","recommended_version":"v1.9.0"}"#),
+            UpdateRequirement::Recommended
+        );
+        assert_eq!(
+            status(r#"{"inbound":[],"min_version":"1.9.0-beta.1","recommended_version":"1.9.0"}"#),
+            UpdateRequirement::Required
+        );
+        // 无法解析的版本号不生效
+        assert_eq!(status(r#"{"inbound":[],"min_version":"latest"}"#), UpdateRequirement::None);
+    }
+}
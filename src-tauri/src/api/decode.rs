@@ -0,0 +1,189 @@
+//! 后端响应解码：识别响应格式版本，统一转换为 ApiResponse
+//!
+//! 严格模式下数据结构与预期不一致时返回错误并给出出错的字段路径，
+//! 宽松模式下只记录日志并返回空数据
+
+use super::types::ApiResponse;
+use crate::config;
+use crate::error::AppError;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde_json::Value;
+use tracing::{error, warn};
+
+/// 响应格式版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Envelope {
+    // 旧格式 {status, msg, data}，部分接口还附带字符串类型的 code
+    V1,
+    // 当前格式 {code, message, data}
+    V2,
+}
+
+impl Envelope {
+    /// 根据状态码字段识别响应格式
+    pub fn detect(value: &Value) -> Option<Self> {
+        let object = value.as_object()?;
+        if object.get("code").map_or(false, Value::is_i64) {
+            Some(Envelope::V2)
+        } else if object.get("status").map_or(false, Value::is_i64) {
+            Some(Envelope::V1)
+        } else {
+            None
+        }
+    }
+
+    // 状态码和消息的字段名
+    fn fields(self) -> (&'static str, &'static str) {
+        match self {
+            Envelope::V1 => ("status", "msg"),
+            Envelope::V2 => ("code", "message"),
+        }
+    }
+}
+
+/// 解码模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeMode {
+    // 数据结构不一致时返回空数据
+    Lenient,
+    // 数据结构不一致时返回错误
+    Strict,
+}
+
+impl DecodeMode {
+    /// 配置中指定的解码模式
+    pub fn configured() -> Self {
+        if config::get_strict_decoding() {
+            DecodeMode::Strict
+        } else {
+            DecodeMode::Lenient
+        }
+    }
+}
+
+/// 解码响应体，状态码不为 200 时不解析 data
+pub fn decode<T: DeserializeOwned>(body: &str, mode: DecodeMode) -> Result<ApiResponse<T>, AppError> {
+    let mut value: Value = serde_json::from_str(body)?;
+    let envelope = match (Envelope::detect(&value), mode) {
+        (Some(envelope), _) => envelope,
+        (None, DecodeMode::Strict) => {
+            return Err(AppError::invalid_response("无法识别的响应格式")
+                .with_details("缺少数字类型的 code 或 status 字段"));
+        }
+        // 兼容之前的处理方式，缺少状态码时视为成功
+        (None, DecodeMode::Lenient) => Envelope::V2,
+    };
+
+    let (code_field, message_field) = envelope.fields();
+    let code = value[code_field].as_i64().unwrap_or(200) as i32;
+    let message = value[message_field].as_str().unwrap_or("未知错误").to_string();
+    if code != 200 {
+        return Ok(ApiResponse {
+            code,
+            message,
+            data: None,
+        });
+    }
+
+    let data = match value.get_mut("data").map(Value::take) {
+        None | Some(Value::Null) => None,
+        Some(data) => match serde_path_to_error::deserialize::<_, T>(data) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                let path = match e.path().to_string().as_str() {
+                    "." => "data".to_string(),
+                    path => format!("data.{}", path),
+                };
+                if mode == DecodeMode::Strict {
+                    return Err(AppError::invalid_response("服务器返回的数据格式与预期不一致")
+                        .with_details(format!("{}: {}", path, e.inner())));
+                }
+                warn!(target: "api", "响应数据格式与预期不一致，忽略数据 - 字段: {}, 错误: {}", path, e.inner());
+                None
+            }
+        },
+    };
+
+    Ok(ApiResponse {
+        code,
+        message,
+        data,
+    })
+}
+
+/// 读取并解码响应
+pub async fn read<T: DeserializeOwned>(
+    response: reqwest::Response,
+    context: &str,
+) -> Result<ApiResponse<T>, AppError> {
+    let body = response.text().await.map_err(|e| {
+        error!(target: "api", "获取{}响应文本失败 - 错误: {}", context, e);
+        e
+    })?;
+    decode(&body, DecodeMode::configured()).map_err(|e| {
+        error!(target: "api", "解析{}响应失败 - 错误: {}", context, e);
+        e
+    })
+}
+
+/// 读取响应的状态码和消息，忽略 data
+pub async fn read_status(
+    response: reqwest::Response,
+    context: &str,
+) -> Result<ApiResponse<()>, AppError> {
+    let response = read::<IgnoredAny>(response, context).await?;
+    Ok(ApiResponse {
+        code: response.code,
+        message: response.message,
+        data: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Account {
+        email: String,
+        tokens: Vec<Token>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Token {
+        value: String,
+    }
+
+    #[test]
+    fn test_detect_envelope() {
+        let v2: ApiResponse<Vec<i32>> =
+            decode(r#"{"code":200,"message":"ok","data":[1,2]}"#, DecodeMode::Strict).unwrap();
+        assert_eq!((v2.code, v2.data), (200, Some(vec![1, 2])));
+
+        let v1: ApiResponse<Vec<i32>> = decode(
+            r#"{"status":403,"msg":"无权限","code":"NO_AUTH","data":"ignored"}"#,
+            DecodeMode::Strict,
+        )
+        .unwrap();
+        assert_eq!((v1.code, v1.message.as_str(), v1.data), (403, "无权限", None));
+
+        assert!(decode::<()>(r#"{"message":"ok"}"#, DecodeMode::Strict).is_err());
+        let legacy = decode::<()>(r#"{"message":"ok"}"#, DecodeMode::Lenient).unwrap();
+        assert_eq!(legacy.code, 200);
+    }
+
+    #[test]
+    fn test_schema_drift() {
+        let body = r#"{"code":200,"message":"ok","data":{"email":"a@b.c","tokens":[{"value":"x"},{"value":1}]}}"#;
+        let error = decode::<Account>(body, DecodeMode::Strict).unwrap_err();
+        assert!(error.details.unwrap().starts_with("data.tokens[1].value: invalid type"));
+
+        let lenient = decode::<Account>(body, DecodeMode::Lenient).unwrap();
+        assert_eq!((lenient.code, lenient.data), (200, None));
+
+        // 任意 data 都可以忽略
+        let status = decode::<IgnoredAny>(body, DecodeMode::Strict).unwrap();
+        assert_eq!(status.code, 200);
+    }
+}
@@ -1,107 +1,26 @@
 use super::client::ApiClient;
-use super::interceptor::save_cursor_token_to_history;
+use super::compat::{self, VersionStatus};
+use super::decode;
+use super::failover::LineStatus;
+use super::inbound;
+use super::interceptor::{save_cursor_token_to_history, EndpointMetrics};
 use super::types::*;
 use crate::config;
-use crate::database::Database;
+use crate::database::{Database, KeySource, LineProbe, SecretsStatus, TaskRun};
+use crate::error::{AppError, ErrorCode};
+use crate::scheduler::{Scheduler, TaskInfo};
+use crate::settings::{self, ImportMode, ImportReport, SettingKey};
+use crate::shutdown;
+use crate::utils::http::{self, ProxyMode, ProxySettings};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::env;
-use tauri::State;
+use std::path::Path;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State};
 use tracing::{error, info};
 
-/// 通用API响应处理函数，处理成功和失败情况
-async fn handle_api_response<T: serde::de::DeserializeOwned>(
-    response: reqwest::Response, 
-    error_context: &str
-) -> Result<ApiResponse<T>, String> {
-    // 获取响应文本
-    let response_text = response.text().await.map_err(|e| {
-        error!(target: "api", "获取{}响应文本失败 - 错误: {}", error_context, e);
-        e.to_string()
-    })?;
-    
-    // 尝试解析为基本JSON格式以获取code和message
-    let api_response: serde_json::Value = serde_json::from_str(&response_text).map_err(|e| {
-        error!(target: "api", "解析{}响应JSON失败 - 错误: {}", error_context, e);
-        e.to_string()
-    })?;
-    println!("api_response: {:#?}", api_response);
-    // 提取code和message
-    let code = api_response["code"].as_i64().unwrap_or(200) as i32;
-    let message = api_response["message"].as_str().unwrap_or("未知错误").to_string();
-    
-    // 如果code不是200，直接返回错误响应
-    if code != 200 {
-        return Ok(ApiResponse {
-            code,
-            message,
-            data: None,
-        });
-    }
-    
-    // 成功情况，尝试解析为完整类型
-    match serde_json::from_str::<ApiResponse<T>>(&response_text) {
-        Ok(typed_response) => Ok(typed_response),
-        Err(e) => {
-            error!(target: "api", "解析{}响应为完整类型失败 - 错误: {}", error_context, e);
-            // 如果解析失败，尝试手动构造响应
-            Ok(ApiResponse {
-                code,
-                message,
-                data: None,
-            })
-        }
-    }
-}
-
-/// 兼容旧的API响应处理函数，用于还未迁移到新格式的API
-async fn handle_old_api_response<T: serde::de::DeserializeOwned>(
-    response: reqwest::Response, 
-    error_context: &str
-) -> Result<OldApiResponse<T>, String> {
-    // 获取响应文本
-    let response_text = response.text().await.map_err(|e| {
-        error!(target: "api", "获取{}响应文本失败 - 错误: {}", error_context, e);
-        e.to_string()
-    })?;
-    
-    // 尝试解析为基本JSON格式以获取status和msg
-    let api_response: serde_json::Value = serde_json::from_str(&response_text).map_err(|e| {
-        error!(target: "api", "解析{}响应JSON失败 - 错误: {}", error_context, e);
-        e.to_string()
-    })?;
-    println!("api_response: {:#?}", api_response);
-    // 提取status和msg
-    let status = api_response["status"].as_i64().unwrap_or(200) as i32;
-    let msg = api_response["msg"].as_str().unwrap_or("未知错误").to_string();
-    
-    // 如果status不是200，直接返回错误响应
-    if status != 200 {
-        return Ok(OldApiResponse {
-            status,
-            msg,
-            data: None,
-            code: api_response["code"].as_str().map(String::from),
-        });
-    }
-    
-    // 成功情况，尝试解析为完整类型
-    match serde_json::from_str::<OldApiResponse<T>>(&response_text) {
-        Ok(typed_response) => Ok(typed_response),
-        Err(e) => {
-            error!(target: "api", "解析{}响应为完整类型失败 - 错误: {}", error_context, e);
-            // 即使解析失败，依然返回成功状态但data为None
-            Ok(OldApiResponse {
-                status,
-                msg,
-                data: None,
-                code: api_response["code"].as_str().map(String::from),
-            })
-        }
-    }
-}
-
 // Bug报告请求结构
 #[derive(Serialize, Deserialize)]
 pub struct BugReportRequest {
@@ -123,7 +42,7 @@ pub struct BugReportRequest {
 pub async fn check_user(
     client: State<'_, ApiClient>,
     email: String,
-) -> Result<ApiResponse<serde_json::Value>, String> {
+) -> Result<ApiResponse<serde_json::Value>, AppError> {
     let response = client
         .post(format!("{}/checkUser", client.get_base_url()))
         .form(&[("email", email)])
@@ -131,10 +50,10 @@ pub async fn check_user(
         .await
         .map_err(|e| {
             error!(target: "api", "检查用户失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
 
-    handle_api_response(response, "检查用户").await
+    decode::read(response, "检查用户").await
 }
 
 /// 发送验证码
@@ -143,7 +62,7 @@ pub async fn send_code(
     client: State<'_, ApiClient>,
     email: String,
     r#type: String,
-) -> Result<ApiResponse<()>, String> {
+) -> Result<ApiResponse<()>, AppError> {
     let response = client
         .post(format!("{}/register/sendEmailCode", client.get_base_url()))
         .form(&[("email", email), ("type", r#type)])
@@ -151,10 +70,10 @@ pub async fn send_code(
         .await
         .map_err(|e| {
             error!(target: "api", "发送验证码失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
 
-    handle_api_response(response, "发送验证码").await
+    decode::read_status(response, "发送验证码").await
 }
 
 /// 注册用户
@@ -164,7 +83,7 @@ pub async fn register(
     email: String,
     code: String,
     password: String,
-) -> Result<ApiResponse<RegisterResponse>, String> {
+) -> Result<ApiResponse<RegisterResponse>, AppError> {
     let response = client
         .post(format!("{}/emailRegister", client.get_base_url()))
         .multipart([
@@ -177,10 +96,10 @@ pub async fn register(
         .await
         .map_err(|e| {
             error!(target: "api", "注册用户失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
 
-    handle_api_response(response, "注册用户").await
+    decode::read(response, "注册用户").await
 }
 
 /// 用户登录
@@ -190,7 +109,7 @@ pub async fn login(
     account: String,
     password: String,
     spread: String,
-) -> Result<ApiResponse<LoginResponse>, String> {
+) -> Result<ApiResponse<LoginResponse>, AppError> {
     let response = client
         .post(format!("{}/login", client.get_base_url()))
         .form(&[
@@ -202,63 +121,25 @@ pub async fn login(
         .await
         .map_err(|e| {
             error!(target: "api", "登录失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
 
-    handle_api_response(response, "登录").await
+    decode::read(response, "登录").await
 }
 
 /// 获取用户信息
 #[tauri::command]
-pub async fn get_user_info(client: State<'_, ApiClient>) -> Result<ApiResponse<UserInfo>, String> {
-    let url = client.get_base_url();
-    println!("url: {}", url);
+pub async fn get_user_info(client: State<'_, ApiClient>) -> Result<ApiResponse<UserInfo>, AppError> {
     let response = client
         .post(format!("{}/api-key/detail", client.get_base_url()))
         .send()
         .await
         .map_err(|e| {
             error!(target: "api", "获取用户信息失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
-    println!("response: {:#?}", response);
-    
-    // 获取响应文本并打印出来，以查看API实际返回的数据格式
-    let response_text = response.text().await.map_err(|e| {
-        error!(target: "api", "获取用户信息响应文本失败 - 错误: {}", e);
-        e.to_string()
-    })?;
-    info!(target: "api", "实际响应数据: {}", response_text);
-    
-    // 解析为JSON值并检查结构
-    let api_response: serde_json::Value = serde_json::from_str(&response_text).map_err(|e| {
-        error!(target: "api", "解析用户信息JSON失败 - 错误: {}", e);
-        e.to_string()
-    })?;
-    info!(target: "api", "解析后的JSON结构: {:?}", api_response);
-    
-    // 尝试手动构造一个简单的用户信息响应
-    let code = api_response["code"].as_i64().unwrap_or(200) as i32;
-    let message = api_response["message"].as_str().unwrap_or("未知错误").to_string();
-    
-    // 构造用户信息对象
-    // 使用默认值，因为我们还不知道实际的数据结构
-    let user_info = UserInfo {
-        total_count: 0,
-        used_count: 0,
-        expire_time: "".to_string(),
-        level: 0,
-        is_expired: false,
-        username: "".to_string(),
-        code_level: "".to_string(),
-        code_status: 0,
-    };
-    
-    Ok(ApiResponse {
-        code,
-        message,
-        data: Some(user_info),
-    })
+
+    decode::read(response, "获取用户信息").await
 }
 
 /// 激活账户
@@ -266,7 +147,7 @@ pub async fn get_user_info(client: State<'_, ApiClient>) -> Result<ApiResponse<U
 pub async fn activate(
     client: State<'_, ApiClient>,
     code: String,
-) -> Result<ApiResponse<()>, String> {
+) -> Result<ApiResponse<()>, AppError> {
     let response = client
         .post(format!("{}/user/activate", client.get_base_url()))
         .form(&[("code", code)])
@@ -274,10 +155,10 @@ pub async fn activate(
         .await
         .map_err(|e| {
             error!(target: "api", "激活账户失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
 
-    handle_api_response(response, "激活账户").await
+    decode::read_status(response, "激活账户").await
 }
 
 /// 修改密码
@@ -286,7 +167,7 @@ pub async fn change_password(
     client: State<'_, ApiClient>,
     old_password: String,
     new_password: String,
-) -> Result<ApiResponse<()>, String> {
+) -> Result<ApiResponse<()>, AppError> {
     let response = client
         .post(format!("{}/user/updatePassword", client.get_base_url()))
         .form(&[
@@ -298,10 +179,10 @@ pub async fn change_password(
         .await
         .map_err(|e| {
             error!(target: "api", "修改密码请求失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
     
-    handle_api_response(response, "修改密码").await
+    decode::read_status(response, "修改密码").await
 }
 
 /// 获取账户信息
@@ -311,7 +192,7 @@ pub async fn get_account(
     db: State<'_, Database>,
     account: Option<String>,
     usage_count: Option<String>,
-) -> Result<ApiResponse<AccountData>, String> {
+) -> Result<ApiResponse<AccountData>, AppError> {
     let mut url = format!("{}/cursor/account/get", client.get_base_url());
 
     let mut query_params = Vec::new();
@@ -328,11 +209,11 @@ pub async fn get_account(
 
     let response = client.get(&url).send().await.map_err(|e| {
         error!(target: "api", "获取账户信息请求失败 - 错误: {}", e);
-        e.to_string()
+        e
     })?;
 
     // 使用通用函数处理API响应
-    let api_response = handle_api_response::<AccountData>(response, "获取账户信息").await?;
+    let api_response = decode::read::<AccountData>(response, "获取账户信息").await?;
     info!(target: "api", "获取到账户信息响应: {:?}", api_response);
 
     // 如果获取成功且有账户信息，将token保存到历史记录
@@ -343,7 +224,7 @@ pub async fn get_account(
             use crate::cursor_reset::get_machine_ids;
             let machine_info = get_machine_ids(db.clone()).await.map_err(|e| {
                 error!(target: "api", "获取机器码失败 - 错误: {}", e);
-                e.to_string()
+                e
             })?;
             let machine_id = machine_info["machineId"]
                 .as_str()
@@ -372,13 +253,16 @@ pub async fn get_account(
 pub async fn get_usage(
     client: State<'_, ApiClient>,
     token: String,
-) -> Result<ApiResponse<CursorUsageInfo>, String> {
+) -> Result<ApiResponse<CursorUsageInfo>, AppError> {
     let user_id = config::CONFIG.read().unwrap().api.cursor_user_id.clone();
 
     // 如果token为空，返回数据库错误
     if token.is_empty() {
         error!(target: "api", "Cursor token为空，可能是数据库问题");
-        return Err("cursor_db_error".to_string());
+        return Err(AppError::new(
+            ErrorCode::CursorDbError,
+            "无法读取Cursor账户信息",
+        ));
     }
 
     // token可能包含了用户ID部分，需要分割并只使用token部分
@@ -400,14 +284,12 @@ pub async fn get_usage(
         .await
         .map_err(|e| {
             error!(target: "api", "获取Cursor使用情况请求失败 - 错误: {}", e);
-            // 网络相关错误
-            "cursor_network_error".to_string()
+            AppError::new(ErrorCode::CursorNetworkError, "请求Cursor服务失败").with_details(e)
         })?;
 
     let response_text = response.text().await.map_err(|e| {
         error!(target: "api", "获取Cursor使用情况响应文本失败 - 错误: {}", e);
-        // 网络相关错误
-        "cursor_network_error".to_string()
+        AppError::new(ErrorCode::CursorNetworkError, "请求Cursor服务失败").with_details(e)
     })?;
 
     match serde_json::from_str::<CursorUsageInfo>(&response_text) {
@@ -418,8 +300,7 @@ pub async fn get_usage(
         }),
         Err(e) => {
             error!(target: "api", "解析Cursor使用情况失败 - 响应: {}, 错误: {}", response_text, e);
-            // 数据格式错误 
-            Err("cursor_data_error".to_string())
+            Err(AppError::new(ErrorCode::CursorDataError, "Cursor使用情况数据格式错误").with_details(e))
         }
     }
 }
@@ -428,20 +309,17 @@ pub async fn get_usage(
 #[tauri::command]
 pub async fn get_public_info(
     client: State<'_, ApiClient>,
-) -> Result<ApiResponse<PublicInfo>, String> {
+) -> Result<ApiResponse<PublicInfo>, AppError> {
     let response = client
         .get(format!("{}/public/info", client.get_base_url()))
         .send()
         .await
         .map_err(|e| {
             error!(target: "api", "获取公告信息失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
 
-    response.json().await.map_err(|e| {
-        error!(target: "api", "解析公告信息响应失败 - 错误: {}", e);
-        e.to_string()
-    })
+    decode::read(response, "获取公告信息").await
 }
 
 /// 重置密码
@@ -451,7 +329,7 @@ pub async fn reset_password(
     email: String,
     code: String,
     password: String,
-) -> Result<ApiResponse<()>, String> {
+) -> Result<ApiResponse<()>, AppError> {
     let response = client
         .post(format!("{}/emailResetPassword", client.get_base_url()))
         .form(&[("email", email), ("code", code), ("password", password)])
@@ -459,10 +337,10 @@ pub async fn reset_password(
         .await
         .map_err(|e| {
             error!(target: "api", "重置密码请求失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
 
-    handle_api_response(response, "重置密码").await
+    decode::read_status(response, "重置密码").await
 }
 
 /// 报告错误
@@ -474,7 +352,7 @@ pub async fn report_bug(
     api_key: Option<String>,
     screenshot_urls: Option<Vec<String>>,
     cursor_version: Option<String>,
-) -> Result<ApiResponse<()>, String> {
+) -> Result<ApiResponse<()>, AppError> {
     let app_version = env::var("CARGO_PKG_VERSION").unwrap_or_else(|_| "unknown".to_string());
     let os_version = os_info::get().to_string();
     let device_model = "PC".to_string();
@@ -499,21 +377,18 @@ pub async fn report_bug(
         .await
         .map_err(|e| {
             error!(target: "api", "提交错误报告失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
 
-    response.json().await.map_err(|e| {
-        error!(target: "api", "解析错误报告响应失败 - 错误: {}", e);
-        e.to_string()
-    })
+    decode::read_status(response, "提交错误报告").await
 }
 
 /// 用户登出
 #[tauri::command]
-pub async fn logout(db: State<'_, Database>) -> Result<ApiResponse<()>, String> {
-    db.delete_item("user.info.token").map_err(|e| {
+pub async fn logout(client: State<'_, ApiClient>) -> Result<ApiResponse<()>, AppError> {
+    client.tokens().clear().await.map_err(|e| {
         error!(target: "api", "删除用户token失败 - 错误: {}", e);
-        e.to_string()
+        AppError::database(e)
     })?;
 
     Ok(ApiResponse {
@@ -529,8 +404,16 @@ pub async fn set_user_data(
     db: State<'_, Database>,
     key: String,
     value: String,
-) -> Result<ApiResponse<()>, String> {
-    match db.set_item(&key, &value) {
+) -> Result<ApiResponse<()>, AppError> {
+    // 已知设置项需要经过校验并通知订阅者
+    let result = match SettingKey::from_db_key(&key) {
+        // 线路配置决定令牌发往哪里，只能由签名校验通过的远程配置更新
+        Some(SettingKey::InboundConfig) => Err(AppError::invalid_input("线路配置不能直接修改")),
+        Some(setting) => settings::set(&db, setting, &value).await.map_err(AppError::from),
+        None => db.set_item(&key, &value).await.map_err(AppError::from),
+    };
+
+    match result {
         Ok(_) => Ok(ApiResponse {
             code: 200,
             message : "成功设置用户数据".to_string(),
@@ -539,7 +422,7 @@ pub async fn set_user_data(
         }),
         Err(e) => {
             error!(target: "api", "设置用户数据失败 - 键: {}, 错误: {}", key, e);
-            Err(e.to_string())
+            Err(e)
         }
     }
 }
@@ -549,8 +432,8 @@ pub async fn set_user_data(
 pub async fn get_user_data(
     db: State<'_, Database>,
     key: String,
-) -> Result<ApiResponse<serde_json::Value>, String> {
-    match db.get_item(&key) {
+) -> Result<ApiResponse<serde_json::Value>, AppError> {
+    match db.get_item(&key).await {
         Ok(value) => Ok(ApiResponse {
             code: 200,
             message: "成功获取用户数据".to_string(),
@@ -558,7 +441,7 @@ pub async fn get_user_data(
         }),
         Err(e) => {
             error!(target: "api", "获取用户数据失败 - 键: {}, 错误: {}", key, e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
@@ -568,8 +451,14 @@ pub async fn get_user_data(
 pub async fn del_user_data(
     db: State<'_, Database>,
     key: String,
-) -> Result<ApiResponse<()>, String> {
-    match db.delete_item(&key) {
+) -> Result<ApiResponse<()>, AppError> {
+    let result = match SettingKey::from_db_key(&key) {
+        Some(SettingKey::InboundConfig) => Err(AppError::invalid_input("线路配置不能直接修改")),
+        Some(setting) => settings::remove(&db, setting).await.map_err(AppError::from),
+        None => db.delete_item(&key).await.map_err(AppError::from),
+    };
+
+    match result {
         Ok(_) => Ok(ApiResponse {
             code: 200,
             message: "成功删除用户数据".to_string(),
@@ -577,16 +466,427 @@ pub async fn del_user_data(
         }),
         Err(e) => {
             error!(target: "api", "删除用户数据失败 - 键: {}, 错误: {}", key, e);
-            Err(e.to_string())
+            Err(e)
         }
     }
 }
 
+/// 获取数据加密密钥状态
+#[tauri::command]
+pub async fn get_secrets_status(
+    db: State<'_, Database>,
+) -> Result<ApiResponse<SecretsStatus>, AppError> {
+    Ok(ApiResponse {
+        code: 200,
+        message: "获取加密状态成功".to_string(),
+        data: Some(db.secrets_status()),
+    })
+}
+
+/// 使用口令解锁加密数据
+#[tauri::command]
+pub async fn unlock_secrets(
+    db: State<'_, Database>,
+    passphrase: String,
+) -> Result<ApiResponse<()>, AppError> {
+    db.unlock_secrets(&passphrase).await.map_err(|e| {
+        error!(target: "api", "解锁加密数据失败 - 错误: {}", e);
+        AppError::from(e)
+    })?;
+    // 未解锁时代理设置不可用，解锁后重新应用
+    match settings::proxy(&db).await {
+        Ok(proxy) => http::set_proxy(proxy),
+        Err(e) => error!(target: "api", "解锁后读取代理设置失败 - 错误: {}", e),
+    }
+
+    Ok(ApiResponse {
+        code: 200,
+        message: "解锁成功".to_string(),
+        data: None,
+    })
+}
+
+/// 轮换数据加密密钥，传入口令时改为由口令派生密钥
+#[tauri::command]
+pub async fn rotate_secrets_key(
+    db: State<'_, Database>,
+    passphrase: Option<String>,
+) -> Result<ApiResponse<()>, AppError> {
+    let source = match passphrase {
+        Some(passphrase) => KeySource::Passphrase(passphrase),
+        None => KeySource::KeyFile,
+    };
+    db.rotate_secrets_key(source).await.map_err(|e| {
+        error!(target: "api", "轮换数据加密密钥失败 - 错误: {}", e);
+        AppError::from(e)
+    })?;
+
+    Ok(ApiResponse {
+        code: 200,
+        message: "密钥轮换成功".to_string(),
+        data: None,
+    })
+}
+
+/// 导出设置到文件，不包含敏感数据
+#[tauri::command]
+pub async fn export_settings(
+    db: State<'_, Database>,
+    path: String,
+) -> Result<ApiResponse<()>, AppError> {
+    settings::export(&db, Path::new(&path)).await.map_err(|e| {
+        error!(target: "api", "导出设置失败 - 路径: {}, 错误: {}", path, e);
+        AppError::filesystem(e)
+    })?;
+
+    Ok(ApiResponse {
+        code: 200,
+        message: "导出设置成功".to_string(),
+        data: None,
+    })
+}
+
+/// 从文件导入设置，dry_run 为 true 时只返回变更预览
+#[tauri::command]
+pub async fn import_settings(
+    db: State<'_, Database>,
+    path: String,
+    mode: ImportMode,
+    dry_run: bool,
+) -> Result<ApiResponse<ImportReport>, AppError> {
+    let report = settings::import(&db, Path::new(&path), mode, dry_run)
+        .await
+        .map_err(|e| {
+            error!(target: "api", "导入设置失败 - 路径: {}, 错误: {}", path, e);
+            AppError::invalid_input(e)
+        })?;
+
+    Ok(ApiResponse {
+        code: 200,
+        message: if dry_run {
+            "设置变更预览".to_string()
+        } else {
+            "导入设置成功".to_string()
+        },
+        data: Some(report),
+    })
+}
+
+/// 获取生效的配置及每一项的来源
+#[tauri::command]
+pub async fn get_effective_config() -> Result<ApiResponse<config::EffectiveConfig>, AppError> {
+    Ok(ApiResponse {
+        code: 200,
+        message: "获取配置成功".to_string(),
+        data: Some(config::get_effective_config()),
+    })
+}
+
+/// 获取各接口的请求统计
+#[tauri::command]
+pub async fn get_request_metrics(
+    client: State<'_, ApiClient>,
+) -> Result<ApiResponse<Vec<EndpointMetrics>>, AppError> {
+    Ok(ApiResponse {
+        code: 200,
+        message: "获取请求统计成功".to_string(),
+        data: Some(client.metrics()),
+    })
+}
+
+/// 获取客户端版本状态，低于最低版本时前端需要强制更新
+#[tauri::command]
+pub async fn get_version_status(app: AppHandle) -> Result<ApiResponse<VersionStatus>, AppError> {
+    Ok(ApiResponse {
+        code: 200,
+        message: "获取版本状态成功".to_string(),
+        data: Some(compat::version_status(&app).await),
+    })
+}
+
+/// 获取所有线路及其健康状态
+#[tauri::command]
+pub async fn get_inbound_lines(
+    client: State<'_, ApiClient>,
+    db: State<'_, Database>,
+) -> Result<ApiResponse<Vec<LineStatus>>, AppError> {
+    Ok(ApiResponse {
+        code: 200,
+        message: "获取线路成功".to_string(),
+        data: Some(inbound::line_statuses(&db, client.lines()).await),
+    })
+}
+
+/// 固定使用指定线路，index 为空时取消固定并恢复自动切换
+#[tauri::command]
+pub async fn pin_inbound_line(
+    db: State<'_, Database>,
+    index: Option<usize>,
+) -> Result<ApiResponse<()>, AppError> {
+    inbound::pin_line(&db, index).await.map_err(|e| {
+        error!(target: "api", "固定线路失败 - 错误: {}", e);
+        AppError::from(e)
+    })?;
+    Ok(ApiResponse {
+        code: 200,
+        message: "设置线路成功".to_string(),
+        data: None,
+    })
+}
+
+/// 获取最近一次线路测速结果
+#[tauri::command]
+pub async fn get_inbound_probe_results(
+    db: State<'_, Database>,
+) -> Result<ApiResponse<Vec<LineProbe>>, AppError> {
+    let probes = db.get_line_probes().await?;
+    Ok(ApiResponse {
+        code: 200,
+        message: "获取测速结果成功".to_string(),
+        data: Some(probes),
+    })
+}
+
+/// 立即测速所有线路，测速过程中发送 inbound-probe-progress 事件
+#[tauri::command]
+pub async fn probe_inbound_lines(app: AppHandle) -> Result<ApiResponse<Vec<LineProbe>>, AppError> {
+    let probes = inbound::probe_lines(&app).await;
+    Ok(ApiResponse {
+        code: 200,
+        message: "线路测速完成".to_string(),
+        data: Some(probes),
+    })
+}
+
+/// 获取网络代理设置
+#[tauri::command]
+pub async fn get_proxy_settings(
+    db: State<'_, Database>,
+) -> Result<ApiResponse<ProxySettings>, AppError> {
+    Ok(ApiResponse {
+        code: 200,
+        message: "获取代理设置成功".to_string(),
+        data: Some(settings::proxy(&db).await?),
+    })
+}
+
+/// 保存网络代理设置，之后的请求立即使用新设置
+#[tauri::command]
+pub async fn set_proxy_settings(
+    db: State<'_, Database>,
+    proxy: ProxySettings,
+) -> Result<ApiResponse<()>, AppError> {
+    settings::set_proxy(&db, &proxy).await.map_err(|e| {
+        error!(target: "api", "保存代理设置失败 - 错误: {}", e);
+        AppError::from(e)
+    })?;
+    info!(target: "api", "代理设置已更新 - 模式: {:?}", proxy.mode);
+
+    Ok(ApiResponse {
+        code: 200,
+        message: "保存代理设置成功".to_string(),
+        data: None,
+    })
+}
+
+/// 通过代理请求当前线路的版本接口，proxy 为空时测试已保存的设置
+#[tauri::command]
+pub async fn test_proxy_connection(
+    client: State<'_, ApiClient>,
+    db: State<'_, Database>,
+    proxy: Option<ProxySettings>,
+) -> Result<ApiResponse<ProxyTestResult>, AppError> {
+    let proxy = match proxy {
+        Some(proxy) => proxy,
+        None => settings::proxy(&db).await?,
+    };
+    proxy.validate().map_err(AppError::invalid_input)?;
+    let url = format!("{}/version", client.get_base_url());
+    let client = http::build_client_with(
+        config::get_request_timeout(),
+        &config::get_api_config(),
+        &proxy,
+    )
+    .map_err(|e| AppError::network("创建HTTP客户端失败").with_details(e))?;
+
+    let start = Instant::now();
+    let response = client.get(&url).send().await.map_err(|e| {
+        error!(target: "api", "代理连接测试失败 - URL: {}, 错误: {}", url, e);
+        AppError::network("代理连接测试失败").with_details(e)
+    })?;
+
+    Ok(ApiResponse {
+        code: 200,
+        message: "代理连接测试完成".to_string(),
+        data: Some(ProxyTestResult {
+            url,
+            proxy: (proxy.mode == ProxyMode::Manual).then(|| proxy.url()),
+            status: response.status().as_u16(),
+            latency_ms: start.elapsed().as_millis() as u64,
+        }),
+    })
+}
+
+/// 获取所有定时任务的运行状态
+#[tauri::command]
+pub async fn get_scheduled_tasks(
+    scheduler: State<'_, Scheduler>,
+) -> Result<ApiResponse<Vec<TaskInfo>>, AppError> {
+    Ok(ApiResponse {
+        code: 200,
+        message: "获取任务成功".to_string(),
+        data: Some(scheduler.list().await),
+    })
+}
+
+/// 暂停定时任务，重启后保持暂停
+#[tauri::command]
+pub async fn pause_scheduled_task(
+    scheduler: State<'_, Scheduler>,
+    task_id: String,
+) -> Result<ApiResponse<TaskInfo>, AppError> {
+    let task = scheduler.pause(&task_id).await.map_err(|e| {
+        error!(target: "api", "暂停任务失败 - 任务: {}, 错误: {}", task_id, e);
+        AppError::from(e)
+    })?;
+    Ok(ApiResponse {
+        code: 200,
+        message: "任务已暂停".to_string(),
+        data: Some(task),
+    })
+}
+
+/// 恢复已暂停的定时任务
+#[tauri::command]
+pub async fn resume_scheduled_task(
+    scheduler: State<'_, Scheduler>,
+    task_id: String,
+) -> Result<ApiResponse<TaskInfo>, AppError> {
+    let task = scheduler.resume(&task_id).await.map_err(|e| {
+        error!(target: "api", "恢复任务失败 - 任务: {}, 错误: {}", task_id, e);
+        AppError::from(e)
+    })?;
+    Ok(ApiResponse {
+        code: 200,
+        message: "任务已恢复".to_string(),
+        data: Some(task),
+    })
+}
+
+/// 修改定时任务的执行间隔(秒)
+#[tauri::command]
+pub async fn reschedule_scheduled_task(
+    scheduler: State<'_, Scheduler>,
+    task_id: String,
+    interval_secs: u64,
+) -> Result<ApiResponse<TaskInfo>, AppError> {
+    let task = scheduler
+        .reschedule(&task_id, interval_secs)
+        .await
+        .map_err(|e| {
+            error!(target: "api", "修改任务间隔失败 - 任务: {}, 错误: {}", task_id, e);
+            AppError::from(e)
+        })?;
+    Ok(ApiResponse {
+        code: 200,
+        message: "任务间隔已更新".to_string(),
+        data: Some(task),
+    })
+}
+
+/// 设置定时任务的计划，支持 cron 表达式、daily HH:MM 和 cycle，为空时恢复默认间隔
+#[tauri::command]
+pub async fn set_task_schedule(
+    scheduler: State<'_, Scheduler>,
+    task_id: String,
+    expression: Option<String>,
+) -> Result<ApiResponse<TaskInfo>, AppError> {
+    let expression = expression.filter(|expr| !expr.trim().is_empty());
+    let task = scheduler
+        .set_schedule(&task_id, expression.as_deref())
+        .await
+        .map_err(|e| {
+            error!(target: "api", "设置任务计划失败 - 任务: {}, 错误: {}", task_id, e);
+            AppError::from(e)
+        })?;
+    Ok(ApiResponse {
+        code: 200,
+        message: "任务计划已更新".to_string(),
+        data: Some(task),
+    })
+}
+
+/// 立即执行一次定时任务，任务正在执行时返回错误
+#[tauri::command]
+pub async fn run_scheduled_task(
+    scheduler: State<'_, Scheduler>,
+    task_id: String,
+) -> Result<ApiResponse<()>, AppError> {
+    scheduler.run_now(&task_id).await.map_err(|e| {
+        error!(target: "api", "执行任务失败 - 任务: {}, 错误: {}", task_id, e);
+        AppError::from(e)
+    })?;
+    Ok(ApiResponse {
+        code: 200,
+        message: "任务已开始执行".to_string(),
+        data: None,
+    })
+}
+
+/// 获取定时任务的运行记录，新记录在前
+#[tauri::command]
+pub async fn get_task_runs(
+    db: State<'_, Database>,
+    task_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<ApiResponse<Vec<TaskRun>>, AppError> {
+    let runs = db
+        .get_task_runs(task_id.as_deref(), limit.unwrap_or(50))
+        .await
+        .map_err(AppError::from)?;
+    Ok(ApiResponse {
+        code: 200,
+        message: "获取运行记录成功".to_string(),
+        data: Some(runs),
+    })
+}
+
+/// 停止后台任务并关闭数据库后重启应用，用于更新和切换线路后重启
+#[tauri::command]
+pub async fn restart_app(app: AppHandle) -> Result<ApiResponse<()>, AppError> {
+    info!(target: "api", "重启应用");
+    shutdown::restart(&app);
+    Ok(ApiResponse {
+        code: 200,
+        message: "正在重启".to_string(),
+        data: None,
+    })
+}
+
+/// 重新加载配置文件和环境变量
+#[tauri::command]
+pub async fn reload_config(app: AppHandle) -> Result<ApiResponse<config::EffectiveConfig>, AppError> {
+    let app_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::filesystem("无法获取应用数据目录").with_details(e))?;
+    config::reload_config(&app_dir).map_err(|e| {
+        error!(target: "api", "重新加载配置失败 - 错误: {}", e);
+        AppError::invalid_input(e)
+    })?;
+
+    Ok(ApiResponse {
+        code: 200,
+        message: "配置已重新加载".to_string(),
+        data: Some(config::get_effective_config()),
+    })
+}
+
 /// 获取公告列表
 #[tauri::command]
 pub async fn get_article_list(
     client: State<'_, ApiClient>,
-) -> Result<ApiResponse<Vec<Article>>, String> {
+) -> Result<ApiResponse<Vec<Article>>, AppError> {
     // 获取公告数据
     let result = fetch_article_list(&client).await;
 
@@ -609,43 +909,23 @@ pub async fn get_article_list(
 }
 
 /// 内部函数：获取公告列表数据
-async fn fetch_article_list(client: &ApiClient) -> Result<Vec<Article>, String> {
+async fn fetch_article_list(client: &ApiClient) -> Result<Vec<Article>, AppError> {
     let response = client
         .get(format!("{}/article/list/1", client.get_base_url()))
         .send()
         .await
         .map_err(|e| {
             error!(target: "api", "获取公告列表请求失败 - 错误: {}", e);
-            e.to_string()
+            e
         })?;
 
-    let response_json: serde_json::Value = response.json().await.map_err(|e| {
-        error!(target: "api", "解析公告列表响应失败 - 错误: {}", e);
-        e.to_string()
-    })?;
-
-    // 检查状态码
-    let status = response_json["status"].as_i64().unwrap_or(0);
-    if status != 200 {
-        let error_msg = "获取公告失败".to_string();
-        error!(target: "api", "公告列表状态码错误 - 状态码: {}", status);
-        return Err(error_msg);
-    }
-
-    // 提取所需字段
-    let empty_vec = Vec::new();
-    let data = response_json["data"].as_array().unwrap_or(&empty_vec);
-    let mut articles = Vec::new();
-
-    for item in data {
-        let id = item["id"].as_i64().unwrap_or(0) as i32;
-        let title = item["title"].as_str().unwrap_or("").to_string();
-        let content = item["content"].as_str().unwrap_or("").to_string();
-
-        articles.push(Article { id, title, content });
+    let response = decode::read::<Vec<Article>>(response, "获取公告列表").await?;
+    if response.code != 200 {
+        error!(target: "api", "公告列表状态码错误 - 状态码: {}", response.code);
+        return Err(AppError::invalid_response("获取公告失败").with_details(response.code));
     }
 
-    Ok(articles)
+    Ok(response.data.unwrap_or_default())
 }
 
 /// 标记文章为已读
@@ -653,9 +933,9 @@ async fn fetch_article_list(client: &ApiClient) -> Result<Vec<Article>, String>
 pub async fn mark_article_read(
     db: State<'_, Database>,
     article_id: i32,
-) -> Result<ApiResponse<()>, String> {
+) -> Result<ApiResponse<()>, AppError> {
     // 获取已读ID集合
-    let read_ids = match db.get_item("system.articles") {
+    let read_ids = match db.get_item("system.articles").await {
         Ok(Some(data)) => serde_json::from_str::<Vec<i32>>(&data).unwrap_or_default(),
         Ok(None) => Vec::new(),
         Err(e) => {
@@ -672,11 +952,11 @@ pub async fn mark_article_read(
         // 保存更新后的已读ID列表
         let json_data = serde_json::to_string(&updated_ids).map_err(|e| {
             error!(target: "api", "序列化已读文章ID列表失败 - 错误: {}", e);
-            e.to_string()
+            AppError::from(e)
         })?;
-        db.set_item("system.articles", &json_data).map_err(|e| {
+        db.set_item("system.articles", &json_data).await.map_err(|e| {
             error!(target: "api", "保存已读文章ID列表失败 - 错误: {}", e);
-            e.to_string()
+            AppError::from(e)
         })?;
     }
 
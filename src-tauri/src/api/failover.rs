@@ -0,0 +1,217 @@
+//! 线路故障转移：根据请求结果记录线路健康状态，连续失败时切换到下一条健康线路
+
+use super::inbound::InboundItem;
+use reqwest::Url;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+// 连续失败多少次后判定线路不健康
+const FAILURE_THRESHOLD: u32 = 3;
+
+/// 线路健康状态
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LineHealth {
+    pub consecutive_failures: u32,
+    pub successes: u64,
+    pub failures: u64,
+    // 最近一次测速的延迟(毫秒)
+    pub latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl LineHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures < FAILURE_THRESHOLD
+    }
+}
+
+/// 线路及其健康状态，供前端展示
+#[derive(Debug, Clone, Serialize)]
+pub struct LineStatus {
+    pub index: usize,
+    pub name: String,
+    pub url: String,
+    pub current: bool,
+    pub pinned: bool,
+    pub healthy: bool,
+    pub health: LineHealth,
+}
+
+/// 按线路地址记录健康状态，线路配置更新后不会错位
+#[derive(Default)]
+pub struct LineTracker {
+    lines: Mutex<HashMap<String, LineHealth>>,
+    // 当前线路配置中的线路，用于判断请求属于哪条线路
+    items: RwLock<Vec<InboundItem>>,
+    // 当前线路索引，与当前线路设置保持同步，请求时不需要读取数据库
+    current: AtomicUsize,
+}
+
+impl LineTracker {
+    /// 线路配置变更后更新线路列表
+    pub fn set_lines(&self, items: Vec<InboundItem>) {
+        *self.items.write().unwrap() = items;
+    }
+
+    /// 当前线路设置变更后更新当前线路索引
+    pub fn set_current(&self, index: usize) {
+        self.current.store(index, Ordering::Relaxed);
+    }
+
+    /// 当前线路，索引超出线路列表时返回 None
+    pub fn current_line(&self) -> Option<InboundItem> {
+        let index = self.current.load(Ordering::Relaxed);
+        self.items.read().unwrap().get(index).cloned()
+    }
+
+    /// 查找请求地址所属的线路，不属于任何线路时返回 None
+    pub fn line_for(&self, url: &str) -> Option<InboundItem> {
+        let url = Url::parse(url).ok()?;
+        self.items
+            .read()
+            .unwrap()
+            .iter()
+            .find(|item| belongs_to(&url, &item.url))
+            .cloned()
+    }
+
+    /// 记录一次成功的请求
+    pub fn record_success(&self, url: &str) {
+        let mut lines = self.lines.lock().unwrap();
+        let health = lines.entry(url.to_string()).or_default();
+        health.successes += 1;
+        health.consecutive_failures = 0;
+    }
+
+    /// 记录一次失败的请求，线路刚变为不健康时返回 true
+    pub fn record_failure(&self, url: &str, error: &str) -> bool {
+        let mut lines = self.lines.lock().unwrap();
+        let health = lines.entry(url.to_string()).or_default();
+        health.failures += 1;
+        health.consecutive_failures += 1;
+        health.last_error = Some(error.to_string());
+        health.consecutive_failures == FAILURE_THRESHOLD
+    }
+
+    /// 记录测速结果，测速成功视为线路恢复
+    pub fn record_probe(&self, url: &str, latency: Option<Duration>) {
+        let mut lines = self.lines.lock().unwrap();
+        let health = lines.entry(url.to_string()).or_default();
+        match latency {
+            Some(latency) => {
+                health.latency_ms = Some(latency.as_millis() as u64);
+                health.consecutive_failures = 0;
+            }
+            None => {
+                health.latency_ms = None;
+                health.failures += 1;
+                // 测速失败直接判定为不健康
+                health.consecutive_failures = health.consecutive_failures.max(FAILURE_THRESHOLD);
+                health.last_error = Some("测速失败".to_string());
+            }
+        }
+    }
+
+    pub fn health(&self, url: &str) -> LineHealth {
+        self.lines.lock().unwrap().get(url).cloned().unwrap_or_default()
+    }
+
+    pub fn is_healthy(&self, url: &str) -> bool {
+        self.health(url).is_healthy()
+    }
+}
+
+// 请求地址是否属于线路：协议、主机和端口相同，并且路径按路径段以线路地址的路径开头
+fn belongs_to(url: &Url, line: &str) -> bool {
+    let line = match Url::parse(line) {
+        Ok(line) => line,
+        Err(_) => return false,
+    };
+    if url.scheme() != line.scheme()
+        || url.host_str() != line.host_str()
+        || url.port_or_known_default() != line.port_or_known_default()
+    {
+        return false;
+    }
+    let mut segments = url.path().split('/').filter(|s| !s.is_empty());
+    line.path()
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .all(|prefix| segments.next() == Some(prefix))
+}
+
+/// 从 current 之后依次查找第一条健康线路，没有其他健康线路时返回 None
+pub fn next_healthy(lines: &[InboundItem], current: usize, tracker: &LineTracker) -> Option<usize> {
+    (1..lines.len())
+        .map(|offset| (current + offset) % lines.len())
+        .find(|&index| tracker.is_healthy(&lines[index].url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(urls: &[&str]) -> Vec<InboundItem> {
+        urls.iter()
+            .map(|url| InboundItem {
+                name: url.to_string(),
+                url: url.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_failure_threshold() {
+        let tracker = LineTracker::default();
+        assert!(!tracker.record_failure("a", "timeout"));
+        assert!(!tracker.record_failure("a", "timeout"));
+        assert!(tracker.record_failure("a", "timeout"));
+        assert!(!tracker.is_healthy("a"));
+        // 已经不健康后不再重复触发
+        assert!(!tracker.record_failure("a", "timeout"));
+
+        tracker.record_success("a");
+        assert!(tracker.is_healthy("a"));
+    }
+
+    #[test]
+    fn test_line_for() {
+        let tracker = LineTracker::default();
+        assert_eq!(tracker.line_for("https://a.example.com/api/user"), None);
+
+        tracker.set_lines(lines(&["https://a.example.com", "https://b.example.com"]));
+        let line = tracker.line_for("https://b.example.com/api/user").unwrap();
+        assert_eq!(line.url, "https://b.example.com");
+        assert_eq!(tracker.line_for("https://api2.cursor.sh/auth/usage"), None);
+        assert_eq!(tracker.line_for("https://a.example.com.evil.net/api/user"), None);
+        assert_eq!(tracker.line_for("https://a.example.comx/api/user"), None);
+        assert_eq!(tracker.line_for("http://a.example.com/api/user"), None);
+
+        tracker.set_lines(lines(&["https://c.example.com:8443/v1"]));
+        assert!(tracker.line_for("https://c.example.com:8443/v1/api/user").is_some());
+        assert_eq!(tracker.line_for("https://c.example.com:8443/v10/api/user"), None);
+        assert_eq!(tracker.line_for("https://c.example.com/v1/api/user"), None);
+    }
+
+    #[test]
+    fn test_next_healthy() {
+        let tracker = LineTracker::default();
+        let lines = lines(&["a", "b", "c"]);
+        assert_eq!(next_healthy(&lines, 0, &tracker), Some(1));
+
+        tracker.record_probe("b", None);
+        assert_eq!(next_healthy(&lines, 0, &tracker), Some(2));
+        assert_eq!(next_healthy(&lines, 2, &tracker), Some(0));
+
+        tracker.record_probe("a", None);
+        tracker.record_probe("c", None);
+        assert_eq!(next_healthy(&lines, 0, &tracker), None);
+
+        tracker.record_probe("b", Some(Duration::from_millis(20)));
+        assert_eq!(next_healthy(&lines, 0, &tracker), Some(1));
+        assert_eq!(next_healthy(&lines[..1], 0, &tracker), None);
+    }
+}
@@ -1,71 +1,315 @@
+use super::client::ApiClient;
+use super::compat::SUPPORTED_SCHEMA;
+use super::failover::{next_healthy, LineStatus, LineTracker};
+use super::probe::{self, PROBE_PROGRESS_EVENT};
 use crate::config;
-use crate::database::Database;
-use reqwest::Client;
+use crate::database::{Database, LineProbe};
+use crate::settings::{self, SettingKey, SettingsError};
+use crate::utils::{http, signature};
+use chrono::Utc;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::time::{Duration, Instant};
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
 use tracing::{error, info, warn};
 
-// 线路配置数据结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 线路配置变更事件
+pub const INBOUND_CONFIG_CHANGED_EVENT: &str = "inbound-config-changed";
+
+// 远程配置数据结构，未知字段会被忽略，新增字段必须有默认值以兼容旧版配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct InboundConfig {
+    // 配置格式版本，旧版配置没有该字段时为 0
+    #[serde(default)]
+    pub schema: u32,
     pub inbound: Vec<InboundItem>,
+    // 最低支持的客户端版本，低于该版本必须更新
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<String>,
+    // 推荐的客户端版本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_version: Option<String>,
+    // 功能开关
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub features: BTreeMap<String, bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InboundItem {
     pub name: String,
     pub url: String,
 }
 
-// 带延迟信息的线路条目
-#[derive(Debug, Clone)]
-struct InboundItemWithLatency {
-    index: usize,
-    item: InboundItem,
-    latency: Option<Duration>,
+// 上一次校验通过的远程配置原文
+const VERIFIED_CONFIG_KEY: &str = "system.inbound.verified";
+
+// 旧版本保存的线路配置已经沿用过一次，之后不再信任
+const LEGACY_MIGRATED_KEY: &str = "system.inbound.legacy_migrated";
+
+/// 签名校验通过的远程配置原文，远程获取失败时作为回退
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifiedConfig {
+    pub payload: String,
+    pub signature: String,
+    // 用于条件请求的响应头
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    // 获取时间(Unix 时间戳，秒)
+    #[serde(default)]
+    pub fetched_at: i64,
 }
 
-/// 从远程获取线路配置
-pub async fn fetch_inbound_config() -> Result<InboundConfig, String> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(5))
-        .build()
-        .map_err(|e| {
-            error!(target: "inbound", "创建HTTP客户端失败: {}", e);
-            format!("创建HTTP客户端失败: {}", e)
-        })?;
+impl VerifiedConfig {
+    /// 校验签名并解析线路配置
+    pub fn parse(&self) -> Result<InboundConfig, String> {
+        signature::verify_remote_config(self.payload.as_bytes(), &self.signature)
+            .map_err(|e| format!("线路配置签名无效: {}", e))?;
+
+        let config = serde_json::from_str::<InboundConfig>(&self.payload)
+            .map_err(|e| format!("解析线路配置失败: {}", e))?;
+        if config.inbound.is_empty() {
+            return Err("线路配置为空".to_string());
+        }
+        if config.schema > SUPPORTED_SCHEMA {
+            info!(target: "inbound", "远程配置格式版本 {} 高于当前支持的版本 {}，将忽略不支持的字段", config.schema, SUPPORTED_SCHEMA);
+        }
+        Ok(config)
+    }
+
+    /// 缓存是否仍在有效期内
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        let age = Utc::now().timestamp() - self.fetched_at;
+        age >= 0 && (age as u64) < ttl.as_secs()
+    }
+}
+
+// 下载文本内容
+async fn download(client: &Client, url: &str) -> Result<String, String> {
+    let response = client.get(url).send().await.map_err(|e| {
+        error!(target: "inbound", "请求线路配置失败 - URL: {}, 错误: {}", url, e);
+        format!("请求线路配置失败: {}", e)
+    })?;
+    if !response.status().is_success() {
+        error!(target: "inbound", "请求线路配置失败 - URL: {}, 状态码: {}", url, response.status());
+        return Err(format!("请求线路配置失败: {}", response.status()));
+    }
+    response.text().await.map_err(|e| {
+        error!(target: "inbound", "读取线路配置失败 - URL: {}, 错误: {}", url, e);
+        format!("读取线路配置失败: {}", e)
+    })
+}
+
+/// 从远程获取线路配置，有缓存时发送条件请求
+///
+/// 远程配置未变化时返回 None，签名校验不通过时拒绝使用
+pub async fn fetch_inbound_config(
+    cached: Option<&VerifiedConfig>,
+) -> Result<Option<VerifiedConfig>, String> {
+    let client = http::build_client(Duration::from_secs(5)).map_err(|e| {
+        error!(target: "inbound", "创建HTTP客户端失败: {}", e);
+        format!("创建HTTP客户端失败: {}", e)
+    })?;
 
     let config_url = config::get_config_file_url();
-    let response = client.get(&config_url).send().await.map_err(|e| {
+    let mut request = client.get(&config_url);
+    if let Some(cached) = cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().await.map_err(|e| {
         error!(target: "inbound", "请求线路配置失败 - URL: {}, 错误: {}", config_url, e);
         format!("请求线路配置失败: {}", e)
     })?;
 
-    let config = response.json::<InboundConfig>().await.map_err(|e| {
-        error!(target: "inbound", "解析线路配置失败: {}", e);
-        format!("解析线路配置失败: {}", e)
+    if response.status() == StatusCode::NOT_MODIFIED {
+        info!(target: "inbound", "远程线路配置未变化");
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        error!(target: "inbound", "请求线路配置失败 - URL: {}, 状态码: {}", config_url, response.status());
+        return Err(format!("请求线路配置失败: {}", response.status()));
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    let payload = response.text().await.map_err(|e| {
+        error!(target: "inbound", "读取线路配置失败 - URL: {}, 错误: {}", config_url, e);
+        format!("读取线路配置失败: {}", e)
     })?;
 
-    if config.inbound.is_empty() {
-        error!(target: "inbound", "线路配置为空");
-        return Err("线路配置为空".to_string());
-    }
+    let verified = VerifiedConfig {
+        payload,
+        signature: download(&client, &config::get_config_signature_url()).await?,
+        etag,
+        last_modified,
+        fetched_at: Utc::now().timestamp(),
+    };
+    verified.parse().map_err(|e| {
+        error!(target: "inbound", "{}", e);
+        e
+    })?;
 
-    Ok(config)
+    Ok(Some(verified))
 }
 
-/// 测试单个线路的延迟
-async fn test_inbound_latency(url: &str) -> Option<Duration> {
-    let ping_timeout = config::get_ping_timeout();
-    let client = match Client::builder().timeout(ping_timeout).build() {
-        Ok(client) => client,
+// 读取缓存的远程配置，只返回签名校验通过的缓存
+async fn load_verified_config(db: &Database) -> Option<VerifiedConfig> {
+    let json = db.get_item(VERIFIED_CONFIG_KEY).await.ok().flatten()?;
+    let verified = match serde_json::from_str::<VerifiedConfig>(&json) {
+        Ok(verified) => verified,
+        Err(e) => {
+            error!(target: "inbound", "解析已缓存的线路配置失败: {}", e);
+            return None;
+        }
+    };
+    match verified.parse() {
+        Ok(_) => Some(verified),
         Err(e) => {
-            error!(target: "inbound", "创建延迟测试HTTP客户端失败 - URL: {}, 错误: {}", url, e);
+            error!(target: "inbound", "已缓存的线路配置无效: {}", e);
+            None
+        }
+    }
+}
+
+/// 上一次校验通过的线路配置，启动时用于初始化请求使用的线路
+pub async fn load_verified_inbound_config(db: &Database) -> Option<InboundConfig> {
+    load_verified_config(db).await?.parse().ok()
+}
+
+// 从旧版本升级后第一次启动时取出数据库中已有的线路配置，之后只使用校验通过的配置
+async fn take_legacy_config(db: &Database) -> Option<InboundConfig> {
+    for key in [VERIFIED_CONFIG_KEY, LEGACY_MIGRATED_KEY] {
+        // 读取失败时同样不使用旧配置
+        if !matches!(db.get_item(key).await, Ok(None)) {
             return None;
         }
+    }
+    // 先记录已迁移，记录失败时不使用旧配置
+    if let Err(e) = db.set_item(LEGACY_MIGRATED_KEY, "1").await {
+        error!(target: "inbound", "记录线路配置迁移状态失败: {}", e);
+        return None;
+    }
+    load_inbound_config(db).await.filter(|c| !c.inbound.is_empty())
+}
+
+// 缓存校验通过的远程配置
+async fn save_verified_config(db: &Database, verified: &VerifiedConfig) {
+    match serde_json::to_string(verified) {
+        Ok(json) => {
+            if let Err(e) = db.set_item(VERIFIED_CONFIG_KEY, &json).await {
+                error!(target: "inbound", "缓存线路配置失败: {}", e);
+            }
+        }
+        Err(e) => error!(target: "inbound", "序列化线路配置失败: {}", e),
+    }
+}
+
+// 保存线路配置
+async fn save_inbound_config(db: &Database, config: &InboundConfig) -> Result<(), String> {
+    let config_json = serde_json::to_string(config).map_err(|e| {
+        error!(target: "inbound", "序列化线路配置失败: {}", e);
+        format!("序列化线路配置失败: {}", e)
+    })?;
+    settings::set(db, SettingKey::InboundConfig, &config_json)
+        .await
+        .map_err(|e| {
+            error!(target: "inbound", "保存线路配置失败: {}", e);
+            format!("保存线路配置失败: {}", e)
+        })
+}
+
+/// 使用新的线路配置，配置有变化时通知前端，返回是否有变化
+///
+/// 当前线路和固定线路仍在新配置中时保持不变，否则分别重置为第一条线路和取消固定
+async fn apply_inbound_config(
+    app_handle: &AppHandle,
+    config: &InboundConfig,
+) -> Result<bool, String> {
+    if let Some(client) = app_handle.try_state::<ApiClient>() {
+        client.apply_inbound_config(config);
+    }
+
+    let db = app_handle.state::<Database>();
+    let old = load_inbound_config(&db).await;
+    if old.as_ref() == Some(config) {
+        return Ok(false);
+    }
+
+    // 按地址找到旧索引在新配置中的位置
+    let remap = |index: usize| -> Option<usize> {
+        let url = &old.as_ref()?.inbound.get(index)?.url;
+        config.inbound.iter().position(|item| &item.url == url)
+    };
+    let current = remap(settings::current_inbound(&db).await).unwrap_or(0);
+    let pinned = settings::pinned_inbound(&db).await.and_then(remap);
+
+    save_inbound_config(&db, config).await?;
+    settings::set_current_inbound(&db, current)
+        .await
+        .map_err(|e| e.to_string())?;
+    settings::set_pinned_inbound(&db, pinned)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    info!(target: "inbound", "线路配置已更新，共 {} 条线路", config.inbound.len());
+    if let Err(e) = app_handle.emit(INBOUND_CONFIG_CHANGED_EVENT, config) {
+        error!(target: "inbound", "发送线路配置变更事件失败: {}", e);
+    }
+    Ok(true)
+}
+
+/// 刷新线路配置，返回生效的配置和是否有变化
+///
+/// 缓存在有效期内且不强制刷新时直接使用缓存，否则发送条件请求
+pub async fn refresh_inbound_config(
+    app_handle: &AppHandle,
+    force: bool,
+) -> Result<(InboundConfig, bool), String> {
+    let db = app_handle.state::<Database>();
+    let cached = load_verified_config(&db).await;
+
+    let fresh = !force
+        && cached
+            .as_ref()
+            .map_or(false, |cached| cached.is_fresh(config::get_config_ttl()));
+    let verified = if fresh {
+        cached.ok_or_else(|| "线路配置缓存不存在".to_string())?
+    } else {
+        let verified = match (fetch_inbound_config(cached.as_ref()).await?, cached) {
+            (Some(fetched), _) => fetched,
+            // 未变化，只更新获取时间
+            (None, Some(cached)) => VerifiedConfig {
+                fetched_at: Utc::now().timestamp(),
+                ..cached
+            },
+            (None, None) => return Err("远程线路配置为空".to_string()),
+        };
+        save_verified_config(&db, &verified).await;
+        verified
     };
 
+    let config = verified.parse()?;
+    let changed = apply_inbound_config(app_handle, &config).await?;
+    Ok((config, changed))
+}
+
+/// 测试单个线路的延迟，同一轮测速的所有采样共用一个客户端
+pub(crate) async fn test_inbound_latency(client: &Client, url: &str) -> Option<Duration> {
     // 测试版本信息接口（通常是轻量级的）
     let test_url = format!("{}/version", url);
     let start = Instant::now();
@@ -86,87 +330,93 @@ async fn test_inbound_latency(url: &str) -> Option<Duration> {
     }
 }
 
-/// 测试所有线路并返回延迟最低的有效线路索引
-async fn find_fastest_inbound(config: &InboundConfig) -> usize {
-    // 如果只有一个线路，直接返回
-    if config.inbound.len() == 1 {
-        return 0;
-    }
-
-    let mut items_with_latency = Vec::new();
-
-    // 测试所有线路
-    for (index, item) in config.inbound.iter().enumerate() {
-        let api_base_url = format!("{}/api", item.url);
-        let latency = test_inbound_latency(&api_base_url).await;
+/// 并发测速所有线路，保存结果并发送进度事件，返回按排名排序的结果
+pub async fn measure_lines(app_handle: &AppHandle, config: &InboundConfig) -> Vec<LineProbe> {
+    let probes = probe::probe_all(&config.inbound, &config::get_probe_config(), |progress| {
+        match progress.median_ms {
+            Some(ms) => info!(
+                target: "inbound",
+                "线路 [{}] {} 延迟中位数: {}ms, 成功率: {:.0}%",
+                progress.index, progress.name, ms, progress.success_rate * 100.0
+            ),
+            None => info!(target: "inbound", "线路 [{}] {} 不可用", progress.index, progress.name),
+        }
+        if let Err(e) = app_handle.emit(PROBE_PROGRESS_EVENT, &progress) {
+            error!(target: "inbound", "发送测速进度失败: {}", e);
+        }
+    })
+    .await;
 
-        items_with_latency.push(InboundItemWithLatency {
-            index,
-            item: item.clone(),
-            latency,
-        });
+    let db = app_handle.state::<Database>();
+    if let Err(e) = db.replace_line_probes(&probes).await {
+        error!(target: "inbound", "保存测速结果失败: {}", e);
+    }
 
-        // 打印测试结果
-        match latency {
-            Some(duration) => info!(target: "inbound", "线路 [{}] {} 延迟: {:?}", index, item.name, duration),
-            None => info!(target: "inbound", "线路 [{}] {} 不可用", index, item.name),
+    // 同步到线路健康状态
+    if let Some(client) = app_handle.try_state::<ApiClient>() {
+        for probe in &probes {
+            client
+                .lines()
+                .record_probe(&probe.url, probe.median_ms.map(Duration::from_millis));
         }
     }
 
-    // 筛选可用的线路
-    let available_items: Vec<_> = items_with_latency
-        .iter()
-        .filter(|item| item.latency.is_some())
-        .collect();
+    probes
+}
 
-    if available_items.is_empty() {
-        warn!(target: "inbound", "所有线路均不可用，使用第一个线路");
+/// 测试所有线路并返回排名最高的可用线路索引
+async fn find_fastest_inbound(app_handle: &AppHandle, config: &InboundConfig) -> usize {
+    // 如果只有一个线路，直接返回
+    if config.inbound.len() == 1 {
         return 0;
     }
 
-    // 找到延迟最低的线路
-    let fastest = available_items
-        .iter()
-        .min_by_key(|item| item.latency.unwrap())
-        .unwrap();
-
-    info!(
-        target: "inbound",
-        "选择延迟最低的线路 [{}] {}: {:?}",
-        fastest.index,
-        fastest.item.name,
-        fastest.latency.unwrap()
-    );
+    let probes = measure_lines(app_handle, config).await;
+    let fastest = probes
+        .first()
+        .filter(|probe| probe.median_ms.is_some())
+        .and_then(|probe| {
+            config
+                .inbound
+                .iter()
+                .position(|item| item.url == probe.url)
+                .map(|index| (index, probe))
+        });
 
-    fastest.index
+    match fastest {
+        Some((index, probe)) => {
+            info!(
+                target: "inbound",
+                "选择延迟最低的线路 [{}] {}: {}ms",
+                index,
+                probe.name,
+                probe.median_ms.unwrap_or_default()
+            );
+            index
+        }
+        None => {
+            warn!(target: "inbound", "所有线路均不可用，使用第一个线路");
+            0
+        }
+    }
 }
 
 /// 初始化线路配置
 pub async fn init_inbound_config(app_handle: &AppHandle) -> Result<(), String> {
     let db = app_handle.state::<Database>();
 
-    // 尝试从远程获取线路配置
-    match fetch_inbound_config().await {
-        Ok(config) => {
-            // 序列化配置
-            let config_json = serde_json::to_string(&config).map_err(|e| {
-                error!(target: "inbound", "序列化线路配置失败: {}", e);
-                format!("序列化线路配置失败: {}", e)
-            })?;
-
-            // 保存到数据库
-            let inbound_config_key = config::get_db_key("inbound_config");
-            db.set_item(&inbound_config_key, &config_json)
-                .map_err(|e| {
-                    error!(target: "inbound", "保存线路配置失败: {}", e);
-                    format!("保存线路配置失败: {}", e)
-                })?;
-
+    // 获取线路配置，缓存未过期时不请求远程
+    match refresh_inbound_config(app_handle, false).await {
+        Ok((config, _)) => {
             // 每次启动都执行测速选择最佳线路
-            let current_inbound_key = config::get_db_key("current_inbound");
             info!(target: "inbound", "开始测速选择最佳线路...");
-            let best_index = find_fastest_inbound(&config).await;
-            db.set_item(&current_inbound_key, &best_index.to_string())
+            let best_index = find_fastest_inbound(app_handle, &config).await;
+            if settings::pinned_inbound(&db).await.is_some() {
+                info!(target: "inbound", "已固定线路，不自动选择");
+                return Ok(());
+            }
+            settings::set_current_inbound(&db, best_index)
+                .await
                 .map_err(|e| {
                     error!(target: "inbound", "设置当前线路失败: {}", e);
                     format!("设置当前线路失败: {}", e)
@@ -175,93 +425,152 @@ pub async fn init_inbound_config(app_handle: &AppHandle) -> Result<(), String> {
         }
         Err(e) => {
             error!(target: "inbound", "获取远程线路配置失败: {}", e);
-            warn!(target: "inbound", "获取远程线路配置失败: {}，将使用默认线路", e);
-
-            // 检查数据库中是否已有配置
-            let inbound_config_key = config::get_db_key("inbound_config");
-            if let Ok(None) = db.get_item(&inbound_config_key) {
-                // 创建默认配置
-                let default_api_url = config::get_default_api_url();
-                let default_config = InboundConfig {
-                    inbound: vec![InboundItem {
-                        name: "默认线路".to_string(),
-                        url: default_api_url
-                            .split("/api")
-                            .next()
-                            .unwrap_or("https://pool.52ai.org")
-                            .to_string(),
-                    }],
-                };
-
-                let config_json = serde_json::to_string(&default_config).map_err(|e| {
-                    error!(target: "inbound", "序列化默认线路配置失败: {}", e);
-                    format!("序列化默认线路配置失败: {}", e)
-                })?;
 
-                db.set_item(&inbound_config_key, &config_json)
-                    .map_err(|e| {
-                        error!(target: "inbound", "保存默认线路配置失败: {}", e);
-                        format!("保存默认线路配置失败: {}", e)
-                    })?;
-
-                let current_inbound_key = config::get_db_key("current_inbound");
-                db.set_item(&current_inbound_key, "0").map_err(|e| {
-                    error!(target: "inbound", "设置默认当前线路失败: {}", e);
-                    format!("设置当前线路失败: {}", e)
-                })?;
+            // 回退到上一次校验通过的配置，即使已经过期
+            if let Some(config) = load_verified_config(&db).await.and_then(|v| v.parse().ok()) {
+                apply_inbound_config(app_handle, &config).await?;
+                warn!(target: "inbound", "获取远程线路配置失败: {}，使用上一次校验通过的配置", e);
+                return Ok(());
+            }
 
-                info!(target: "inbound", "已创建默认线路配置");
+            // 从旧版本升级后还没有校验通过的配置时，沿用一次数据库中已有的配置
+            if let Some(config) = take_legacy_config(&db).await {
+                apply_inbound_config(app_handle, &config).await?;
+                warn!(target: "inbound", "获取远程线路配置失败: {}，尚无校验通过的配置，本次沿用已保存的线路配置", e);
+                return Ok(());
             }
+
+            // 缓存的配置无效时不信任数据库中已有的配置，改用默认线路
+            warn!(target: "inbound", "获取远程线路配置失败: {}，将使用默认线路", e);
+            let default_api_url = config::get_default_api_url();
+            let default_config = InboundConfig {
+                inbound: vec![InboundItem {
+                    name: "默认线路".to_string(),
+                    url: default_api_url
+                        .split("/api")
+                        .next()
+                        .unwrap_or("https://pool.52ai.org")
+                        .to_string(),
+                }],
+                ..InboundConfig::default()
+            };
+            apply_inbound_config(app_handle, &default_config).await?;
+            info!(target: "inbound", "已使用默认线路配置");
         }
     }
 
     Ok(())
 }
 
-/// 获取当前线路URL
-pub fn get_current_inbound_url(db: &Database) -> String {
-    let default_api_url = config::get_default_api_url();
-
-    // 获取当前选择的线路索引
-    let current_inbound_key = config::get_db_key("current_inbound");
-    let current_index = match db.get_item(&current_inbound_key) {
-        Ok(Some(index)) => index.parse::<usize>().unwrap_or(0),
-        Ok(None) => {
-            error!(target: "inbound", "未找到当前线路索引配置");
-            0
-        }
+/// 读取已保存的线路配置
+pub async fn load_inbound_config(db: &Database) -> Option<InboundConfig> {
+    let json = settings::get(db, SettingKey::InboundConfig).await.ok()??;
+    match serde_json::from_str::<InboundConfig>(&json) {
+        Ok(config) => Some(config),
         Err(e) => {
-            error!(target: "inbound", "获取当前线路索引失败: {}", e);
-            0
+            error!(target: "inbound", "解析线路配置失败: {}", e);
+            None
         }
-    };
+    }
+}
 
-    // 获取线路配置
-    let inbound_config_key = config::get_db_key("inbound_config");
-    let config = match db.get_item(&inbound_config_key) {
-        Ok(Some(json)) => match serde_json::from_str::<InboundConfig>(&json) {
-            Ok(config) => config,
-            Err(e) => {
-                error!(target: "inbound", "解析线路配置失败: {}", e);
-                return default_api_url;
-            }
-        },
-        Ok(None) => {
-            error!(target: "inbound", "未找到线路配置");
-            return default_api_url;
-        }
-        Err(e) => {
-            error!(target: "inbound", "获取线路配置失败: {}", e);
-            return default_api_url;
+/// 当前线路不健康时切换到下一条健康线路
+///
+/// 已固定线路、当前线路仍健康或没有其他健康线路时不切换，返回 None
+pub async fn fail_over(db: &Database, tracker: &LineTracker) -> Option<usize> {
+    if settings::pinned_inbound(db).await.is_some() {
+        return None;
+    }
+    let config = load_inbound_config(db).await?;
+    let current = settings::current_inbound(db).await;
+    if config
+        .inbound
+        .get(current)
+        .map_or(false, |item| tracker.is_healthy(&item.url))
+    {
+        return None;
+    }
+
+    let next = match next_healthy(&config.inbound, current, tracker) {
+        Some(next) => next,
+        None => {
+            warn!(target: "inbound", "当前线路不可用，且没有其他可用线路");
+            return None;
         }
     };
+    if let Err(e) = settings::set_current_inbound(db, next).await {
+        error!(target: "inbound", "切换线路失败: {}", e);
+        return None;
+    }
+    warn!(
+        target: "inbound",
+        "线路 [{}] 不可用，已切换到线路 [{}] {}",
+        current, next, config.inbound[next].name
+    );
+    Some(next)
+}
 
-    // 检查索引是否有效
-    if current_index >= config.inbound.len() {
-        error!(target: "inbound", "当前线路索引无效: {}, 线路总数: {}", current_index, config.inbound.len());
-        return default_api_url;
+/// 重新测试所有线路并更新健康状态，当前线路不可用时切换
+pub async fn probe_lines(app_handle: &AppHandle) -> Vec<LineProbe> {
+    let db = app_handle.state::<Database>();
+    let config = match load_inbound_config(&db).await {
+        Some(config) => config,
+        None => return Vec::new(),
+    };
+
+    let probes = measure_lines(app_handle, &config).await;
+    if let Some(client) = app_handle.try_state::<ApiClient>() {
+        fail_over(&db, client.lines()).await;
     }
+    probes
+}
 
-    // 返回API基础URL
-    format!("{}/api", config.inbound[current_index].url)
+/// 列出所有线路及其健康状态
+pub async fn line_statuses(db: &Database, tracker: &LineTracker) -> Vec<LineStatus> {
+    let config = match load_inbound_config(db).await {
+        Some(config) => config,
+        None => return Vec::new(),
+    };
+    let current = settings::current_inbound(db).await;
+    let pinned = settings::pinned_inbound(db).await;
+
+    config
+        .inbound
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let health = tracker.health(&item.url);
+            LineStatus {
+                index,
+                current: index == current,
+                pinned: pinned == Some(index),
+                healthy: health.is_healthy(),
+                health,
+                name: item.name,
+                url: item.url,
+            }
+        })
+        .collect()
+}
+
+/// 固定使用指定线路，传入 None 时取消固定，恢复自动切换
+pub async fn pin_line(db: &Database, index: Option<usize>) -> Result<(), SettingsError> {
+    if let Some(index) = index {
+        let count = load_inbound_config(db)
+            .await
+            .map_or(0, |config| config.inbound.len());
+        if index >= count {
+            return Err(SettingsError::Invalid(format!(
+                "线路索引无效: {}, 线路总数: {}",
+                index, count
+            )));
+        }
+        settings::set_current_inbound(db, index).await?;
+    }
+    settings::set_pinned_inbound(db, index).await?;
+    match index {
+        Some(index) => info!(target: "inbound", "已固定线路 [{}]", index),
+        None => info!(target: "inbound", "已取消固定线路"),
+    }
+    Ok(())
 }
@@ -1,102 +1,528 @@
+use crate::api::compat::{self, VersionStatus};
+use crate::api::failover::LineTracker;
+use crate::api::inbound::{self, InboundConfig};
+use crate::api::types::{ApiResponse, LoginResponse};
+use crate::auth::{is_auth_failure, Session, TokenStore, SESSION_EXPIRED_EVENT};
 use crate::config;
 use crate::database::Database;
-use reqwest::Request;
+use crate::error::{AppError, ErrorCode};
+use crate::settings::{self, SettingKey};
+use async_trait::async_trait;
+use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use reqwest::{Method, Request, Response, StatusCode};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::ops::Not;
-use std::sync::Arc;
+use semver::Version;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
 use tauri::AppHandle;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+use tracing::{debug, error, warn};
 
-/// HTTP 请求拦截器特征
+/// 请求上下文，同一请求的所有拦截器共享
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub method: Method,
+    pub url: String,
+    pub host: String,
+    pub path: String,
+    pub started_at: Instant,
+    // 本次请求携带的认证令牌
+    pub token: Option<String>,
+}
+
+impl RequestContext {
+    pub fn new(request: &Request) -> Self {
+        Self {
+            method: request.method().clone(),
+            url: request.url().to_string(),
+            host: request.url().host_str().unwrap_or_default().to_string(),
+            path: request.url().path().to_string(),
+            started_at: Instant::now(),
+            token: None,
+        }
+    }
+}
+
+/// HTTP 拦截器特征
+///
+/// 请求按注册顺序经过 `on_request`，响应按相反顺序经过 `on_response`。
+/// 请求失败时，按相反顺序通知还没有处理过响应的拦截器
+#[async_trait]
 pub trait Interceptor: Send + Sync {
-    /// 拦截并处理请求
-    fn intercept(&self, request: &mut Request) -> Result<(), String>;
+    /// 发送请求前调用
+    async fn on_request(&self, _ctx: &mut RequestContext, _request: &mut Request) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    /// 收到响应后调用
+    async fn on_response(&self, _ctx: &RequestContext, response: Response) -> Result<Response, AppError> {
+        Ok(response)
+    }
+
+    /// 请求失败时调用
+    async fn on_error(&self, _ctx: &RequestContext, _error: &AppError) {}
+}
+
+/// 路由匹配规则
+///
+/// `*` 匹配所有请求；其余规则按路径末尾的若干段匹配，段中的 `*` 匹配任意一段，
+/// 例如 `/user/*` 匹配 `/api/user/activate`，这样带路径前缀的线路地址也能匹配
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutePattern {
+    // None 表示匹配所有请求
+    segments: Option<Vec<String>>,
+}
+
+impl RoutePattern {
+    pub fn parse(pattern: &str) -> Self {
+        let pattern = pattern.trim();
+        if pattern == "*" {
+            return Self { segments: None };
+        }
+        Self {
+            segments: Some(
+                pattern
+                    .split('/')
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let pattern = match &self.segments {
+            Some(segments) => segments,
+            None => return true,
+        };
+        let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        if path.len() < pattern.len() {
+            return false;
+        }
+        path[path.len() - pattern.len()..]
+            .iter()
+            .zip(pattern)
+            .all(|(segment, p)| p == "*" || segment == p)
+    }
+}
+
+/// 拦截器链
+#[derive(Default)]
+pub struct Pipeline {
+    routes: Vec<(RoutePattern, Arc<dyn Interceptor>)>,
+}
+
+impl Pipeline {
+    /// 注册拦截器，只对匹配路由的请求生效
+    pub fn register(&mut self, pattern: &str, interceptor: Arc<dyn Interceptor>) {
+        self.routes.push((RoutePattern::parse(pattern), interceptor));
+    }
+
+    /// 按注册顺序返回匹配该路径的拦截器
+    pub fn matching(&self, path: &str) -> Vec<Arc<dyn Interceptor>> {
+        self.routes
+            .iter()
+            .filter(|(pattern, _)| pattern.matches(path))
+            .map(|(_, interceptor)| interceptor.clone())
+            .collect()
+    }
+}
+
+// 读取响应体并重建响应，供需要检查响应内容的拦截器使用
+async fn buffer_response(response: Response) -> Result<(Response, Vec<u8>), AppError> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?.to_vec();
+
+    let mut builder = http::Response::builder().status(status);
+    if let Some(builder_headers) = builder.headers_mut() {
+        *builder_headers = headers;
+    }
+    let rebuilt = builder
+        .body(body.clone())
+        .map_err(|e| AppError::internal("重建响应失败").with_details(e))?;
+    Ok((Response::from(rebuilt), body))
+}
+
+// 认证失败的响应体只是很小的JSON，超过这个大小的响应不检查
+const AUTH_ENVELOPE_MAX_BYTES: u64 = 64 * 1024;
+
+// 只有较小的JSON响应才可能是认证失败的响应，其余响应不缓冲响应体
+fn may_carry_auth_envelope(response: &Response) -> bool {
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.to_ascii_lowercase().contains("json"));
+    let small = response
+        .content_length()
+        .map_or(true, |len| len <= AUTH_ENVELOPE_MAX_BYTES);
+    is_json && small
+}
+
+/// 请求日志
+pub struct LoggingInterceptor;
+
+#[async_trait]
+impl Interceptor for LoggingInterceptor {
+    async fn on_request(&self, ctx: &mut RequestContext, _request: &mut Request) -> Result<(), AppError> {
+        debug!(target: "http_client", "发送请求 - 方法: {}, URL: {}", ctx.method, ctx.url);
+        Ok(())
+    }
+
+    async fn on_response(&self, ctx: &RequestContext, response: Response) -> Result<Response, AppError> {
+        debug!(
+            target: "http_client",
+            "请求完成 - 方法: {}, URL: {}, 状态码: {}, 耗时: {:?}",
+            ctx.method, ctx.url, response.status(), ctx.started_at.elapsed()
+        );
+        Ok(response)
+    }
+
+    async fn on_error(&self, ctx: &RequestContext, error: &AppError) {
+        error!(
+            target: "http_client",
+            "请求失败 - 方法: {}, URL: {}, 耗时: {:?}, 错误: {}",
+            ctx.method, ctx.url, ctx.started_at.elapsed(), error
+        );
+    }
+}
+
+/// 单个接口的请求统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EndpointMetrics {
+    pub endpoint: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+}
+
+/// 按接口统计请求次数、失败次数和耗时
+#[derive(Default)]
+pub struct MetricsInterceptor {
+    endpoints: Mutex<HashMap<String, EndpointMetrics>>,
+}
+
+impl MetricsInterceptor {
+    fn record(&self, ctx: &RequestContext, failed: bool) {
+        let endpoint = format!("{} {}", ctx.method, ctx.path);
+        let elapsed = ctx.started_at.elapsed().as_millis() as u64;
+
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let metrics = endpoints
+            .entry(endpoint.clone())
+            .or_insert_with(|| EndpointMetrics {
+                endpoint,
+                ..EndpointMetrics::default()
+            });
+        metrics.requests += 1;
+        if failed {
+            metrics.errors += 1;
+        }
+        metrics.total_ms += elapsed;
+        metrics.max_ms = metrics.max_ms.max(elapsed);
+    }
+
+    /// 当前统计数据，按接口排序
+    pub fn snapshot(&self) -> Vec<EndpointMetrics> {
+        let mut metrics: Vec<_> = self.endpoints.lock().unwrap().values().cloned().collect();
+        metrics.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+        metrics
+    }
+}
+
+#[async_trait]
+impl Interceptor for MetricsInterceptor {
+    async fn on_response(&self, ctx: &RequestContext, response: Response) -> Result<Response, AppError> {
+        self.record(ctx, false);
+        Ok(response)
+    }
+
+    async fn on_error(&self, ctx: &RequestContext, _error: &AppError) {
+        self.record(ctx, true);
+    }
+}
+
+/// 将服务端错误和限流响应转换为 AppError
+pub struct ErrorMappingInterceptor;
+
+#[async_trait]
+impl Interceptor for ErrorMappingInterceptor {
+    async fn on_response(&self, _ctx: &RequestContext, response: Response) -> Result<Response, AppError> {
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(AppError::network("请求过于频繁，请稍后再试").with_details(status));
+        }
+        if status.is_server_error() {
+            return Err(AppError::network("服务器暂时不可用，请稍后再试").with_details(status));
+        }
+        Ok(response)
+    }
+}
+
+/// 客户端版本低于远程配置要求的最低版本时拒绝请求后端接口
+pub struct VersionGateInterceptor {
+    current: Version,
+    // 线路配置更新时重新计算，避免每个请求都读取数据库
+    status: RwLock<VersionStatus>,
+}
+
+impl VersionGateInterceptor {
+    pub fn new(current: Version, config: &InboundConfig) -> Self {
+        Self {
+            status: RwLock::new(compat::evaluate(&current, config)),
+            current,
+        }
+    }
+
+    /// 线路配置更新后重新计算版本状态
+    pub fn update(&self, config: &InboundConfig) {
+        *self.status.write().unwrap() = compat::evaluate(&self.current, config);
+    }
+}
+
+// Cursor 官方接口的域名
+fn is_cursor_host(host: &str) -> bool {
+    host == "cursor.com" || host.ends_with(".cursor.com")
+}
+
+#[async_trait]
+impl Interceptor for VersionGateInterceptor {
+    async fn on_request(&self, ctx: &mut RequestContext, _request: &mut Request) -> Result<(), AppError> {
+        // 版本检查接口和 Cursor 接口不受限制
+        if ctx.path.ends_with("/version") || is_cursor_host(&ctx.host) {
+            return Ok(());
+        }
+
+        let status = self.status.read().unwrap().clone();
+        if status.update_required() {
+            let min_version = status.min_version.unwrap_or_default();
+            warn!(target: "http_client", "客户端版本 {} 低于最低版本 {}，已拒绝请求: {}", status.current_version, min_version, ctx.path);
+            return Err(AppError::update_required(&min_version));
+        }
+        Ok(())
+    }
+}
+
+/// 根据请求结果记录线路健康状态，当前线路连续失败时切换到下一条健康线路
+pub struct FailoverInterceptor {
+    app_handle: Arc<AppHandle>,
+    tracker: Arc<LineTracker>,
+}
+
+impl FailoverInterceptor {
+    pub fn new(app_handle: Arc<AppHandle>, tracker: Arc<LineTracker>) -> Self {
+        Self {
+            app_handle,
+            tracker,
+        }
+    }
+
+    // 记录请求所属线路的结果，error 为 None 表示成功
+    async fn record(&self, url: &str, error: Option<String>) {
+        // 不属于任何线路的请求（如 Cursor 接口）不记录
+        let line = match self.tracker.line_for(url) {
+            Some(line) => line,
+            None => return,
+        };
+        let error = match error {
+            None => {
+                self.tracker.record_success(&line.url);
+                return;
+            }
+            Some(error) => error,
+        };
+        if !self.tracker.record_failure(&line.url, &error) {
+            return;
+        }
+
+        warn!(target: "http_client", "线路 {} 连续请求失败，判定为不可用", line.name);
+        inbound::fail_over(&self.app_handle.state::<Database>(), &self.tracker).await;
+    }
+}
+
+#[async_trait]
+impl Interceptor for FailoverInterceptor {
+    async fn on_response(&self, ctx: &RequestContext, response: Response) -> Result<Response, AppError> {
+        let status = response.status();
+        let error = if status.is_server_error() {
+            Some(format!("服务器返回错误: {}", status))
+        } else {
+            None
+        };
+        self.record(&ctx.url, error).await;
+        Ok(response)
+    }
+
+    async fn on_error(&self, ctx: &RequestContext, error: &AppError) {
+        // 只有网络错误说明线路有问题
+        if error.code == ErrorCode::NetworkError {
+            self.record(&ctx.url, Some(error.to_string())).await;
+        }
+    }
 }
 
 /// JWT 认证拦截器
 pub struct AuthInterceptor {
     app_handle: Arc<AppHandle>,
+    tokens: Arc<dyn TokenStore>,
 }
 
 impl AuthInterceptor {
     /// 创建认证拦截器实例
-    pub fn new(app_handle: Arc<AppHandle>) -> Self {
-        Self { app_handle }
+    pub fn new(app_handle: Arc<AppHandle>, tokens: Arc<dyn TokenStore>) -> Self {
+        Self { app_handle, tokens }
     }
 }
 
+#[async_trait]
 impl Interceptor for AuthInterceptor {
-    fn intercept(&self, request: &mut Request) -> Result<(), String> {
-        let db = self.app_handle.state::<Database>();
-
-        let token_key = config::get_db_key("token");
-        
-        //header 添加X-API-Key = token 
-        let token = match db.get_item(&token_key) {
-            Ok(Some(token)) => token,
-            _ => return Ok(()),
+    async fn on_request(&self, ctx: &mut RequestContext, request: &mut Request) -> Result<(), AppError> {
+        if !is_auth_required_url(&ctx.url) {
+            return Ok(());
+        }
+
+        let token = match self.tokens.get().await {
+            Some(token) => token,
+            None => return Ok(()),
         };
 
+        let header = |value: &str| {
+            HeaderValue::from_str(value)
+                .map_err(|e| AppError::internal("请求头包含无效字符").with_details(e))
+        };
+        let lang = settings::lang(&self.app_handle.state::<Database>()).await;
+        let headers = request.headers_mut();
         // 添加 X-API-Key 到请求头
-        request.headers_mut().insert(
-            "X-API-Key",
-            token.parse().unwrap(),
-        );
+        headers.insert("X-API-Key", header(&token)?);
+        headers.insert("Authorization", header(&format!("Bearer {}", token))?);
+        headers.insert("cb-lang", header(&lang)?);
 
-        request.headers_mut().insert(
-            "Authorization",
-            format!("Bearer {}", token).parse().unwrap(),
-        );
+        ctx.token = Some(token);
+        Ok(())
+    }
+}
+
+/// 检测登录失效：清除令牌并通知前端和托盘
+pub struct SessionInterceptor {
+    app_handle: Arc<AppHandle>,
+    session: Arc<Session>,
+}
+
+impl SessionInterceptor {
+    pub fn new(app_handle: Arc<AppHandle>, session: Arc<Session>) -> Self {
+        Self {
+            app_handle,
+            session,
+        }
+    }
+}
 
-        let lang_key = config::get_db_key("lang");
-        let lang = match db.get_item(&lang_key) {
-            Ok(Some(lang)) if lang != "zh-CN" => lang,
-            _ => "zh-CN".to_string(),
+#[async_trait]
+impl Interceptor for SessionInterceptor {
+    async fn on_response(&self, ctx: &RequestContext, response: Response) -> Result<Response, AppError> {
+        // 没有携带令牌的请求不会导致登录失效
+        let token = match &ctx.token {
+            Some(token) => token.clone(),
+            None => return Ok(response),
         };
 
-        request
-            .headers_mut()
-            .insert("cb-lang", lang.parse().unwrap());
+        // 401 不需要读取响应体，其余响应只检查可能是认证失败的JSON
+        if response.status() != StatusCode::UNAUTHORIZED {
+            if !may_carry_auth_envelope(&response) {
+                return Ok(response);
+            }
+            let (response, body) = buffer_response(response).await?;
+            if !is_auth_failure(response.status(), &body) {
+                return Ok(response);
+            }
+        }
 
-        Ok(())
+        match self.session.expire(&token).await {
+            // 并发请求中只有第一个清除令牌的请求发送事件
+            Ok(true) => {
+                if let Err(e) = self.app_handle.emit(
+                    SESSION_EXPIRED_EVENT,
+                    serde_json::json!({ "message": "登录已过期，请重新登录" }),
+                ) {
+                    error!(target: "http_client", "发送登录失效事件失败: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => {
+                error!(target: "http_client", "清除认证令牌失败 - URL: {}, 错误: {}", ctx.url, e);
+            }
+        }
+
+        Err(AppError::new(ErrorCode::Unauthorized, "登录已过期，请重新登录"))
     }
 }
 
-/// 检查 URL 是否需要认证
-pub fn is_auth_required_url(url: &str) -> bool {
-    config::is_public_endpoint(url).not()
+/// 登录、注册成功后保存认证令牌
+pub struct TokenCaptureInterceptor {
+    tokens: Arc<dyn TokenStore>,
 }
 
-/// 保存认证令牌
-pub async fn save_auth_token(
-    db: &tauri::State<'_, Database>,
-    url: &str,
-    response_text: &str,
-) -> Result<(), String> {
-    if !url.contains("/login") && !url.contains("/emailRegister") {
-        return Ok(());
+impl TokenCaptureInterceptor {
+    pub fn new(tokens: Arc<dyn TokenStore>) -> Self {
+        Self { tokens }
     }
+}
 
-    let api_response: crate::api::types::ApiResponse<crate::api::types::LoginResponse> =
-        match serde_json::from_str(response_text) {
-            Ok(response) => response,
-            Err(_) => return Ok(()),
-        };
+#[async_trait]
+impl Interceptor for TokenCaptureInterceptor {
+    async fn on_response(&self, ctx: &RequestContext, response: Response) -> Result<Response, AppError> {
+        let (response, body) = buffer_response(response).await?;
 
-    if api_response.code == 200 && api_response.data.is_some() {
-        let data = api_response.data.unwrap();
-        if let Some(token) = data.token {
-            let token_key = config::get_db_key("token");
-            db.set_item(&token_key, &token).map_err(|e| e.to_string())?;
+        let token = serde_json::from_slice::<ApiResponse<LoginResponse>>(&body)
+            .ok()
+            .filter(|r| r.code == 200)
+            .and_then(|r| r.data)
+            .and_then(|data| data.token);
+        if let Some(token) = token {
+            if let Err(e) = self.tokens.set(&token).await {
+                error!(target: "http_client", "保存认证令牌失败 - URL: {}, 错误: {}", ctx.url, e);
+            }
         }
+
+        Ok(response)
+    }
+}
+
+/// 修改密码成功后清除认证令牌
+pub struct TokenClearInterceptor {
+    tokens: Arc<dyn TokenStore>,
+}
+
+impl TokenClearInterceptor {
+    pub fn new(tokens: Arc<dyn TokenStore>) -> Self {
+        Self { tokens }
     }
+}
 
-    Ok(())
+#[async_trait]
+impl Interceptor for TokenClearInterceptor {
+    async fn on_response(&self, ctx: &RequestContext, response: Response) -> Result<Response, AppError> {
+        let (response, body) = buffer_response(response).await?;
+
+        let succeeded = serde_json::from_slice::<serde_json::Value>(&body)
+            .map_or(false, |json| json["status"] == 200);
+        if succeeded {
+            if let Err(e) = self.tokens.clear().await {
+                error!(target: "http_client", "清除认证令牌失败 - URL: {}, 错误: {}", ctx.url, e);
+            }
+        }
+
+        Ok(response)
+    }
 }
 
-/// 清除认证令牌
-pub async fn clear_auth_token(db: &tauri::State<'_, Database>) -> Result<(), String> {
-    let token_key = config::get_db_key("token");
-    db.delete_item(&token_key).map_err(|e| e.to_string())
+/// 检查 URL 是否需要认证
+pub fn is_auth_required_url(url: &str) -> bool {
+    config::is_public_endpoint(url).not()
 }
 
 /// 保存Cursor token到历史记录
@@ -113,7 +539,7 @@ pub async fn save_cursor_token_to_history(
         token.to_string()
     };
     // 1. 获取当前历史记录
-    let accounts = match db.get_item("user.history.accounts") {
+    let accounts = match db.get_item(&SettingKey::HistoryAccounts.db_key()).await {
         Ok(Some(data)) => {
             serde_json::from_str::<Vec<crate::api::types::HistoryAccountRecord>>(&data)
                 .unwrap_or_default()
@@ -149,8 +575,33 @@ pub async fn save_cursor_token_to_history(
     // 4. 保存回数据库
     let json_data = serde_json::to_string(&updated_accounts).map_err(|e| e.to_string())?;
 
-    db.set_item("user.history.accounts", &json_data)
+    db.set_item(&SettingKey::HistoryAccounts.db_key(), &json_data)
+        .await
         .map_err(|e| e.to_string())?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_cursor_host() {
+        assert!(is_cursor_host("cursor.com"));
+        assert!(is_cursor_host("www.cursor.com"));
+        assert!(!is_cursor_host("cursor.com.example.org"));
+        assert!(!is_cursor_host("notcursor.com"));
+    }
+
+    #[test]
+    fn test_route_pattern() {
+        assert!(RoutePattern::parse("*").matches("/anything/at/all"));
+        assert!(RoutePattern::parse("/login").matches("/login"));
+        assert!(RoutePattern::parse("/login").matches("/api/login"));
+        assert!(!RoutePattern::parse("/login").matches("/login/history"));
+        assert!(RoutePattern::parse("/user/*").matches("/api/user/activate"));
+        assert!(!RoutePattern::parse("/user/*").matches("/user"));
+        assert!(!RoutePattern::parse("/user/updatePassword").matches("/updatePassword"));
+    }
+}
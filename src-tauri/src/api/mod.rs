@@ -1,7 +1,11 @@
 pub mod client;
+pub mod compat;
+pub mod decode;
 pub mod endpoints;
+pub mod failover;
 pub mod inbound;
 pub mod interceptor;
+pub mod probe;
 pub mod types;
 
 pub use client::ApiClient;
@@ -0,0 +1,174 @@
+//! 线路测速：并发测试所有线路，每条线路多次采样，按延迟中位数和成功率排序
+
+use super::inbound::{test_inbound_latency, InboundItem};
+use crate::config::{get_ping_timeout, ProbeConfig};
+use crate::database::LineProbe;
+use crate::utils::http;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{timeout_at, Instant};
+use tracing::error;
+
+/// 测速进度事件
+pub const PROBE_PROGRESS_EVENT: &str = "inbound-probe-progress";
+
+/// 测速进度，每条线路测完时发送一次
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeProgress {
+    pub completed: usize,
+    pub total: usize,
+    pub index: usize,
+    pub name: String,
+    pub median_ms: Option<u64>,
+    pub success_rate: f64,
+}
+
+/// 并发测试所有线路，返回按排名排序的结果
+///
+/// 所有线路共用一个总时限和一个HTTP客户端，超时未完成的采样视为失败
+pub async fn probe_all<F>(lines: &[InboundItem], config: &ProbeConfig, mut on_progress: F) -> Vec<LineProbe>
+where
+    F: FnMut(ProbeProgress),
+{
+    let deadline = Instant::now() + Duration::from_millis(config.deadline_ms);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    // 客户端创建失败时所有采样视为失败
+    let client = match http::build_client(get_ping_timeout()) {
+        Ok(client) => Some(client),
+        Err(e) => {
+            error!(target: "inbound", "创建延迟测试HTTP客户端失败: {}", e);
+            None
+        }
+    };
+
+    for (index, item) in lines.iter().enumerate() {
+        let tx = tx.clone();
+        let client = client.clone();
+        let api_base_url = format!("{}/api", item.url);
+        let samples = config.samples;
+        tokio::spawn(async move {
+            let mut latencies = Vec::new();
+            if let Some(client) = client {
+                for _ in 0..samples {
+                    match timeout_at(deadline, test_inbound_latency(&client, &api_base_url)).await {
+                        Ok(Some(latency)) => latencies.push(latency),
+                        Ok(None) => {}
+                        Err(_) => break,
+                    }
+                }
+            }
+            let _ = tx.send((index, latencies));
+        });
+    }
+    drop(tx);
+
+    let probed_at = chrono::Local::now().to_rfc3339();
+    let mut probes = Vec::with_capacity(lines.len());
+    while let Some((index, latencies)) = rx.recv().await {
+        let probe = summarize(&lines[index], &latencies, config.samples, &probed_at);
+        on_progress(ProbeProgress {
+            completed: probes.len() + 1,
+            total: lines.len(),
+            index,
+            name: probe.name.clone(),
+            median_ms: probe.median_ms,
+            success_rate: probe.success_rate,
+        });
+        probes.push(probe);
+    }
+
+    rank(&mut probes);
+    probes
+}
+
+// 汇总单条线路的采样结果，排名在 rank 中统一设置
+fn summarize(item: &InboundItem, latencies: &[Duration], samples: u32, probed_at: &str) -> LineProbe {
+    LineProbe {
+        url: item.url.clone(),
+        name: item.name.clone(),
+        rank: 0,
+        median_ms: median_ms(latencies),
+        success_rate: if samples == 0 {
+            0.0
+        } else {
+            latencies.len() as f64 / f64::from(samples)
+        },
+        samples,
+        probed_at: probed_at.to_string(),
+    }
+}
+
+// 延迟中位数(毫秒)，偶数个采样取中间两个的平均值
+fn median_ms(latencies: &[Duration]) -> Option<u64> {
+    let mut millis: Vec<u64> = latencies.iter().map(|d| d.as_millis() as u64).collect();
+    millis.sort_unstable();
+    let mid = millis.len() / 2;
+    match millis.len() {
+        0 => None,
+        len if len % 2 == 1 => Some(millis[mid]),
+        _ => Some((millis[mid - 1] + millis[mid]) / 2),
+    }
+}
+
+// 中位数除以成功率作为有效延迟，不稳定的线路排名靠后
+fn effective_latency(probe: &LineProbe) -> Option<f64> {
+    match probe.median_ms {
+        Some(ms) if probe.success_rate > 0.0 => Some(ms as f64 / probe.success_rate),
+        _ => None,
+    }
+}
+
+/// 按有效延迟排序并设置排名，不可用的线路排在最后
+pub fn rank(probes: &mut [LineProbe]) {
+    probes.sort_by(|a, b| match (effective_latency(a), effective_latency(b)) {
+        (Some(a_ms), Some(b_ms)) => a_ms.partial_cmp(&b_ms).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    for (rank, probe) in probes.iter_mut().enumerate() {
+        probe.rank = rank;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(url: &str, median_ms: Option<u64>, success_rate: f64) -> LineProbe {
+        LineProbe {
+            url: url.to_string(),
+            name: url.to_string(),
+            rank: 0,
+            median_ms,
+            success_rate,
+            samples: 3,
+            probed_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_median_ms() {
+        let ms = |values: &[u64]| -> Vec<Duration> {
+            values.iter().map(|v| Duration::from_millis(*v)).collect()
+        };
+        assert_eq!(median_ms(&[]), None);
+        assert_eq!(median_ms(&ms(&[300, 100, 200])), Some(200));
+        assert_eq!(median_ms(&ms(&[400, 100, 200, 1000])), Some(300));
+    }
+
+    #[test]
+    fn test_rank_prefers_stable_lines() {
+        let mut probes = vec![
+            probe("dead", None, 0.0),
+            probe("flaky", Some(100), 1.0 / 3.0),
+            probe("stable", Some(200), 1.0),
+        ];
+        rank(&mut probes);
+
+        let order: Vec<(&str, usize)> = probes.iter().map(|p| (p.url.as_str(), p.rank)).collect();
+        assert_eq!(order, vec![("stable", 0), ("flaky", 1), ("dead", 2)]);
+    }
+}
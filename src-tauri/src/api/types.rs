@@ -9,17 +9,6 @@ pub struct ApiResponse<T> {
     pub data: Option<T>,
 }
 
-// 旧的API响应结构（保留以兼容旧代码）
-#[derive(Debug, Serialize, Deserialize)]
-pub struct OldApiResponse<T> {
-    pub status: i32,
-    pub msg: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<T>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub code: Option<String>,
-}
-
 // 注册响应
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RegisterResponse {
@@ -342,3 +331,14 @@ pub struct Article {
 pub struct ArticleListResponse {
     pub articles: Vec<Article>,
 }
+
+// 代理连接测试结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProxyTestResult {
+    // 测试请求的地址
+    pub url: String,
+    // 代理地址，直连或使用系统代理时为空
+    pub proxy: Option<String>,
+    pub status: u16,
+    pub latency_ms: u64,
+}
@@ -1,3 +1,7 @@
 mod authentication;
+mod session;
+mod token_store;
 
 pub use authentication::*;
+pub use session::{is_auth_failure, Session, SESSION_EXPIRED_EVENT};
+pub use token_store::{DbTokenStore, MemoryTokenStore, TokenStore};
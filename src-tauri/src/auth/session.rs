@@ -0,0 +1,90 @@
+use super::TokenStore;
+use crate::config;
+use reqwest::StatusCode;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// 登录失效时发送给前端和托盘的事件
+pub const SESSION_EXPIRED_EVENT: &str = "session-expired";
+
+/// 判断响应是否表示认证失败：HTTP 401，或响应体中的 code/status 属于认证失败码
+pub fn is_auth_failure(status: StatusCode, body: &[u8]) -> bool {
+    if status == StatusCode::UNAUTHORIZED {
+        return true;
+    }
+    let json = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => json,
+        Err(_) => return false,
+    };
+    let codes = config::get_auth_failure_codes();
+    ["code", "status"]
+        .iter()
+        .filter_map(|field| json[*field].as_i64())
+        .any(|code| codes.contains(&code))
+}
+
+/// 登录会话，令牌被服务端拒绝时负责清除
+pub struct Session {
+    tokens: Arc<dyn TokenStore>,
+    // 保证并发请求同时失败时只清除一次
+    expire_lock: Mutex<()>,
+}
+
+impl Session {
+    pub fn new(tokens: Arc<dyn TokenStore>) -> Self {
+        Self {
+            tokens,
+            expire_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn tokens(&self) -> &Arc<dyn TokenStore> {
+        &self.tokens
+    }
+
+    /// 请求携带的令牌被服务端拒绝
+    ///
+    /// 只有该令牌仍是当前令牌时才清除并返回 true；
+    /// 已被其他请求清除或用户已重新登录时返回 false
+    pub async fn expire(&self, rejected_token: &str) -> Result<bool, String> {
+        let _guard = self.expire_lock.lock().await;
+        if self.tokens.get().await.as_deref() != Some(rejected_token) {
+            return Ok(false);
+        }
+        self.tokens.clear().await?;
+        warn!(target: "auth", "登录已失效，已清除认证令牌");
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::MemoryTokenStore;
+
+    #[tokio::test]
+    async fn test_expire_once() {
+        let tokens: Arc<dyn TokenStore> = Arc::new(MemoryTokenStore::default());
+        tokens.set("old").await.unwrap();
+        let session = Session::new(tokens.clone());
+
+        assert!(session.expire("old").await.unwrap());
+        assert!(!session.expire("old").await.unwrap());
+        assert_eq!(tokens.get().await, None);
+
+        // 重新登录后，旧令牌的失败响应不会清除新令牌
+        tokens.set("new").await.unwrap();
+        assert!(!session.expire("old").await.unwrap());
+        assert_eq!(tokens.get().await.as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn test_is_auth_failure() {
+        assert!(is_auth_failure(StatusCode::UNAUTHORIZED, b""));
+        assert!(is_auth_failure(StatusCode::OK, br#"{"code": 401, "message": "unauthorized"}"#));
+        assert!(is_auth_failure(StatusCode::OK, br#"{"status": 401, "msg": "unauthorized"}"#));
+        assert!(!is_auth_failure(StatusCode::OK, br#"{"code": 200, "message": "ok"}"#));
+        assert!(!is_auth_failure(StatusCode::OK, b"not json"));
+    }
+}
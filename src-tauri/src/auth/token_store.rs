@@ -0,0 +1,69 @@
+use crate::database::Database;
+use crate::settings::{self, SettingKey};
+use async_trait::async_trait;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// 后端认证令牌的存取
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    /// 读取当前令牌
+    async fn get(&self) -> Option<String>;
+    /// 保存令牌
+    async fn set(&self, token: &str) -> Result<(), String>;
+    /// 清除令牌
+    async fn clear(&self) -> Result<(), String>;
+}
+
+/// 保存在应用数据库设置中的令牌
+pub struct DbTokenStore {
+    app_handle: AppHandle,
+}
+
+impl DbTokenStore {
+    pub fn new(app_handle: AppHandle) -> Self {
+        Self { app_handle }
+    }
+}
+
+#[async_trait]
+impl TokenStore for DbTokenStore {
+    async fn get(&self) -> Option<String> {
+        settings::token(&self.app_handle.state::<Database>()).await
+    }
+
+    async fn set(&self, token: &str) -> Result<(), String> {
+        settings::set(&self.app_handle.state::<Database>(), SettingKey::Token, token)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn clear(&self) -> Result<(), String> {
+        settings::remove(&self.app_handle.state::<Database>(), SettingKey::Token)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// 只保存在内存中的令牌，应用退出后丢失
+#[derive(Default)]
+pub struct MemoryTokenStore {
+    token: Mutex<Option<String>>,
+}
+
+#[async_trait]
+impl TokenStore for MemoryTokenStore {
+    async fn get(&self) -> Option<String> {
+        self.token.lock().unwrap().clone()
+    }
+
+    async fn set(&self, token: &str) -> Result<(), String> {
+        *self.token.lock().unwrap() = Some(token.to_string());
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<(), String> {
+        *self.token.lock().unwrap() = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! 远程配置签名工具
+//!
+//! 生成密钥对，公钥保存到仓库的 CONFIG_SIGNING_PUBLIC_KEY secret，私钥离线保存：
+//!
+//!     cargo run --bin sign_config -- keygen
+//!
+//! 发布新的 config.json 前签名，生成同目录下的 config.json.sig，两个文件一起上传：
+//!
+//!     CURSOR_POOL_CONFIG_SIGNING_KEY=<私钥> cargo run --bin sign_config -- sign config.json
+
+use cursor_pool_lib::api::InboundConfig;
+use cursor_pool_lib::utils::signature;
+use std::env;
+use std::fs;
+use std::process;
+
+// 签名私钥的环境变量，避免私钥出现在命令行历史中
+const SIGNING_KEY_VAR: &str = "CURSOR_POOL_CONFIG_SIGNING_KEY";
+
+fn usage() -> ! {
+    eprintln!("用法:");
+    eprintln!("  sign_config keygen");
+    eprintln!(
+        "  {}=<私钥> sign_config sign <config.json>",
+        SIGNING_KEY_VAR
+    );
+    process::exit(2);
+}
+
+fn keygen() {
+    let (secret, public) = signature::generate_keypair();
+    println!("私钥(只用于签名，不要提交到仓库): {}", secret);
+    println!("公钥(CONFIG_SIGNING_PUBLIC_KEY): {}", public);
+}
+
+fn sign(path: &str) -> Result<(), String> {
+    let secret = env::var(SIGNING_KEY_VAR)
+        .map_err(|_| format!("请通过 {} 环境变量提供私钥", SIGNING_KEY_VAR))?;
+    let payload = fs::read(path).map_err(|e| format!("读取配置文件失败 {}: {}", path, e))?;
+
+    // 签名前检查配置格式，避免发布客户端无法使用的配置
+    let config = serde_json::from_slice::<InboundConfig>(&payload)
+        .map_err(|e| format!("配置文件格式错误: {}", e))?;
+    if config.inbound.is_empty() {
+        return Err("配置文件中没有线路".to_string());
+    }
+
+    let sig = signature::sign(&secret, &payload).map_err(|e| e.to_string())?;
+    let public = signature::public_key(&secret).map_err(|e| e.to_string())?;
+    signature::verify(&public, &payload, &sig).map_err(|e| e.to_string())?;
+
+    let sig_path = format!("{}.sig", path);
+    fs::write(&sig_path, &sig).map_err(|e| format!("写入签名文件失败 {}: {}", sig_path, e))?;
+    println!("已生成签名文件: {}", sig_path);
+    println!("签名公钥: {}", public);
+    if let Some(builtin) = signature::REMOTE_CONFIG_PUBLIC_KEY {
+        if builtin != public {
+            eprintln!("警告: 私钥与当前构建内置的公钥不匹配，客户端将拒绝该配置");
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["keygen"] => {
+            keygen();
+            Ok(())
+        }
+        ["sign", path] => sign(path),
+        _ => usage(),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -1,9 +1,21 @@
+use crate::api::interceptor::RoutePattern;
+use crate::utils::retry::RetryPolicy;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::RwLock;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+use tracing::{error, info};
+
+// 应用数据目录下的配置文件名
+const CONFIG_FILE_NAME: &str = "config.json";
+// 环境变量前缀，层级之间用双下划线分隔，例如 CURSOR_POOL_API__DEFAULT_API_URL
+const ENV_PREFIX: &str = "CURSOR_POOL_";
 
 // API 配置结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +24,8 @@ pub struct ApiConfig {
     pub default_api_url: String,
     // 配置文件URL
     pub config_file_url: String,
+    // 配置文件缓存有效期(秒)，有效期内不重新请求
+    pub config_ttl_secs: u64,
     // Cursor API 用户ID
     pub cursor_user_id: String,
     // 不需要认证的公共端点
@@ -20,6 +34,14 @@ pub struct ApiConfig {
     pub request_timeout: u64,
     // 是否验证SSL证书
     pub verify_ssl: bool,
+    // 额外信任的CA证书文件(PEM)，为空时只使用系统证书
+    pub ca_bundle: String,
+    // 公钥固定规则，格式为 域名:sha256/<base64>
+    pub spki_pins: Vec<String>,
+    // 响应体中表示登录失效的 code/status
+    pub auth_failure_codes: Vec<i64>,
+    // 严格解码响应，数据结构与预期不一致时返回错误
+    pub strict_decoding: bool,
 }
 
 // 路径配置结构
@@ -58,13 +80,25 @@ pub struct LinuxPaths {
 pub struct DbKeyConfig {
     pub inbound_config_key: String,
     pub current_inbound_key: String,
+    pub pinned_inbound_key: String,
     pub cursor_main_js_path_key: String,
     pub token_key: String,
     pub lang_key: String,
+    pub history_accounts_key: String,
     // 任务调度相关键
     pub dashboard_refresh_interval_key: String,
     pub account_limit_check_interval_key: String,
     pub account_usage_threshold_key: String,
+    pub usage_alert_levels_key: String,
+    pub inbound_probe_interval_key: String,
+    pub config_refresh_interval_key: String,
+    // 任务计划、暂停状态、静默时段和用量周期设置键
+    pub task_schedules_key: String,
+    pub paused_tasks_key: String,
+    pub quiet_hours_key: String,
+    pub usage_cycle_start_key: String,
+    // 网络代理设置键
+    pub proxy_key: String,
 }
 
 // 超时配置
@@ -76,6 +110,15 @@ pub struct TimeoutConfig {
     pub request_timeout_secs: u64,
 }
 
+// 线路测速配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeConfig {
+    // 每条线路的采样次数
+    pub samples: u32,
+    // 一次测速的总时限(毫秒)，超时未完成的采样视为失败
+    pub deadline_ms: u64,
+}
+
 // 任务调度配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchedulerConfig {
@@ -85,6 +128,32 @@ pub struct SchedulerConfig {
     pub account_limit_check_interval: u64,
     // 账户使用量警告阈值(百分比，0-1之间)
     pub account_usage_threshold: f64,
+    // 线路测速间隔(秒)
+    pub inbound_probe_interval: u64,
+    // 远程配置刷新间隔(秒)
+    pub config_refresh_interval: u64,
+}
+
+// 请求重试配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    // 最多尝试次数（包含第一次）
+    pub max_attempts: u32,
+    // 第一次重试前的等待时间(毫秒)
+    pub initial_delay_ms: u64,
+    // 单次等待的上限(毫秒)
+    pub max_delay_ms: u64,
+    // 允许的最长总耗时(秒)
+    pub max_elapsed_secs: u64,
+    // 可以安全重试的 POST 接口，其余 POST 请求失败后不自动重试
+    pub idempotent_endpoints: Vec<String>,
+}
+
+// 日志配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSettings {
+    // 日志过滤规则，格式同 RUST_LOG
+    pub level: String,
 }
 
 // 全局应用配置
@@ -94,7 +163,10 @@ pub struct AppConfig {
     pub paths: PathConfig,
     pub db_keys: DbKeyConfig,
     pub timeouts: TimeoutConfig,
+    pub probe: ProbeConfig,
     pub scheduler: SchedulerConfig,
+    pub retry: RetryConfig,
+    pub log: LogSettings,
 }
 
 impl Default for AppConfig {
@@ -104,6 +176,7 @@ impl Default for AppConfig {
                 default_api_url: "http://127.0.0.1:9501".to_string(),
                 config_file_url: "https://cursorpool.oss-cn-guangzhou.aliyuncs.com/config.json"
                     .to_string(),
+                config_ttl_secs: 3600,
                 cursor_user_id: "user_01000000000000000000000000".to_string(),
                 public_endpoints: vec![
                     "/login".to_string(),
@@ -118,7 +191,11 @@ impl Default for AppConfig {
                     "/api/usage".to_string(),
                 ],
                 request_timeout: 10,
-                verify_ssl: false,
+                verify_ssl: true,
+                ca_bundle: String::new(),
+                spki_pins: Vec::new(),
+                auth_failure_codes: vec![401],
+                strict_decoding: false,
             },
             paths: PathConfig {
                 windows: WindowsPaths {
@@ -140,36 +217,359 @@ impl Default for AppConfig {
             db_keys: DbKeyConfig {
                 inbound_config_key: "system.inbound.config".to_string(),
                 current_inbound_key: "system.inbound.current".to_string(),
+                pinned_inbound_key: "system.inbound.pinned".to_string(),
                 cursor_main_js_path_key: "system.cursor.path.mainJs".to_string(),
                 token_key: "user.info.token".to_string(),
                 lang_key: "user.info.lang".to_string(),
+                history_accounts_key: "user.history.accounts".to_string(),
                 dashboard_refresh_interval_key: "system.scheduler.dashboard_refresh_interval".to_string(),
                 account_limit_check_interval_key: "system.scheduler.account_limit_check_interval".to_string(),
                 account_usage_threshold_key: "system.scheduler.account_usage_threshold".to_string(),
+                usage_alert_levels_key: "system.scheduler.usage_alert_levels".to_string(),
+                inbound_probe_interval_key: "system.scheduler.inbound_probe_interval".to_string(),
+                config_refresh_interval_key: "system.scheduler.config_refresh_interval".to_string(),
+                task_schedules_key: "system.scheduler.task_schedules".to_string(),
+                paused_tasks_key: "system.scheduler.paused".to_string(),
+                quiet_hours_key: "system.scheduler.quiet_hours".to_string(),
+                usage_cycle_start_key: "system.usage.cycle_start".to_string(),
+                proxy_key: "system.network.proxy".to_string(),
             },
             timeouts: TimeoutConfig {
                 ping_timeout_ms: 5000,
                 request_timeout_secs: 10,
             },
+            probe: ProbeConfig {
+                samples: 3,
+                deadline_ms: 10000,
+            },
             scheduler: SchedulerConfig {
                 dashboard_refresh_interval: 300, // 5分钟
                 account_limit_check_interval: 600, // 10分钟
                 account_usage_threshold: 0.1, // 10%
+                inbound_probe_interval: 300, // 5分钟
+                config_refresh_interval: 1800, // 30分钟
             },
+            retry: RetryConfig {
+                max_attempts: 3,
+                initial_delay_ms: 300,
+                max_delay_ms: 5000,
+                max_elapsed_secs: 30,
+                idempotent_endpoints: vec![
+                    "/checkUser".to_string(),
+                    "/api-key/detail".to_string(),
+                ],
+            },
+            log: LogSettings {
+                level: if cfg!(debug_assertions) {
+                    "debug,hyper=off,reqwest=off,tauri_plugin_updater=off".to_string()
+                } else {
+                    "info,hyper=off,reqwest=off,tauri_plugin_updater=off".to_string()
+                },
+            },
+        }
+    }
+}
+
+impl AppConfig {
+    // 校验配置值
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+
+        for (name, url) in [
+            ("api.default_api_url", &self.api.default_api_url),
+            ("api.config_file_url", &self.api.config_file_url),
+        ] {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                errors.push(format!("{} 不是有效的URL: {}", name, url));
+            }
+        }
+        for (name, value) in [
+            ("api.request_timeout", self.api.request_timeout),
+            ("timeouts.ping_timeout_ms", self.timeouts.ping_timeout_ms),
+            ("timeouts.request_timeout_secs", self.timeouts.request_timeout_secs),
+            ("probe.samples", u64::from(self.probe.samples)),
+            ("probe.deadline_ms", self.probe.deadline_ms),
+            (
+                "scheduler.dashboard_refresh_interval",
+                self.scheduler.dashboard_refresh_interval,
+            ),
+            (
+                "scheduler.account_limit_check_interval",
+                self.scheduler.account_limit_check_interval,
+            ),
+            (
+                "scheduler.inbound_probe_interval",
+                self.scheduler.inbound_probe_interval,
+            ),
+            (
+                "scheduler.config_refresh_interval",
+                self.scheduler.config_refresh_interval,
+            ),
+            ("retry.max_attempts", u64::from(self.retry.max_attempts)),
+            ("retry.max_elapsed_secs", self.retry.max_elapsed_secs),
+        ] {
+            if value == 0 {
+                errors.push(format!("{} 必须大于0", name));
+            }
+        }
+        if self.retry.initial_delay_ms > self.retry.max_delay_ms {
+            errors.push("retry.initial_delay_ms 不能大于 retry.max_delay_ms".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.scheduler.account_usage_threshold) {
+            errors.push("scheduler.account_usage_threshold 必须在 0 到 1 之间".to_string());
+        }
+        if !self.api.ca_bundle.trim().is_empty() && !Path::new(self.api.ca_bundle.trim()).is_file() {
+            errors.push(format!("api.ca_bundle 文件不存在: {}", self.api.ca_bundle));
+        }
+        for pin in &self.api.spki_pins {
+            if let Err(e) = crate::utils::tls::PinRule::parse(pin) {
+                errors.push(format!("api.spki_pins {}", e));
+            }
+        }
+        let keys = &self.db_keys;
+        for (name, key) in [
+            ("db_keys.inbound_config_key", &keys.inbound_config_key),
+            ("db_keys.current_inbound_key", &keys.current_inbound_key),
+            ("db_keys.pinned_inbound_key", &keys.pinned_inbound_key),
+            ("db_keys.cursor_main_js_path_key", &keys.cursor_main_js_path_key),
+            ("db_keys.token_key", &keys.token_key),
+            ("db_keys.lang_key", &keys.lang_key),
+            ("db_keys.history_accounts_key", &keys.history_accounts_key),
+            (
+                "db_keys.dashboard_refresh_interval_key",
+                &keys.dashboard_refresh_interval_key,
+            ),
+            (
+                "db_keys.account_limit_check_interval_key",
+                &keys.account_limit_check_interval_key,
+            ),
+            (
+                "db_keys.account_usage_threshold_key",
+                &keys.account_usage_threshold_key,
+            ),
+            ("db_keys.usage_alert_levels_key", &keys.usage_alert_levels_key),
+            (
+                "db_keys.inbound_probe_interval_key",
+                &keys.inbound_probe_interval_key,
+            ),
+            (
+                "db_keys.config_refresh_interval_key",
+                &keys.config_refresh_interval_key,
+            ),
+            ("db_keys.task_schedules_key", &keys.task_schedules_key),
+            ("db_keys.paused_tasks_key", &keys.paused_tasks_key),
+            ("db_keys.quiet_hours_key", &keys.quiet_hours_key),
+            ("db_keys.usage_cycle_start_key", &keys.usage_cycle_start_key),
+            ("db_keys.proxy_key", &keys.proxy_key),
+        ] {
+            if key.trim().is_empty() {
+                errors.push(format!("{} 不能为空", name));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("配置校验失败: {}", errors.join("; ")))
         }
     }
 }
 
+/// 配置值的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    // 编译时默认值
+    Default,
+    // 应用数据目录下的配置文件
+    File,
+    // 环境变量
+    Env,
+}
+
+/// 生效的配置及每一项的来源
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveConfig {
+    pub config: AppConfig,
+    pub sources: BTreeMap<String, ConfigSource>,
+}
+
 // 全局配置实例
 lazy_static! {
     pub static ref CONFIG: RwLock<AppConfig> = RwLock::new(AppConfig::default());
+    static ref CONFIG_SOURCES: RwLock<BTreeMap<String, ConfigSource>> =
+        RwLock::new(BTreeMap::new());
+    // 配置变更通知
+    static ref CONFIG_CHANGES: watch::Sender<AppConfig> = watch::channel(AppConfig::default()).0;
+}
+
+// 配置文件轮询间隔
+const CONFIG_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+// 初始化配置: 默认值 -> 配置文件 -> 环境变量，后面的覆盖前面的
+pub fn init_config(app_dir: &Path) -> Result<(), String> {
+    // .env 文件不存在时忽略
+    let _ = dotenv::dotenv();
+    reload_config(app_dir)
 }
 
-// 初始化配置
-pub fn init_config() -> Result<(), String> {
+// 重新加载配置并通知订阅者，加载失败时保留当前配置
+pub fn reload_config(app_dir: &Path) -> Result<(), String> {
+    let file = read_config_file(&app_dir.join(CONFIG_FILE_NAME))?;
+    let (config, sources) = load_layers(file, env::vars())?;
+
+    *CONFIG_SOURCES.write().unwrap() = sources;
+    publish(config);
+    Ok(())
+}
+
+// 校验并替换当前配置，通知订阅者
+pub fn update_config(config: AppConfig) -> Result<(), String> {
+    config.validate()?;
+    publish(config);
     Ok(())
 }
 
+fn publish(config: AppConfig) {
+    *CONFIG.write().unwrap() = config.clone();
+    CONFIG_CHANGES.send_replace(config);
+}
+
+// 订阅配置变更
+pub fn subscribe() -> watch::Receiver<AppConfig> {
+    CONFIG_CHANGES.subscribe()
+}
+
+// 监视配置文件，修改后自动重新加载
+pub async fn watch_config_file(app_dir: PathBuf) {
+    let path = app_dir.join(CONFIG_FILE_NAME);
+    let modified_at = |path: &Path| -> Option<SystemTime> {
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    };
+
+    let mut last_modified = modified_at(&path);
+    let mut ticker = tokio::time::interval(CONFIG_WATCH_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let modified = modified_at(&path);
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        match reload_config(&app_dir) {
+            Ok(()) => info!(target: "config", "配置文件已变更，已重新加载"),
+            Err(e) => error!(target: "config", "重新加载配置失败，保留当前配置: {}", e),
+        }
+    }
+}
+
+// 获取生效的配置
+pub fn get_effective_config() -> EffectiveConfig {
+    EffectiveConfig {
+        config: CONFIG.read().unwrap().clone(),
+        sources: CONFIG_SOURCES.read().unwrap().clone(),
+    }
+}
+
+fn read_config_file(path: &Path) -> Result<Option<Value>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("读取配置文件失败 {:?}: {}", path, e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("配置文件格式错误 {:?}: {}", path, e))
+}
+
+// 按层合并配置，返回合并结果和每一项的来源
+fn load_layers(
+    file: Option<Value>,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<(AppConfig, BTreeMap<String, ConfigSource>), String> {
+    let mut merged =
+        serde_json::to_value(AppConfig::default()).map_err(|e| format!("序列化默认配置失败: {}", e))?;
+
+    let mut leaves = Vec::new();
+    collect_leaves(&merged, "", &mut leaves);
+    let mut sources: BTreeMap<String, ConfigSource> = leaves
+        .iter()
+        .map(|path| (path.clone(), ConfigSource::Default))
+        .collect();
+
+    if let Some(file) = file {
+        // 拼写错误或已废弃的配置项会被静默忽略，直接拒绝
+        let mut file_leaves = Vec::new();
+        collect_leaves(&file, "", &mut file_leaves);
+        let unknown: Vec<String> = file_leaves
+            .into_iter()
+            .filter(|path| !sources.contains_key(path))
+            .collect();
+        if !unknown.is_empty() {
+            return Err(format!("配置文件包含未知配置项: {}", unknown.join(", ")));
+        }
+
+        for path in &leaves {
+            if let Some(value) = file.pointer(&pointer(path)) {
+                set_leaf(&mut merged, path, value.clone());
+                sources.insert(path.clone(), ConfigSource::File);
+            }
+        }
+    }
+
+    let vars: HashMap<String, String> = vars.into_iter().collect();
+    for path in &leaves {
+        let name = env_var_name(path);
+        if let Some(raw) = vars.get(&name) {
+            // 字符串直接使用，其他类型按JSON解析
+            let value = match merged.pointer(&pointer(path)) {
+                Some(Value::String(_)) => Value::String(raw.clone()),
+                _ => serde_json::from_str(raw)
+                    .map_err(|e| format!("环境变量 {} 格式错误: {}", name, e))?,
+            };
+            set_leaf(&mut merged, path, value);
+            sources.insert(path.clone(), ConfigSource::Env);
+        }
+    }
+
+    let config: AppConfig =
+        serde_json::from_value(merged).map_err(|e| format!("配置格式错误: {}", e))?;
+    config.validate()?;
+    Ok((config, sources))
+}
+
+// 收集所有叶子配置项的路径，例如 api.default_api_url
+fn collect_leaves(value: &Value, prefix: &str, leaves: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                collect_leaves(child, &path, leaves);
+            }
+        }
+        _ => leaves.push(prefix.to_string()),
+    }
+}
+
+fn pointer(path: &str) -> String {
+    format!("/{}", path.replace('.', "/"))
+}
+
+fn set_leaf(root: &mut Value, path: &str, value: Value) {
+    if let Some(slot) = root.pointer_mut(&pointer(path)) {
+        *slot = value;
+    }
+}
+
+fn env_var_name(path: &str) -> String {
+    format!("{}{}", ENV_PREFIX, path.replace('.', "__").to_uppercase())
+}
+
 // 辅助函数: 根据操作系统获取当前系统路径配置
 pub fn get_os_paths() -> PathBuf {
     let config = CONFIG.read().unwrap();
@@ -236,6 +636,16 @@ pub fn get_config_file_url() -> String {
     CONFIG.read().unwrap().api.config_file_url.clone()
 }
 
+// 获取配置文件签名 URL，签名文件与配置文件同名并以 .sig 结尾
+pub fn get_config_signature_url() -> String {
+    format!("{}.sig", get_config_file_url())
+}
+
+// 获取配置文件缓存有效期
+pub fn get_config_ttl() -> Duration {
+    Duration::from_secs(CONFIG.read().unwrap().api.config_ttl_secs)
+}
+
 // 获取请求超时时间
 pub fn get_request_timeout() -> Duration {
     Duration::from_secs(CONFIG.read().unwrap().timeouts.request_timeout_secs)
@@ -246,6 +656,16 @@ pub fn get_ping_timeout() -> Duration {
     Duration::from_millis(CONFIG.read().unwrap().timeouts.ping_timeout_ms)
 }
 
+// 获取API配置
+pub fn get_api_config() -> ApiConfig {
+    CONFIG.read().unwrap().api.clone()
+}
+
+// 获取线路测速配置
+pub fn get_probe_config() -> ProbeConfig {
+    CONFIG.read().unwrap().probe.clone()
+}
+
 // 检查是否为公共端点
 pub fn is_public_endpoint(url: &str) -> bool {
     let config = CONFIG.read().unwrap();
@@ -263,6 +683,28 @@ pub fn is_public_endpoint(url: &str) -> bool {
     false
 }
 
+// 获取请求重试策略
+pub fn get_retry_policy() -> RetryPolicy {
+    let config = CONFIG.read().unwrap();
+    RetryPolicy {
+        max_attempts: config.retry.max_attempts,
+        initial_delay: Duration::from_millis(config.retry.initial_delay_ms),
+        max_delay: Duration::from_millis(config.retry.max_delay_ms),
+        max_elapsed: Some(Duration::from_secs(config.retry.max_elapsed_secs)),
+        ..RetryPolicy::default()
+    }
+}
+
+// 检查 POST 接口是否可以安全重试，按请求路径匹配，规则与拦截器的路由相同
+pub fn is_idempotent_endpoint(path: &str) -> bool {
+    let config = CONFIG.read().unwrap();
+    config
+        .retry
+        .idempotent_endpoints
+        .iter()
+        .any(|endpoint| RoutePattern::parse(endpoint).matches(path))
+}
+
 // 获取调度器配置
 pub fn get_scheduler_config() -> SchedulerConfig {
     CONFIG.read().unwrap().scheduler.clone()
@@ -273,23 +715,79 @@ pub fn get_account_usage_threshold() -> f64 {
     CONFIG.read().unwrap().scheduler.account_usage_threshold
 }
 
-// 获取数据库键
-pub fn get_db_key(key_name: &str) -> String {
-    let config = CONFIG.read().unwrap();
-    match key_name {
-        "dashboard_refresh_interval" => config.db_keys.dashboard_refresh_interval_key.clone(),
-        "account_limit_check_interval" => config.db_keys.account_limit_check_interval_key.clone(),
-        "account_usage_threshold" => config.db_keys.account_usage_threshold_key.clone(),
-        "inbound_config" => config.db_keys.inbound_config_key.clone(),
-        "current_inbound" => config.db_keys.current_inbound_key.clone(),
-        "cursor_main_js_path" => config.db_keys.cursor_main_js_path_key.clone(),
-        "token" => config.db_keys.token_key.clone(),
-        "lang" => config.db_keys.lang_key.clone(),
-        _ => panic!("Unknown key name: {}", key_name),
-    }
+// 获取表示登录失效的响应码
+pub fn get_auth_failure_codes() -> Vec<i64> {
+    CONFIG.read().unwrap().api.auth_failure_codes.clone()
 }
 
 // 获取是否验证SSL证书的配置
 pub fn get_verify_ssl() -> bool {
     CONFIG.read().unwrap().api.verify_ssl
 }
+
+// 是否严格解码后端响应
+pub fn get_strict_decoding() -> bool {
+    CONFIG.read().unwrap().api.strict_decoding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_defaults_only() {
+        let (config, sources) = load_layers(None, Vec::new()).unwrap();
+        assert_eq!(config.api.default_api_url, AppConfig::default().api.default_api_url);
+        assert!(sources.values().all(|s| *s == ConfigSource::Default));
+        assert!(sources.contains_key("scheduler.account_usage_threshold"));
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let file = json!({
+            "api": { "default_api_url": "https://file.example.com", "verify_ssl": false },
+            "timeouts": { "ping_timeout_ms": 1000 }
+        });
+        let env = vars(&[
+            ("CURSOR_POOL_API__DEFAULT_API_URL", "https://env.example.com"),
+            ("CURSOR_POOL_SCHEDULER__ACCOUNT_USAGE_THRESHOLD", "0.2"),
+        ]);
+
+        let (config, sources) = load_layers(Some(file), env).unwrap();
+        assert_eq!(config.api.default_api_url, "https://env.example.com");
+        assert!(!config.api.verify_ssl);
+        assert_eq!(config.timeouts.ping_timeout_ms, 1000);
+        assert_eq!(config.scheduler.account_usage_threshold, 0.2);
+        assert_eq!(sources["api.default_api_url"], ConfigSource::Env);
+        assert_eq!(sources["api.verify_ssl"], ConfigSource::File);
+        assert_eq!(sources["timeouts.request_timeout_secs"], ConfigSource::Default);
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        let file = json!({ "scheduler": { "account_usage_threshold": 2.0 } });
+        assert!(load_layers(Some(file), Vec::new()).is_err());
+
+        let env = vars(&[("CURSOR_POOL_TIMEOUTS__PING_TIMEOUT_MS", "fast")]);
+        assert!(load_layers(None, env).is_err());
+
+        let file = json!({ "api": { "config_file_url": "oss://bucket/config.json" } });
+        assert!(load_layers(Some(file), Vec::new()).is_err());
+
+        let file = json!({ "api": { "spki_pins": ["pool.example.com:md5/AAAA"] } });
+        assert!(load_layers(Some(file), Vec::new()).is_err());
+
+        // 未知或拼写错误的配置项
+        let file = json!({ "api": { "verify_sll": false } });
+        let err = load_layers(Some(file), Vec::new()).unwrap_err();
+        assert!(err.contains("api.verify_sll"));
+    }
+}
@@ -1,5 +1,8 @@
 use crate::api::client::ApiClient;
+use crate::config;
 use crate::database::Database;
+use crate::error::{AppError, ErrorCode};
+use crate::settings::SettingKey;
 use crate::utils::hook::Hook;
 use crate::utils::id_generator::generate_new_ids;
 use crate::utils::paths::AppPaths;
@@ -20,7 +23,7 @@ const CURSOR_SLEEP_DURATION: Duration = Duration::from_millis(500);
 
 /// 终止 Cursor 进程
 #[tauri::command]
-pub async fn close_cursor() -> Result<bool, String> {
+pub async fn close_cursor() -> Result<bool, AppError> {
     let process_manager = ProcessManager::new();
 
     // 检查Cursor是否在运行
@@ -31,7 +34,7 @@ pub async fn close_cursor() -> Result<bool, String> {
     // 关闭Cursor进程
     if let Err(e) = process_manager.kill_cursor_processes() {
         error!(target: "cursor", "关闭Cursor进程失败: {}", e);
-        return Err(e);
+        return Err(AppError::process("关闭Cursor进程失败").with_details(e));
     }
 
     // 等待进程完全关闭
@@ -43,9 +46,9 @@ pub async fn close_cursor() -> Result<bool, String> {
 
     // 检查是否成功关闭
     if process_manager.is_cursor_running() {
-        let err_msg = "无法完全关闭Cursor进程".to_string();
+        let err_msg = "无法完全关闭Cursor进程";
         error!(target: "cursor", "{}", err_msg);
-        return Err(err_msg);
+        return Err(AppError::process(err_msg));
     }
 
     Ok(true)
@@ -53,8 +56,8 @@ pub async fn close_cursor() -> Result<bool, String> {
 
 /// 启动 Cursor 应用
 #[tauri::command]
-pub async fn launch_cursor(db: State<'_, Database>) -> Result<bool, String> {
-    let paths = match AppPaths::new_with_db(Some(&db)) {
+pub async fn launch_cursor(db: State<'_, Database>) -> Result<bool, AppError> {
+    let paths = match app_paths(&db) {
         Ok(p) => p,
         Err(e) => {
             error!(target: "cursor", "获取应用路径失败: {}", e);
@@ -65,7 +68,7 @@ pub async fn launch_cursor(db: State<'_, Database>) -> Result<bool, String> {
     // 启动Cursor
     if let Err(e) = paths.launch_cursor() {
         error!(target: "cursor", "启动Cursor失败: {}", e);
-        return Err(e);
+        return Err(AppError::process("启动Cursor失败").with_details(e));
     }
 
     Ok(true)
@@ -78,13 +81,13 @@ pub async fn reset_machine_id(
     db: State<'_, Database>,
     force_kill: bool,
     machine_id: Option<String>,
-) -> Result<bool, String> {
+) -> Result<bool, AppError> {
     let process_manager = ProcessManager::new();
 
     // 检查Cursor进程
     if !force_kill && process_manager.is_cursor_running() {
         error!(target: "reset", "重置失败: Cursor进程正在运行且没有强制关闭选项");
-        return Err("Cursor进程正在运行, 请先关闭Cursor".to_string());
+        return Err(AppError::cursor_running());
     }
 
     // 如果force_kill为true, 则强制终止Cursor进程
@@ -102,12 +105,12 @@ pub async fn reset_machine_id(
                     Some("low".to_string()),
                 )
                 .await;
-                return Err(e);
+                return Err(AppError::process("强制终止Cursor进程失败").with_details(e));
             }
         }
     }
 
-    let paths = match AppPaths::new_with_db(Some(&db)) {
+    let paths = match app_paths(&db) {
         Ok(p) => p,
         Err(e) => {
             // 上报错误
@@ -115,7 +118,7 @@ pub async fn reset_machine_id(
             ErrorReporter::report_error(
                 client.clone(),
                 "reset_machine_id",
-                &e,
+                &e.to_string(),
                 None,
                 Some("low".to_string()),
             )
@@ -150,7 +153,7 @@ pub async fn reset_machine_id(
                     Some("low".to_string()),
                 )
                 .await;
-                return Err(err);
+                return Err(AppError::filesystem(err));
             }
         };
 
@@ -167,7 +170,7 @@ pub async fn reset_machine_id(
                     Some("low".to_string()),
                 )
                 .await;
-                return Err(err);
+                return Err(AppError::filesystem(err));
             }
         }
     } else {
@@ -203,7 +206,7 @@ pub async fn reset_machine_id(
             Some("low".to_string()),
         )
         .await;
-        return Err(err);
+        return Err(AppError::filesystem(err));
     }
 
     let storage_content_str = match serde_json::to_string_pretty(&storage_content) {
@@ -219,7 +222,7 @@ pub async fn reset_machine_id(
                 Some("low".to_string()),
             )
             .await;
-            return Err(err);
+            return Err(AppError::filesystem(err));
         }
     };
 
@@ -257,7 +260,7 @@ pub async fn reset_machine_id(
             Some("low".to_string()),
         )
         .await;
-        return Err(err);
+        return Err(AppError::filesystem(err));
     }
 
     // 更新数据库
@@ -279,7 +282,7 @@ pub async fn reset_machine_id(
                 Some("low".to_string()),
             )
             .await;
-            return Err(e);
+            return Err(AppError::new(ErrorCode::CursorDbError, "更新Cursor数据库失败").with_details(e));
         }
         error!(target: "reset", "成功更新数据库中的设备ID信息");
     } else {
@@ -297,26 +300,26 @@ pub async fn switch_account(
     token: String,
     force_kill: bool,
     db: tauri::State<'_, crate::database::Database>,
-) -> Result<bool, String> {
+) -> Result<bool, AppError> {
     error!(target: "account", "开始切换账号到: {}", email);
     let process_manager = ProcessManager::new();
 
     // 检查Cursor进程
     if !force_kill && process_manager.is_cursor_running() {
         error!(target: "account", "切换账号失败: Cursor进程正在运行且没有强制关闭选项");
-        return Err("Cursor进程正在运行, 请先关闭Cursor".to_string());
+        return Err(AppError::cursor_running());
     }
 
     // 如果force_kill为true, 则强制终止Cursor进程
     if force_kill {
         if let Err(e) = process_manager.kill_cursor_processes() {
             error!(target: "account", "强制终止Cursor进程失败: {}", e);
-            return Err(e);
+            return Err(AppError::process("强制终止Cursor进程失败").with_details(e));
         }
         error!(target: "account", "已强制终止Cursor进程");
     }
 
-    let paths = match AppPaths::new_with_db(Some(&db)) {
+    let paths = match app_paths(&db) {
         Ok(p) => p,
         Err(e) => {
             error!(target: "account", "获取应用路径失败: {}", e);
@@ -378,7 +381,7 @@ pub async fn switch_account(
 
     // 检查当前账户是否已在历史记录中
     let account_exists_in_history = if !current_email.is_empty() {
-        match db.get_item("user.history.accounts") {
+        match db.get_item(&SettingKey::HistoryAccounts.db_key()).await {
             Ok(Some(data)) => {
                 match serde_json::from_str::<Vec<crate::api::types::HistoryAccountRecord>>(&data) {
                     Ok(accounts) => accounts.iter().any(|a| a.email == current_email),
@@ -439,7 +442,7 @@ pub async fn switch_account(
 
     if let Err(e) = update_database(&paths.db, &account_updates) {
         error!(target: "account", "更新数据库失败: {}", e);
-        return Err(e);
+        return Err(AppError::new(ErrorCode::CursorDbError, "更新Cursor数据库失败").with_details(e));
     }
     error!(target: "account", "成功更新数据库中的账户信息");
 
@@ -449,7 +452,7 @@ pub async fn switch_account(
     let machine_id = result["machineId"].as_str().unwrap_or_default().to_string();
 
     // ### 检查新账户是否需要保存 ###
-    let new_account_exists_in_history = match db.get_item("user.history.accounts") {
+    let new_account_exists_in_history = match db.get_item(&SettingKey::HistoryAccounts.db_key()).await {
         Ok(Some(data)) => {
             match serde_json::from_str::<Vec<crate::api::types::HistoryAccountRecord>>(&data) {
                 Ok(accounts) => accounts.iter().any(|a| a.email == email),
@@ -485,7 +488,7 @@ pub async fn switch_account(
         }
     } else {
         // 如果账户已存在但token可能更新了，更新历史记录
-        if let Ok(Some(data)) = db.get_item("user.history.accounts") {
+        if let Ok(Some(data)) = db.get_item(&SettingKey::HistoryAccounts.db_key()).await {
             if let Ok(mut accounts) =
                 serde_json::from_str::<Vec<crate::api::types::HistoryAccountRecord>>(&data)
             {
@@ -506,7 +509,7 @@ pub async fn switch_account(
 
                 // 保存更新后的记录
                 if let Ok(json_data) = serde_json::to_string(&accounts) {
-                    if let Err(e) = db.set_item("user.history.accounts", &json_data) {
+                    if let Err(e) = db.set_item(&SettingKey::HistoryAccounts.db_key(), &json_data).await {
                         error!(target: "account", "更新历史账户记录失败: {}", e);
                     } else {
                         error!(target: "account", "成功更新账户 {} 的历史记录", email);
@@ -529,18 +532,17 @@ pub async fn switch_account(
 
 /// 获取设备标识符和当前账号信息
 #[tauri::command]
-pub async fn get_machine_ids(db: State<'_, Database>) -> Result<Value, String> {
+pub async fn get_machine_ids(db: State<'_, Database>) -> Result<Value, AppError> {
     // 使用通用重试函数替代手动重试逻辑
     retry::retry(
         || async { try_get_machine_ids(&db) },
-        3,
-        Duration::from_millis(500),
+        &config::get_retry_policy(),
         "获取机器码"
     ).await
 }
 
-fn try_get_machine_ids(db: &Database) -> Result<Value, String> {
-    let paths = match AppPaths::new_with_db(Some(db)) {
+fn try_get_machine_ids(db: &Database) -> Result<Value, AppError> {
+    let paths = match app_paths(db) {
         Ok(p) => p,
         Err(e) => {
             error!(target: "machine_id", "获取应用路径失败: {}", e);
@@ -601,21 +603,26 @@ fn try_get_machine_ids(db: &Database) -> Result<Value, String> {
 
 /// 检查 Cursor 进程状态
 #[tauri::command]
-pub fn check_cursor_running() -> Result<bool, String> {
+pub fn check_cursor_running() -> Result<bool, AppError> {
     let process_manager = ProcessManager::new();
     Ok(process_manager.is_cursor_running())
 }
 
 /// 检查管理员权限
 #[tauri::command]
-pub fn check_admin_privileges() -> Result<bool, String> {
-    crate::utils::check_admin_privileges()
+pub fn check_admin_privileges() -> Result<bool, AppError> {
+    crate::utils::check_admin_privileges().map_err(AppError::permission)
 }
 
 /// 请求管理员权限
 #[tauri::command]
-pub fn request_admin_privileges(exe_path: String) -> Result<bool, String> {
-    crate::utils::privileges::request_admin_privileges(&exe_path)
+pub fn request_admin_privileges(exe_path: String) -> Result<bool, AppError> {
+    crate::utils::privileges::request_admin_privileges(&exe_path).map_err(AppError::permission)
+}
+
+// 获取应用路径，找不到 main.js 时返回 MainJsNotFound 让前端提示用户选择路径
+fn app_paths(db: &Database) -> Result<AppPaths, AppError> {
+    AppPaths::new_with_db(Some(db)).map_err(AppError::from)
 }
 
 /// 更新数据库键值对
@@ -675,8 +682,8 @@ fn update_database(
 
 /// 检查 main.js 注入状态
 #[tauri::command]
-pub async fn is_hook(db: State<'_, Database>) -> Result<bool, String> {
-    let paths = match AppPaths::new_with_db(Some(&db)) {
+pub async fn is_hook(db: State<'_, Database>) -> Result<bool, AppError> {
+    let paths = match app_paths(&db) {
         Ok(p) => p,
         Err(e) => {
             error!(target: "hook", "获取应用路径失败: {}", e);
@@ -687,9 +694,8 @@ pub async fn is_hook(db: State<'_, Database>) -> Result<bool, String> {
     let content = match fs::read_to_string(&paths.main_js) {
         Ok(c) => c,
         Err(e) => {
-            let err_msg = format!("读取 main.js 失败: {}", e);
-            error!(target: "hook", "{}", err_msg);
-            return Err(err_msg);
+            error!(target: "hook", "读取 main.js 失败: {}", e);
+            return Err(AppError::filesystem("读取 main.js 失败").with_details(e));
         }
     };
 
@@ -711,13 +717,13 @@ pub async fn hook_main_js(
     client: State<'_, ApiClient>,
     db: State<'_, Database>,
     force_kill: bool,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let process_manager = ProcessManager::new();
 
     // 检查 Cursor 进程
     if !force_kill && process_manager.is_cursor_running() {
         error!(target: "hook", "注入失败: Cursor进程正在运行且没有强制关闭选项");
-        return Err("Cursor进程正在运行, 请先关闭Cursor".to_string());
+        return Err(AppError::cursor_running());
     }
 
     // 如果 force_kill 为 true, 则强制终止 Cursor 进程
@@ -737,7 +743,7 @@ pub async fn hook_main_js(
                     Some("medium".to_string()),
                 )
                 .await;
-                return Err(e);
+                return Err(AppError::process("强制终止Cursor进程失败").with_details(e));
             }
         }
     }
@@ -757,13 +763,13 @@ pub async fn restore_hook(
     client: State<'_, ApiClient>,
     db: State<'_, Database>,
     force_kill: bool,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let process_manager = ProcessManager::new();
 
     // 检查 Cursor 进程
     if !force_kill && process_manager.is_cursor_running() {
         error!(target: "hook", "恢复失败: Cursor进程正在运行且没有强制关闭选项");
-        return Err("Cursor进程正在运行, 请先关闭Cursor".to_string());
+        return Err(AppError::cursor_running());
     }
 
     // 如果 force_kill 为 true, 则强制终止 Cursor 进程
@@ -783,7 +789,7 @@ pub async fn restore_hook(
                     Some("medium".to_string()),
                 )
                 .await;
-                return Err(e);
+                return Err(AppError::process("强制终止Cursor进程失败").with_details(e));
             }
         }
     }
@@ -809,7 +815,7 @@ pub async fn find_cursor_path(
     client: State<'_, ApiClient>,
     db: State<'_, Database>,
     selected_path: String,
-) -> Result<bool, String> {
+) -> Result<bool, AppError> {
     error!(target: "path", "正在查找Cursor路径: {}", selected_path);
 
     // 尝试从选择的路径找到main.js
@@ -828,7 +834,7 @@ pub async fn find_cursor_path(
                 Some("low".to_string()),
             )
             .await;
-            return Err(e);
+            return Err(AppError::main_js_not_found(e));
         }
     };
 
@@ -838,9 +844,9 @@ pub async fn find_cursor_path(
             .file_name()
             .map_or(false, |name| name != "main.js")
     {
-        let err_msg = "选择的路径不包含有效的main.js文件".to_string();
+        let err_msg = "选择的路径不包含有效的main.js文件";
         error!(target: "path", "{}", err_msg);
-        return Err(err_msg);
+        return Err(AppError::main_js_not_found(err_msg));
     }
 
     // 保存路径到数据库
@@ -854,7 +860,7 @@ pub async fn find_cursor_path(
             Some("low".to_string()),
         )
         .await;
-        return Err(e);
+        return Err(AppError::database(e));
     }
 
     error!(target: "path", "成功验证并保存Cursor路径");
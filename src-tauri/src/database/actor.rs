@@ -0,0 +1,195 @@
+use super::DatabaseError;
+use rusqlite::Connection;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, JoinHandle, Thread};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::sync::oneshot;
+use tracing::{error, info};
+
+// 预编译语句缓存容量
+const STATEMENT_CACHE_CAPACITY: usize = 32;
+
+type Job = Box<dyn FnOnce(&mut Connection) + Send>;
+
+enum Message {
+    Run(Job),
+    // 执行完之前提交的操作后关闭连接
+    Close,
+}
+
+/// 数据库连接线程句柄，所有查询都在独立线程中顺序执行
+#[derive(Debug, Clone)]
+pub struct DbActor {
+    sender: mpsc::Sender<Message>,
+    thread: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl DbActor {
+    /// 将连接移交给独立线程
+    pub fn spawn(connection: Connection) -> Result<Self, DatabaseError> {
+        let (sender, receiver) = mpsc::channel::<Message>();
+        connection.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+
+        let thread = thread::Builder::new()
+            .name("database".to_string())
+            .spawn(move || {
+                let mut connection = connection;
+                // 收到关闭指令或所有句柄释放后退出循环并关闭连接
+                for message in receiver {
+                    match message {
+                        Message::Run(job) => job(&mut connection),
+                        Message::Close => break,
+                    }
+                }
+                match connection.close() {
+                    Ok(()) => info!(target: "database", "数据库连接已关闭"),
+                    Err((_, e)) => error!(target: "database", "关闭数据库连接失败: {}", e),
+                }
+            })
+            .map_err(DatabaseError::Io)?;
+
+        Ok(Self {
+            sender,
+            thread: Arc::new(Mutex::new(Some(thread))),
+        })
+    }
+
+    /// 等待已提交的操作完成后关闭连接，之后的操作都返回 Closed
+    pub fn close(&self) -> Result<(), DatabaseError> {
+        let thread = match self.thread.lock().unwrap().take() {
+            Some(thread) => thread,
+            None => return Ok(()),
+        };
+        let _ = self.sender.send(Message::Close);
+        thread.join().map_err(|_| DatabaseError::Closed)
+    }
+
+    fn submit<R, F>(&self, f: F) -> Result<oneshot::Receiver<Result<R, DatabaseError>>, DatabaseError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<R, DatabaseError> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(Message::Run(Box::new(move |conn| {
+                // 单个操作 panic 不影响数据库线程，调用方收到 Panicked
+                let result = catch_unwind(AssertUnwindSafe(|| f(conn))).unwrap_or_else(|_| {
+                    error!(target: "database", "数据库任务执行时发生panic");
+                    Err(DatabaseError::Panicked)
+                });
+                let _ = tx.send(result);
+            })))
+            .map_err(|_| DatabaseError::Closed)?;
+        Ok(rx)
+    }
+
+    /// 在数据库线程中执行操作并异步等待结果
+    pub async fn call<R, F>(&self, f: F) -> Result<R, DatabaseError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<R, DatabaseError> + Send + 'static,
+    {
+        self.submit(f)?.await.map_err(|_| DatabaseError::Closed)?
+    }
+}
+
+/// 在同步代码中等待数据库操作完成
+///
+/// 操作由数据库线程完成，等待时不依赖异步运行时；在多线程运行时的工作线程中调用时会先让出工作线程
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| park_on(future))
+        }
+        _ => park_on(future),
+    }
+}
+
+// 唤醒等待结果的线程
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// 在当前线程中轮询直到完成，未就绪时挂起线程
+fn park_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actor() -> DbActor {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE item (key TEXT PRIMARY KEY, value TEXT NOT NULL)", [])
+            .unwrap();
+        DbActor::spawn(conn).unwrap()
+    }
+
+    fn count(actor: &DbActor) -> Result<i64, DatabaseError> {
+        block_on(actor.call(|conn| {
+            Ok(conn.query_row("SELECT COUNT(*) FROM item", [], |row| row.get(0))?)
+        }))
+    }
+
+    #[test]
+    fn test_block_on_without_runtime() {
+        let actor = actor();
+        block_on(actor.call(|conn| Ok(conn.execute("INSERT INTO item VALUES ('a', '1')", [])?)))
+            .unwrap();
+        assert_eq!(count(&actor).unwrap(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_block_on_in_multi_thread_runtime() {
+        let actor = actor();
+        assert_eq!(count(&actor).unwrap(), 0);
+        let inserted = actor
+            .call(|conn| Ok(conn.execute("INSERT INTO item VALUES ('a', '1')", [])?))
+            .await
+            .unwrap();
+        assert_eq!(inserted, 1);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_in_current_thread_runtime() {
+        let actor = actor();
+        assert_eq!(count(&actor).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_close_waits_for_pending_jobs() {
+        let actor = actor();
+        let pending = actor
+            .submit(|conn| Ok(conn.execute("INSERT INTO item VALUES ('a', '1')", [])?))
+            .unwrap();
+        actor.close().unwrap();
+        assert_eq!(pending.blocking_recv().unwrap().unwrap(), 1);
+        assert!(matches!(count(&actor), Err(DatabaseError::Closed)));
+        // 重复关闭不报错
+        actor.close().unwrap();
+    }
+
+    #[test]
+    fn test_panic_does_not_kill_actor() {
+        let actor = actor();
+        let result: Result<(), DatabaseError> = block_on(actor.call(|_| panic!("boom")));
+        assert!(matches!(result, Err(DatabaseError::Panicked)));
+        assert_eq!(count(&actor).unwrap(), 0);
+    }
+}
@@ -0,0 +1,49 @@
+use super::migrations::MigrationError;
+use std::fmt;
+
+/// 数据库错误
+#[derive(Debug)]
+pub enum DatabaseError {
+    // SQLite 执行错误
+    Sqlite(rusqlite::Error),
+    // 数据库迁移错误
+    Migration(MigrationError),
+    // 敏感数据加解密错误
+    Secrets(String),
+    // 文件系统错误
+    Io(std::io::Error),
+    // 数据库线程已退出
+    Closed,
+    // 数据库操作执行时发生 panic，连接仍然可用
+    Panicked,
+    // 数据库文件损坏
+    Corrupted(String),
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::Sqlite(e) => write!(f, "数据库错误: {}", e),
+            DatabaseError::Migration(e) => write!(f, "{}", e),
+            DatabaseError::Secrets(msg) => write!(f, "数据加密错误: {}", msg),
+            DatabaseError::Io(e) => write!(f, "数据库文件操作失败: {}", e),
+            DatabaseError::Closed => write!(f, "数据库连接已关闭"),
+            DatabaseError::Panicked => write!(f, "数据库操作异常终止"),
+            DatabaseError::Corrupted(detail) => write!(f, "数据库文件已损坏: {}", detail),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<rusqlite::Error> for DatabaseError {
+    fn from(e: rusqlite::Error) -> Self {
+        DatabaseError::Sqlite(e)
+    }
+}
+
+impl From<MigrationError> for DatabaseError {
+    fn from(e: MigrationError) -> Self {
+        DatabaseError::Migration(e)
+    }
+}
@@ -0,0 +1,14 @@
+-- cursor_pool.db as created by 1.8.x (no schema_version table)
+CREATE TABLE item (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE account (
+    account TEXT PRIMARY KEY,
+    userId TEXT NOT NULL,
+    cursorToken TEXT NOT NULL
+);
+INSERT INTO item (key, value) VALUES ('user.info.token', 'legacy-token');
+INSERT INTO item (key, value) VALUES ('user.info.lang', 'en-US');
+INSERT INTO item (key, value) VALUES ('system.scheduler.dashboard_refresh_interval', '300');
+INSERT INTO account (account, userId, cursorToken) VALUES ('legacy@example.com', 'user_01', 'legacy-cursor-token');
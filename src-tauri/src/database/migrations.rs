@@ -0,0 +1,314 @@
+use rusqlite::{params, Connection, Transaction};
+use std::fmt;
+use tracing::{error, info};
+
+/// 单个数据库迁移
+pub struct Migration {
+    /// 迁移版本号，必须严格递增
+    pub version: u32,
+    /// 迁移说明
+    pub description: &'static str,
+    /// 迁移内容，必须是幂等的
+    pub up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// 迁移错误
+#[derive(Debug)]
+pub enum MigrationError {
+    // SQLite 执行错误
+    Sqlite(rusqlite::Error),
+    // 数据库版本高于当前程序支持的版本
+    UnsupportedVersion { found: u32, supported: u32 },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "数据库迁移失败: {}", e),
+            MigrationError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "数据库版本 ({}) 高于当前程序支持的版本 ({})，请升级到最新版本后再运行",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+/// 所有迁移，按版本号顺序排列
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "创建 item 和 account 表",
+        up: create_initial_tables,
+    },
+    Migration {
+        version: 2,
+        description: "创建 inbound_probe 表",
+        up: create_inbound_probe_table,
+    },
+    Migration {
+        version: 3,
+        description: "创建 task_run 表",
+        up: create_task_run_table,
+    },
+    Migration {
+        version: 4,
+        description: "创建 usage_alert 表",
+        up: create_usage_alert_table,
+    },
+];
+
+/// 当前程序支持的最高数据库版本
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+// v1: 1.8.x 及更早版本在启动时直接创建的表结构
+fn create_initial_tables(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS item (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS account (
+            account TEXT PRIMARY KEY,
+            userId TEXT NOT NULL,
+            cursorToken TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// v2: 保存线路测速结果
+fn create_inbound_probe_table(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS inbound_probe (
+            url TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            rank INTEGER NOT NULL,
+            median_ms INTEGER,
+            success_rate REAL NOT NULL,
+            samples INTEGER NOT NULL,
+            probed_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// v3: 保存定时任务的运行历史
+fn create_task_run_table(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS task_run (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id TEXT NOT NULL,
+            status TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            error TEXT
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_task_run_task_id ON task_run (task_id, id)",
+        [],
+    )?;
+    Ok(())
+}
+
+// v4: 已发送的用量警告，避免每次检查和重启后重复提醒
+fn create_usage_alert_table(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS usage_alert (
+            account TEXT NOT NULL,
+            model TEXT NOT NULL,
+            level REAL NOT NULL,
+            cycle_start TEXT NOT NULL,
+            fired_at TEXT NOT NULL,
+            PRIMARY KEY (account, model, level)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// 读取数据库当前版本，未记录版本的旧数据库视为 0
+pub fn current_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// 在一个事务中执行所有未应用的迁移，返回迁移后的版本
+pub fn run_migrations(conn: &mut Connection) -> Result<u32, MigrationError> {
+    run_migrations_with(conn, MIGRATIONS)
+}
+
+fn run_migrations_with(
+    conn: &mut Connection,
+    migrations: &[Migration],
+) -> Result<u32, MigrationError> {
+    let supported = migrations.last().map_or(0, |m| m.version);
+    let found = current_version(conn)?;
+
+    if found > supported {
+        error!(target: "database", "数据库版本过高 - 当前: {}, 支持: {}", found, supported);
+        return Err(MigrationError::UnsupportedVersion { found, supported });
+    }
+
+    let pending: Vec<&Migration> = migrations.iter().filter(|m| m.version > found).collect();
+    if pending.is_empty() {
+        return Ok(found);
+    }
+
+    let tx = conn.transaction()?;
+    for migration in pending {
+        info!(
+            target: "database",
+            "执行数据库迁移 v{}: {}",
+            migration.version, migration.description
+        );
+        (migration.up)(&tx).map_err(|e| {
+            error!(target: "database", "数据库迁移 v{} 失败: {}", migration.version, e);
+            e
+        })?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)",
+            params![
+                migration.version,
+                migration.description,
+                chrono::Local::now().to_rfc3339()
+            ],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(supported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1.8.x 版本创建的数据库
+    const FIXTURE_V1_8: &str = include_str!("fixtures/v1_8.sql");
+
+    fn open_fixture(sql: &str) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(sql).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_migrate_fresh_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(run_migrations(&mut conn).unwrap(), latest_version());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn test_migrate_legacy_database_keeps_data() {
+        let mut conn = open_fixture(FIXTURE_V1_8);
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        run_migrations(&mut conn).unwrap();
+
+        let token: String = conn
+            .query_row(
+                "SELECT value FROM item WHERE key = 'user.info.token'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(token, "legacy-token");
+        let accounts: i64 = conn
+            .query_row("SELECT COUNT(*) FROM account", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(accounts, 1);
+    }
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let mut conn = open_fixture(FIXTURE_V1_8);
+        run_migrations(&mut conn).unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        let rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, MIGRATIONS.len() as i64);
+    }
+
+    #[test]
+    fn test_refuse_newer_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?, 'future', '')",
+            params![latest_version() + 1],
+        )
+        .unwrap();
+
+        match run_migrations(&mut conn) {
+            Err(MigrationError::UnsupportedVersion { found, supported }) => {
+                assert_eq!(found, latest_version() + 1);
+                assert_eq!(supported, latest_version());
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        fn broken(tx: &Transaction) -> rusqlite::Result<()> {
+            tx.execute("CREATE TABLE rollback_probe (id INTEGER)", [])?;
+            tx.execute("NOT VALID SQL", []).map(|_| ())
+        }
+        let migrations = [
+            Migration {
+                version: 1,
+                description: "initial",
+                up: create_initial_tables,
+            },
+            Migration {
+                version: 2,
+                description: "broken",
+                up: broken,
+            },
+        ];
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert!(run_migrations_with(&mut conn, &migrations).is_err());
+        assert_eq!(current_version(&conn).unwrap(), 0);
+        let probe: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'rollback_probe'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(probe, 0);
+    }
+}
@@ -1,242 +1,688 @@
-use rusqlite::{params, Connection, Result as SqliteResult};
-use std::sync::{Arc, Mutex};
+use crate::settings::SettingKey;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
 use tauri::AppHandle;
 use tauri::Manager;
-use tracing::error;
+use tracing::{error, info, warn};
 
+mod actor;
+mod error;
+pub mod migrations;
+pub mod recovery;
+pub mod secrets;
+
+pub use actor::DbActor;
+pub use error::DatabaseError;
+pub use migrations::MigrationError;
+pub use recovery::RecoveryReport;
+pub use secrets::{KeySource, Secrets};
+
+use secrets::Refresh;
+
+/// 数据库操作结果
+pub type DbResult<T> = Result<T, DatabaseError>;
+
+/// 本地数据库句柄
+///
+/// 连接由独立的数据库线程持有，句柄可以在任意线程和异步任务中克隆使用
 #[derive(Debug, Clone)]
 pub struct Database {
-    connection: Arc<Mutex<Connection>>,
+    actor: DbActor,
+    secrets: Arc<Secrets>,
+    // 启动时发生的自动恢复
+    recovery: Option<RecoveryReport>,
+}
+
+/// 数据加密密钥状态
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretsStatus {
+    // 是否有需要口令解锁的密钥
+    pub locked: bool,
+    // 当前密钥是否由口令派生
+    pub passphrase: bool,
+    // 是否有未完成的密钥轮换
+    pub rotation_pending: bool,
+}
+
+/// 单条线路的测速结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LineProbe {
+    pub url: String,
+    pub name: String,
+    // 排名，从 0 开始，越小越好
+    pub rank: usize,
+    // 成功采样的延迟中位数(毫秒)，全部失败时为空
+    pub median_ms: Option<u64>,
+    // 采样成功率(0-1)
+    pub success_rate: f64,
+    // 采样次数
+    pub samples: u32,
+    pub probed_at: String,
+}
+
+/// 定时任务的一次运行记录
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskRun {
+    pub task_id: String,
+    // success, failed, timed_out, panicked
+    pub status: String,
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+/// 已发送的用量警告
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageAlert {
+    pub model: String,
+    // 剩余比例阈值(0-1)
+    pub level: f64,
+    // 发送警告时的用量周期开始时间
+    pub cycle_start: String,
+    pub fired_at: String,
+}
+
+// 每个任务保留的运行记录条数
+const TASK_RUN_HISTORY_LIMIT: i64 = 100;
+
+// 需要加密存储的 item 表键
+fn is_secret_item(key: &str) -> bool {
+    SettingKey::from_db_key(key).map_or(false, SettingKey::is_secret)
+}
+
+fn item_aad(key: &str) -> String {
+    format!("item:{}", key)
+}
+
+fn account_aad(account: &str) -> String {
+    format!("account:{}", account)
 }
 
 impl Database {
-    pub fn new(app_handle: &AppHandle) -> SqliteResult<Self> {
+    pub fn new(app_handle: &AppHandle) -> DbResult<Self> {
         // 获取应用数据目录
-        let app_dir = app_handle
-            .path()
-            .app_data_dir()
-            .expect("无法获取应用数据目录");
+        let app_dir = app_handle.path().app_data_dir().map_err(|e| {
+            error!(target: "database", "无法获取应用数据目录: {}", e);
+            DatabaseError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, e.to_string()))
+        })?;
 
+        Self::open(&app_dir)
+    }
+
+    /// 打开指定目录下的数据库
+    pub fn open(app_dir: &Path) -> DbResult<Self> {
         // 确保目录存在
-        std::fs::create_dir_all(&app_dir).expect("无法创建数据目录");
+        std::fs::create_dir_all(app_dir).map_err(|e| {
+            error!(target: "database", "无法创建数据目录 - 路径: {:?}, 错误: {}", app_dir, e);
+            DatabaseError::Io(e)
+        })?;
 
         // 数据库文件路径
         let db_path = app_dir.join("cursor_pool.db");
 
-        // 创建或打开数据库连接
-        let connection = Connection::open(&db_path).map_err(|e| {
-            error!(target: "database", "打开数据库连接失败 - 路径: {:?}, 错误: {}", db_path, e);
+        // 创建或打开数据库连接，检查完整性并在损坏时自动恢复
+        let backup_dir = app_dir.join("backups");
+        let (mut connection, recovery) =
+            recovery::open_checked(&db_path, &backup_dir).map_err(|e| {
+                error!(target: "database", "打开数据库连接失败 - 路径: {:?}, 错误: {}", db_path, e);
+                e
+            })?;
+
+        // 迁移前定期备份
+        if let Err(e) = recovery::backup_if_due(&connection, &backup_dir) {
+            warn!(target: "database", "创建数据库备份失败: {}", e);
+        }
+
+        // 执行数据库迁移
+        let version = migrations::run_migrations(&mut connection).map_err(|e| {
+            error!(target: "database", "数据库迁移失败: {}", e);
             e
         })?;
+        info!(target: "database", "数据库版本: {}", version);
 
-        // 初始化表结构
-        connection
-            .execute(
-                "CREATE TABLE IF NOT EXISTS item (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            )",
-                [],
-            )
-            .map_err(|e| {
-                error!(target: "database", "创建item表失败: {}", e);
-                e
-            })?;
+        // 加载敏感数据加密密钥
+        let secrets = Secrets::load(&app_dir.join("secret.key")).map_err(|e| {
+            error!(target: "database", "加载数据加密密钥失败: {}", e);
+            DatabaseError::Secrets(e)
+        })?;
 
-        connection
-            .execute(
-                "CREATE TABLE IF NOT EXISTS account (
-                account TEXT PRIMARY KEY,
-                userId TEXT NOT NULL,
-                cursorToken TEXT NOT NULL
-            )",
-                [],
-            )
-            .map_err(|e| {
-                error!(target: "database", "创建account表失败: {}", e);
-                e
-            })?;
+        let db = Self {
+            actor: DbActor::spawn(connection)?,
+            secrets: Arc::new(secrets),
+            recovery,
+        };
 
-        Ok(Self {
-            connection: Arc::new(Mutex::new(connection)),
-        })
+        // 加密旧版本遗留的明文敏感数据，并完成上次中断的密钥轮换
+        if db.secrets.is_locked() {
+            warn!(target: "database", "数据加密密钥未解锁，敏感数据暂不可用");
+        }
+        if let Err(e) = actor::block_on(db.refresh_secrets()) {
+            warn!(target: "database", "重新加密敏感数据失败: {}", e);
+        }
+
+        Ok(db)
     }
 
-    // item表操作
+    /// 启动时的自动恢复结果
+    pub fn recovery_report(&self) -> Option<&RecoveryReport> {
+        self.recovery.as_ref()
+    }
 
-    pub fn set_item(&self, key: &str, value: &str) -> SqliteResult<()> {
-        let conn = self.connection.lock().unwrap();
-        conn.execute(
-            "INSERT OR REPLACE INTO item (key, value) VALUES (?, ?)",
-            params![key, value],
-        )
-        .map_err(|e| {
-            error!(target: "database", "设置数据项失败 - 键: {}, 错误: {}", key, e);
-            e
-        })?;
-        Ok(())
+    /// 在数据库线程中执行自定义操作
+    pub async fn call<R, F>(&self, f: F) -> DbResult<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Connection) -> DbResult<R> + Send + 'static,
+    {
+        self.actor.call(f).await
     }
 
-    pub fn get_item(&self, key: &str) -> SqliteResult<Option<String>> {
-        let conn = self.connection.lock().unwrap();
-        let mut stmt = conn
-            .prepare("SELECT value FROM item WHERE key = ?")
-            .map_err(|e| {
-                error!(target: "database", "准备查询语句失败 - 键: {}, 错误: {}", key, e);
-                e
-            })?;
+    /// 同步调用数据库，供设置层和同步回调等无法等待异步操作的代码使用
+    pub fn blocking(&self) -> BlockingDatabase<'_> {
+        BlockingDatabase(self)
+    }
 
-        let mut rows = stmt.query(params![key]).map_err(|e| {
-            error!(target: "database", "执行查询失败 - 键: {}, 错误: {}", key, e);
-            e
-        })?;
+    /// 等待已提交的写入完成后关闭数据库连接，退出应用时调用
+    pub fn close(&self) -> DbResult<()> {
+        self.actor.close()
+    }
 
-        match rows.next().map_err(|e| {
-            error!(target: "database", "获取查询结果失败 - 键: {}, 错误: {}", key, e);
-            e
-        })? {
-            Some(row) => row.get(0).map(Some).map_err(|e| {
-                error!(target: "database", "读取结果值失败 - 键: {}, 错误: {}", key, e);
-                e
-            }),
-            None => Ok(None),
+    // 敏感数据操作
+
+    /// 将明文和旧密钥加密的敏感数据用当前密钥重新加密，全部完成后移除旧密钥
+    pub async fn refresh_secrets(&self) -> DbResult<usize> {
+        let (count, unavailable) = self.reencrypt_secrets().await?;
+        if count > 0 {
+            info!(target: "database", "已使用当前密钥加密 {} 条敏感数据", count);
         }
+        if unavailable > 0 {
+            warn!(target: "database", "{} 条敏感数据的密钥未解锁，暂不完成密钥轮换", unavailable);
+        } else if self.secrets.has_retiring_key() {
+            self.secrets
+                .retire_old_key()
+                .map_err(DatabaseError::Secrets)?;
+        }
+        Ok(count)
     }
 
-    pub fn delete_item(&self, key: &str) -> SqliteResult<()> {
-        let conn = self.connection.lock().unwrap();
-        conn.execute("DELETE FROM item WHERE key = ?", params![key])
-            .map_err(|e| {
-                error!(target: "database", "删除数据项失败 - 键: {}, 错误: {}", key, e);
-                e
-            })?;
+    /// 加密数据的密钥状态
+    pub fn secrets_status(&self) -> SecretsStatus {
+        SecretsStatus {
+            locked: self.secrets.is_locked(),
+            passphrase: self.secrets.uses_passphrase(),
+            rotation_pending: self.secrets.has_retiring_key(),
+        }
+    }
+
+    /// 使用口令解锁数据加密密钥
+    pub async fn unlock_secrets(&self, passphrase: &str) -> DbResult<()> {
+        self.secrets
+            .unlock(passphrase)
+            .map_err(DatabaseError::Secrets)?;
+        self.refresh_secrets().await?;
         Ok(())
     }
 
-    pub fn get_all_items(&self) -> SqliteResult<Vec<(String, String)>> {
-        let conn = self.connection.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT key, value FROM item").map_err(|e| {
-            error!(target: "database", "准备获取所有数据项查询失败: {}", e);
-            e
-        })?;
+    /// 轮换数据加密密钥，并重新加密所有敏感数据
+    ///
+    /// 新密钥先和旧密钥一起写入密钥文件，重新加密完成后才移除旧密钥，
+    /// 中途失败时下次启动会继续完成轮换
+    pub async fn rotate_secrets_key(&self, source: KeySource) -> DbResult<()> {
+        // 先完成上一次中断的轮换
+        self.refresh_secrets().await?;
+        self.secrets
+            .begin_rotation(source)
+            .map_err(DatabaseError::Secrets)?;
+        self.refresh_secrets().await?;
+        if self.secrets.has_retiring_key() {
+            return Err(DatabaseError::Secrets(
+                "部分数据尚未重新加密，密钥轮换将在下次解锁时完成".to_string(),
+            ));
+        }
+        Ok(())
+    }
 
-        let rows = stmt
-            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
-            .map_err(|e| {
-                error!(target: "database", "执行获取所有数据项查询失败: {}", e);
-                e
-            })?;
+    // 在一个事务中重新加密所有敏感数据，返回更新条数和密钥不可用的条数
+    async fn reencrypt_secrets(&self) -> DbResult<(usize, usize)> {
+        let secrets = self.secrets.clone();
+        let item_keys: Vec<String> = SettingKey::ALL
+            .into_iter()
+            .filter(|key| key.is_secret())
+            .map(SettingKey::db_key)
+            .collect();
+        self.actor.call(move |conn| {
+            let tx = conn.transaction()?;
+            let mut count = 0;
+            let mut unavailable = 0;
+            let mut refresh = |value: &str, aad: &str| -> DbResult<Option<String>> {
+                match secrets.refresh(value, aad).map_err(DatabaseError::Secrets)? {
+                    Refresh::Updated(updated) => {
+                        count += 1;
+                        Ok(Some(updated))
+                    }
+                    Refresh::Unchanged => Ok(None),
+                    Refresh::Unavailable => {
+                        unavailable += 1;
+                        Ok(None)
+                    }
+                }
+            };
+
+            for key in &item_keys {
+                let value: Option<String> = tx
+                    .query_row("SELECT value FROM item WHERE key = ?", params![key], |row| {
+                        row.get(0)
+                    })
+                    .optional()?;
+                if let Some(value) = value {
+                    if let Some(updated) = refresh(&value, &item_aad(key))? {
+                        tx.execute(
+                            "UPDATE item SET value = ? WHERE key = ?",
+                            params![updated, key],
+                        )?;
+                    }
+                }
+            }
 
-        let mut items = Vec::new();
-        for item in rows {
-            match item {
-                Ok(i) => items.push(i),
-                Err(e) => {
-                    error!(target: "database", "获取数据项失败: {}", e);
-                    return Err(e);
+            let accounts: Vec<(String, String)> = {
+                let mut stmt = tx.prepare("SELECT account, cursorToken FROM account")?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+            for (account, token) in accounts {
+                if let Some(updated) = refresh(&token, &account_aad(&account))? {
+                    tx.execute(
+                        "UPDATE account SET cursorToken = ? WHERE account = ?",
+                        params![updated, account],
+                    )?;
                 }
             }
+
+            tx.commit()?;
+            Ok((count, unavailable))
+        }).await
+    }
+
+    // 读取时解密敏感数据，密钥未解锁或解密失败时返回错误，与账户令牌的处理一致
+    fn reveal(&self, value: String, aad: &str) -> DbResult<String> {
+        self.secrets.decrypt(&value, aad).map_err(|e| {
+            warn!(target: "database", "敏感数据不可用: {}", e);
+            DatabaseError::Secrets(e)
+        })
+    }
+
+    // item表操作
+
+    pub async fn set_item(&self, key: &str, value: &str) -> DbResult<()> {
+        let value = if is_secret_item(key) {
+            self.secrets
+                .encrypt(value, &item_aad(key))
+                .map_err(DatabaseError::Secrets)?
+        } else {
+            value.to_string()
+        };
+
+        let key = key.to_string();
+        self.actor.call(move |conn| {
+            conn.prepare_cached("INSERT OR REPLACE INTO item (key, value) VALUES (?, ?)")?
+                .execute(params![key, value])
+                .map_err(|e| {
+                    error!(target: "database", "设置数据项失败 - 键: {}, 错误: {}", key, e);
+                    e
+                })?;
+            Ok(())
+        }).await
+    }
+
+    pub async fn get_item(&self, key: &str) -> DbResult<Option<String>> {
+        let owned_key = key.to_string();
+        let value = self.actor.call(move |conn| {
+            conn.prepare_cached("SELECT value FROM item WHERE key = ?")?
+                .query_row(params![owned_key], |row| row.get::<_, String>(0))
+                .optional()
+                .map_err(|e| {
+                    error!(target: "database", "查询数据项失败 - 键: {}, 错误: {}", owned_key, e);
+                    e.into()
+                })
+        }).await?;
+
+        match value {
+            Some(value) if is_secret_item(key) => self.reveal(value, &item_aad(key)).map(Some),
+            value => Ok(value),
         }
+    }
 
-        Ok(items)
+    pub async fn delete_item(&self, key: &str) -> DbResult<()> {
+        let key = key.to_string();
+        self.actor.call(move |conn| {
+            conn.prepare_cached("DELETE FROM item WHERE key = ?")?
+                .execute(params![key])
+                .map_err(|e| {
+                    error!(target: "database", "删除数据项失败 - 键: {}, 错误: {}", key, e);
+                    e
+                })?;
+            Ok(())
+        }).await
     }
 
-    // account表操作
+    pub async fn get_all_items(&self) -> DbResult<Vec<(String, String)>> {
+        let rows = self.actor.call(|conn| {
+            let mut stmt = conn.prepare_cached("SELECT key, value FROM item")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<Vec<(String, String)>>>()
+                .map_err(|e| {
+                    error!(target: "database", "获取所有数据项失败: {}", e);
+                    e.into()
+                })
+        }).await?;
 
-    pub fn add_account(
-        &self,
-        account: &str,
-        user_id: &str,
-        cursor_token: &str,
-    ) -> SqliteResult<()> {
-        let conn = self.connection.lock().unwrap();
-        conn.execute(
-            "INSERT OR REPLACE INTO account (account, userId, cursorToken) VALUES (?, ?, ?)",
-            params![account, user_id, cursor_token],
-        )
-        .map_err(|e| {
-            error!(target: "database", "添加账户失败 - 账户: {}, 错误: {}", account, e);
-            e
-        })?;
-        Ok(())
+        rows.into_iter()
+            .map(|(key, value)| {
+                if is_secret_item(&key) {
+                    let value = self.reveal(value, &item_aad(&key))?;
+                    Ok((key, value))
+                } else {
+                    Ok((key, value))
+                }
+            })
+            .collect()
     }
 
-    pub fn get_account(&self, account: &str) -> SqliteResult<Option<(String, String, String)>> {
-        let conn = self.connection.lock().unwrap();
-        let mut stmt = conn
-            .prepare("SELECT account, userId, cursorToken FROM account WHERE account = ?")
+    // account表操作
+
+    pub async fn add_account(&self, account: &str, user_id: &str, cursor_token: &str) -> DbResult<()> {
+        let cursor_token = self
+            .secrets
+            .encrypt(cursor_token, &account_aad(account))
+            .map_err(DatabaseError::Secrets)?;
+        let account = account.to_string();
+        let user_id = user_id.to_string();
+        self.actor.call(move |conn| {
+            conn.prepare_cached(
+                "INSERT OR REPLACE INTO account (account, userId, cursorToken) VALUES (?, ?, ?)",
+            )?
+            .execute(params![account, user_id, cursor_token])
             .map_err(|e| {
-                error!(target: "database", "准备查询账户语句失败 - 账户: {}, 错误: {}", account, e);
+                error!(target: "database", "添加账户失败 - 账户: {}, 错误: {}", account, e);
                 e
             })?;
+            Ok(())
+        }).await
+    }
 
-        let mut rows = stmt.query(params![account]).map_err(|e| {
-            error!(target: "database", "执行查询账户失败 - 账户: {}, 错误: {}", account, e);
-            e
-        })?;
+    pub async fn get_account(&self, account: &str) -> DbResult<Option<(String, String, String)>> {
+        let owned_account = account.to_string();
+        let row = self.actor.call(move |conn| {
+            conn.prepare_cached("SELECT account, userId, cursorToken FROM account WHERE account = ?")?
+                .query_row(params![owned_account], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })
+                .optional()
+                .map_err(|e| {
+                    error!(target: "database", "查询账户失败 - 账户: {}, 错误: {}", owned_account, e);
+                    e.into()
+                })
+        }).await?;
 
-        match rows.next().map_err(|e| {
-            error!(target: "database", "获取账户查询结果失败 - 账户: {}, 错误: {}", account, e);
-            e
-        })? {
-            Some(row) => {
-                let res = (row.get(0)?, row.get(1)?, row.get(2)?);
-                Ok(Some(res))
+        match row {
+            Some((account, user_id, token)) => {
+                let token = self
+                    .secrets
+                    .decrypt(&token, &account_aad(&account))
+                    .map_err(DatabaseError::Secrets)?;
+                Ok(Some((account, user_id, token)))
             }
             None => Ok(None),
         }
     }
 
-    pub fn delete_account(&self, account: &str) -> SqliteResult<()> {
-        let conn = self.connection.lock().unwrap();
-        conn.execute("DELETE FROM account WHERE account = ?", params![account])
-            .map_err(|e| {
-                error!(target: "database", "删除账户失败 - 账户: {}, 错误: {}", account, e);
-                e
-            })?;
-        Ok(())
+    pub async fn delete_account(&self, account: &str) -> DbResult<()> {
+        let account = account.to_string();
+        self.actor.call(move |conn| {
+            conn.prepare_cached("DELETE FROM account WHERE account = ?")?
+                .execute(params![account])
+                .map_err(|e| {
+                    error!(target: "database", "删除账户失败 - 账户: {}, 错误: {}", account, e);
+                    e
+                })?;
+            Ok(())
+        }).await
     }
 
-    pub fn get_all_accounts(&self) -> SqliteResult<Vec<(String, String, String)>> {
-        let conn = self.connection.lock().unwrap();
-        let mut stmt = conn
-            .prepare("SELECT account, userId, cursorToken FROM account")
-            .map_err(|e| {
-                error!(target: "database", "准备获取所有账户查询失败: {}", e);
-                e
+    pub async fn get_all_accounts(&self) -> DbResult<Vec<(String, String, String)>> {
+        let rows = self.actor.call(|conn| {
+            let mut stmt = conn.prepare_cached("SELECT account, userId, cursorToken FROM account")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect::<rusqlite::Result<Vec<(String, String, String)>>>()
+                .map_err(|e| {
+                    error!(target: "database", "获取所有账户失败: {}", e);
+                    e.into()
+                })
+        }).await?;
+
+        rows.into_iter()
+            .map(|(account, user_id, token)| {
+                let token = self
+                    .secrets
+                    .decrypt(&token, &account_aad(&account))
+                    .map_err(DatabaseError::Secrets)?;
+                Ok((account, user_id, token))
+            })
+            .collect()
+    }
+
+    // inbound_probe表操作
+
+    /// 用最新一次测速结果替换已保存的结果
+    pub async fn replace_line_probes(&self, probes: &[LineProbe]) -> DbResult<()> {
+        let probes = probes.to_vec();
+        self.actor.call(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM inbound_probe", [])?;
+            {
+                let mut stmt = tx.prepare_cached(
+                    "INSERT INTO inbound_probe (url, name, rank, median_ms, success_rate, samples, probed_at)
+                     VALUES (?, ?, ?, ?, ?, ?, ?)",
+                )?;
+                for probe in &probes {
+                    stmt.execute(params![
+                        probe.url,
+                        probe.name,
+                        probe.rank as i64,
+                        probe.median_ms.map(|ms| ms as i64),
+                        probe.success_rate,
+                        probe.samples,
+                        probe.probed_at
+                    ])
+                    .map_err(|e| {
+                        error!(target: "database", "保存测速结果失败 - 线路: {}, 错误: {}", probe.url, e);
+                        e
+                    })?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        }).await
+    }
+
+    /// 按排名获取最近一次测速结果
+    pub async fn get_line_probes(&self) -> DbResult<Vec<LineProbe>> {
+        self.actor.call(|conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT url, name, rank, median_ms, success_rate, samples, probed_at
+                 FROM inbound_probe ORDER BY rank",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(LineProbe {
+                    url: row.get(0)?,
+                    name: row.get(1)?,
+                    rank: row.get::<_, i64>(2)? as usize,
+                    median_ms: row.get::<_, Option<i64>>(3)?.map(|ms| ms as u64),
+                    success_rate: row.get(4)?,
+                    samples: row.get(5)?,
+                    probed_at: row.get(6)?,
+                })
             })?;
+            rows.collect::<rusqlite::Result<Vec<LineProbe>>>()
+                .map_err(|e| {
+                    error!(target: "database", "获取测速结果失败: {}", e);
+                    e.into()
+                })
+        }).await
+    }
 
-        let rows = stmt
-            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
-            .map_err(|e| {
-                error!(target: "database", "执行获取所有账户查询失败: {}", e);
-                e
+    // usage_alert表操作
+
+    /// 获取账户已发送的用量警告
+    pub async fn get_usage_alerts(&self, account: &str) -> DbResult<Vec<UsageAlert>> {
+        let account = account.to_string();
+        self.actor.call(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT model, level, cycle_start, fired_at FROM usage_alert
+                 WHERE account = ? ORDER BY model, level DESC",
+            )?;
+            let rows = stmt.query_map(params![account], |row| {
+                Ok(UsageAlert {
+                    model: row.get(0)?,
+                    level: row.get(1)?,
+                    cycle_start: row.get(2)?,
+                    fired_at: row.get(3)?,
+                })
             })?;
+            rows.collect::<rusqlite::Result<Vec<UsageAlert>>>()
+                .map_err(|e| {
+                    error!(target: "database", "获取用量警告失败 - 账户: {}, 错误: {}", account, e);
+                    e.into()
+                })
+        }).await
+    }
 
-        let mut accounts = Vec::new();
-        for account in rows {
-            match account {
-                Ok(a) => accounts.push(a),
-                Err(e) => {
-                    error!(target: "database", "获取账户信息失败: {}", e);
-                    return Err(e);
+    /// 替换账户已发送的用量警告
+    pub async fn replace_usage_alerts(&self, account: &str, alerts: &[UsageAlert]) -> DbResult<()> {
+        let account = account.to_string();
+        let alerts = alerts.to_vec();
+        self.actor.call(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM usage_alert WHERE account = ?", params![account])?;
+            {
+                let mut stmt = tx.prepare_cached(
+                    "INSERT INTO usage_alert (account, model, level, cycle_start, fired_at)
+                     VALUES (?, ?, ?, ?, ?)",
+                )?;
+                for alert in &alerts {
+                    stmt.execute(params![
+                        account,
+                        alert.model,
+                        alert.level,
+                        alert.cycle_start,
+                        alert.fired_at
+                    ])?;
                 }
             }
-        }
+            tx.commit().map_err(|e| {
+                error!(target: "database", "保存用量警告失败 - 账户: {}, 错误: {}", account, e);
+                e.into()
+            })
+        }).await
+    }
 
-        Ok(accounts)
+    // task_run表操作
+
+    /// 保存一次任务运行记录，只保留最近的记录
+    pub async fn record_task_run(&self, run: &TaskRun) -> DbResult<()> {
+        let run = run.clone();
+        self.actor.call(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO task_run (task_id, status, started_at, duration_ms, error)
+                 VALUES (?, ?, ?, ?, ?)",
+                params![run.task_id, run.status, run.started_at, run.duration_ms as i64, run.error],
+            )?;
+            tx.execute(
+                "DELETE FROM task_run WHERE task_id = ?1 AND id NOT IN (
+                    SELECT id FROM task_run WHERE task_id = ?1 ORDER BY id DESC LIMIT ?2
+                 )",
+                params![run.task_id, TASK_RUN_HISTORY_LIMIT],
+            )?;
+            tx.commit().map_err(|e| {
+                error!(target: "database", "保存任务运行记录失败 - 任务: {}, 错误: {}", run.task_id, e);
+                e.into()
+            })
+        }).await
     }
 
-    pub fn update_account_token(&self, account: &str, cursor_token: &str) -> SqliteResult<()> {
-        let conn = self.connection.lock().unwrap();
-        conn.execute(
-            "UPDATE account SET cursorToken = ? WHERE account = ?",
-            params![cursor_token, account],
-        )
-        .map_err(|e| {
-            error!(target: "database", "更新账户令牌失败 - 账户: {}, 错误: {}", account, e);
-            e
-        })?;
-        Ok(())
+    /// 获取最近的任务运行记录，新记录在前，task_id 为空时返回所有任务的记录
+    pub async fn get_task_runs(&self, task_id: Option<&str>, limit: usize) -> DbResult<Vec<TaskRun>> {
+        let task_id = task_id.map(String::from);
+        self.actor.call(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT task_id, status, started_at, duration_ms, error FROM task_run
+                 WHERE ?1 IS NULL OR task_id = ?1 ORDER BY id DESC LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(params![task_id, limit as i64], |row| {
+                Ok(TaskRun {
+                    task_id: row.get(0)?,
+                    status: row.get(1)?,
+                    started_at: row.get(2)?,
+                    duration_ms: row.get::<_, i64>(3)? as u64,
+                    error: row.get(4)?,
+                })
+            })?;
+            rows.collect::<rusqlite::Result<Vec<TaskRun>>>()
+                .map_err(|e| {
+                    error!(target: "database", "获取任务运行记录失败: {}", e);
+                    e.into()
+                })
+        }).await
+    }
+
+    pub async fn update_account_token(&self, account: &str, cursor_token: &str) -> DbResult<()> {
+        let cursor_token = self
+            .secrets
+            .encrypt(cursor_token, &account_aad(account))
+            .map_err(DatabaseError::Secrets)?;
+        let account = account.to_string();
+        self.actor.call(move |conn| {
+            conn.prepare_cached("UPDATE account SET cursorToken = ? WHERE account = ?")?
+                .execute(params![cursor_token, account])
+                .map_err(|e| {
+                    error!(target: "database", "更新账户令牌失败 - 账户: {}, 错误: {}", account, e);
+                    e
+                })?;
+            Ok(())
+        }).await
+    }
+}
+
+/// 数据库的同步视图，异步代码应直接调用 Database 的异步方法
+pub struct BlockingDatabase<'a>(&'a Database);
+
+impl BlockingDatabase<'_> {
+    pub fn set_item(&self, key: &str, value: &str) -> DbResult<()> {
+        actor::block_on(self.0.set_item(key, value))
+    }
+
+    pub fn get_item(&self, key: &str) -> DbResult<Option<String>> {
+        actor::block_on(self.0.get_item(key))
+    }
+
+    pub fn delete_item(&self, key: &str) -> DbResult<()> {
+        actor::block_on(self.0.delete_item(key))
+    }
+
+    pub fn get_all_items(&self) -> DbResult<Vec<(String, String)>> {
+        actor::block_on(self.0.get_all_items())
+    }
+
+    /// 等待异步的数据库操作完成，例如设置层的读写
+    pub fn wait<F: Future>(&self, future: F) -> F::Output {
+        actor::block_on(future)
     }
 }
@@ -0,0 +1,267 @@
+use super::DatabaseError;
+use chrono::Local;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+
+// 保留的备份数量
+const MAX_BACKUPS: usize = 5;
+// 自动备份间隔
+const BACKUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const BACKUP_PREFIX: &str = "cursor_pool-";
+const BACKUP_EXTENSION: &str = "db";
+
+/// 数据库恢复结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecoveryReport {
+    // 已从备份恢复
+    Restored {
+        backup: PathBuf,
+        corrupted: PathBuf,
+    },
+    // 没有可用备份，已创建新数据库
+    Reset { corrupted: PathBuf },
+}
+
+impl RecoveryReport {
+    /// 面向用户的提示信息
+    pub fn message(&self) -> String {
+        match self {
+            RecoveryReport::Restored { backup, .. } => format!(
+                "检测到本地数据库损坏，已从备份 {} 恢复",
+                backup.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            RecoveryReport::Reset { corrupted } => format!(
+                "检测到本地数据库损坏且没有可用备份，已创建新数据库，损坏的文件已保存为 {}",
+                corrupted.display()
+            ),
+        }
+    }
+}
+
+/// 打开数据库并检查完整性，损坏时自动恢复
+pub fn open_checked(
+    db_path: &Path,
+    backup_dir: &Path,
+) -> Result<(Connection, Option<RecoveryReport>), DatabaseError> {
+    match open_verified(db_path) {
+        Ok(conn) => return Ok((conn, None)),
+        // 只有确认损坏时才恢复，权限或占用等问题直接返回
+        Err(e) if is_corruption(&e) => {
+            error!(target: "database", "数据库完整性检查失败: {}", e)
+        }
+        Err(e) => return Err(e),
+    }
+
+    let corrupted = move_aside(db_path)?;
+
+    for backup in list_backups(backup_dir) {
+        if let Err(e) = fs::copy(&backup, db_path) {
+            warn!(target: "database", "复制备份失败 - 备份: {:?}, 错误: {}", backup, e);
+            continue;
+        }
+        match open_verified(db_path) {
+            Ok(conn) => {
+                info!(target: "database", "已从备份恢复数据库: {:?}", backup);
+                return Ok((conn, Some(RecoveryReport::Restored { backup, corrupted })));
+            }
+            Err(e) => {
+                warn!(target: "database", "备份不可用 - 备份: {:?}, 错误: {}", backup, e);
+                let _ = fs::remove_file(db_path);
+            }
+        }
+    }
+
+    warn!(target: "database", "没有可用的数据库备份，创建新数据库");
+    let conn = open_verified(db_path)?;
+    Ok((conn, Some(RecoveryReport::Reset { corrupted })))
+}
+
+fn is_corruption(e: &DatabaseError) -> bool {
+    match e {
+        DatabaseError::Corrupted(_) => true,
+        DatabaseError::Sqlite(rusqlite::Error::SqliteFailure(err, _)) => matches!(
+            err.code,
+            rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase
+        ),
+        _ => false,
+    }
+}
+
+// 打开数据库，启用 WAL 并执行完整性检查
+fn open_verified(db_path: &Path) -> Result<Connection, DatabaseError> {
+    let conn = Connection::open(db_path)?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+    let result: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if result != "ok" {
+        return Err(DatabaseError::Corrupted(result));
+    }
+    Ok(conn)
+}
+
+// 将损坏的数据库及其 WAL 文件移到一旁
+fn move_aside(db_path: &Path) -> Result<PathBuf, DatabaseError> {
+    let suffix = format!("corrupt-{}", Local::now().format("%Y%m%d%H%M%S"));
+    let corrupted = db_path.with_extension(format!("db.{}", suffix));
+
+    if db_path.exists() {
+        fs::rename(db_path, &corrupted).map_err(DatabaseError::Io)?;
+    }
+    for ext in ["db-wal", "db-shm"] {
+        let side_file = db_path.with_extension(ext);
+        if side_file.exists() {
+            let _ = fs::rename(&side_file, db_path.with_extension(format!("{}.{}", ext, suffix)));
+        }
+    }
+
+    warn!(target: "database", "已将损坏的数据库移动到: {:?}", corrupted);
+    Ok(corrupted)
+}
+
+/// 列出所有备份，最新的在前
+pub fn list_backups(backup_dir: &Path) -> Vec<PathBuf> {
+    let mut backups: Vec<PathBuf> = match fs::read_dir(backup_dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension().map_or(false, |ext| ext == BACKUP_EXTENSION)
+                    && path
+                        .file_name()
+                        .map_or(false, |name| name.to_string_lossy().starts_with(BACKUP_PREFIX))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    // 文件名包含时间戳，按名称倒序即为时间倒序
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// 创建数据库备份并清理多余的旧备份
+pub fn backup(conn: &Connection, backup_dir: &Path) -> Result<PathBuf, DatabaseError> {
+    fs::create_dir_all(backup_dir).map_err(DatabaseError::Io)?;
+    let path = backup_dir.join(format!(
+        "{}{}.{}",
+        BACKUP_PREFIX,
+        Local::now().format("%Y%m%d-%H%M%S"),
+        BACKUP_EXTENSION
+    ));
+    if path.exists() {
+        return Ok(path);
+    }
+
+    conn.execute("VACUUM INTO ?", params![path.to_string_lossy()])?;
+    info!(target: "database", "已创建数据库备份: {:?}", path);
+
+    for old in list_backups(backup_dir).into_iter().skip(MAX_BACKUPS) {
+        if let Err(e) = fs::remove_file(&old) {
+            warn!(target: "database", "删除旧备份失败 - 备份: {:?}, 错误: {}", old, e);
+        }
+    }
+    Ok(path)
+}
+
+/// 距离上次备份超过备份间隔时创建新备份
+pub fn backup_if_due(conn: &Connection, backup_dir: &Path) -> Result<Option<PathBuf>, DatabaseError> {
+    let latest_age = list_backups(backup_dir)
+        .first()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|meta| meta.modified().ok())
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+
+    match latest_age {
+        Some(age) if age < BACKUP_INTERVAL => Ok(None),
+        _ => backup(conn, backup_dir).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_db(path: &Path, value: &str) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS item (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO item (key, value) VALUES ('k', ?)",
+            params![value],
+        )
+        .unwrap();
+    }
+
+    fn read_value(conn: &Connection) -> String {
+        conn.query_row("SELECT value FROM item WHERE key = 'k'", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_healthy_database_uses_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("cursor_pool.db");
+        create_db(&db_path, "v");
+
+        let (conn, report) = open_checked(&db_path, &dir.path().join("backups")).unwrap();
+        assert!(report.is_none());
+        let mode: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+    }
+
+    #[test]
+    fn test_restore_from_latest_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("cursor_pool.db");
+        let backup_dir = dir.path().join("backups");
+        create_db(&db_path, "backed-up");
+        backup(&Connection::open(&db_path).unwrap(), &backup_dir).unwrap();
+
+        fs::write(&db_path, b"this is definitely not a sqlite database").unwrap();
+
+        let (conn, report) = open_checked(&db_path, &backup_dir).unwrap();
+        assert!(matches!(report, Some(RecoveryReport::Restored { .. })));
+        assert_eq!(read_value(&conn), "backed-up");
+    }
+
+    #[test]
+    fn test_reset_without_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("cursor_pool.db");
+        fs::write(&db_path, b"this is definitely not a sqlite database").unwrap();
+
+        let (_, report) = open_checked(&db_path, &dir.path().join("backups")).unwrap();
+        match report {
+            Some(RecoveryReport::Reset { corrupted }) => assert!(corrupted.exists()),
+            other => panic!("unexpected report: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_backups_are_rotated() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup_dir = dir.path().join("backups");
+        fs::create_dir_all(&backup_dir).unwrap();
+        for i in 0..MAX_BACKUPS + 2 {
+            fs::write(backup_dir.join(format!("{}2000010{}-000000.db", BACKUP_PREFIX, i)), b"")
+                .unwrap();
+        }
+        let db_path = dir.path().join("cursor_pool.db");
+        create_db(&db_path, "v");
+
+        let latest = backup(&Connection::open(&db_path).unwrap(), &backup_dir).unwrap();
+        let backups = list_backups(&backup_dir);
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        assert_eq!(backups[0], latest);
+    }
+}
@@ -0,0 +1,527 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tracing::{error, info};
+
+// 密文前缀，格式: enc:v1:<密钥ID>:<hex(nonce || ciphertext)>
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+// 口令模式下用于校验口令的固定明文
+const CHECK_PLAINTEXT: &str = "cursor-pool-secrets";
+
+/// 密钥来源
+pub enum KeySource {
+    // 随机生成并保存在密钥文件中
+    KeyFile,
+    // 由用户口令派生
+    Passphrase(String),
+}
+
+// 单个密钥在密钥文件中的内容
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+enum KeyEntry {
+    Random { key: String },
+    Passphrase { salt: String, check: String },
+}
+
+// 密钥文件内容，轮换期间同时保存新旧两个密钥
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    #[serde(flatten)]
+    current: KeyEntry,
+    // 尚未退役的旧密钥，所有数据用新密钥重新加密后移除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retiring: Option<KeyEntry>,
+}
+
+struct SecretKey {
+    id: String,
+    cipher: XChaCha20Poly1305,
+}
+
+impl SecretKey {
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let digest = Sha256::digest(bytes);
+        Self {
+            id: hex::encode(&digest[..4]),
+            cipher: XChaCha20Poly1305::new(bytes.into()),
+        }
+    }
+
+    fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self, String> {
+        let mut bytes = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut bytes)
+            .map_err(|e| format!("派生密钥失败: {}", e))?;
+        Ok(Self::from_bytes(&bytes))
+    }
+
+    fn encrypt(&self, plaintext: &str, aad: &str) -> Result<String, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: aad.as_bytes(),
+                },
+            )
+            .map_err(|_| "加密失败".to_string())?;
+
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}:{}", PREFIX, self.id, hex::encode(blob)))
+    }
+
+    fn decrypt(&self, value: &str, aad: &str) -> Result<String, String> {
+        let key_id = key_id(value).ok_or("加密数据格式错误")?;
+        if key_id != self.id {
+            return Err(format!("加密数据使用了未知密钥: {}", key_id));
+        }
+        let (_, data) = value[PREFIX.len()..]
+            .split_once(':')
+            .ok_or("加密数据格式错误")?;
+
+        let blob = hex::decode(data).map_err(|e| format!("加密数据格式错误: {}", e))?;
+        if blob.len() < NONCE_LEN {
+            return Err("加密数据长度错误".to_string());
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: aad.as_bytes(),
+                },
+            )
+            .map_err(|_| "解密失败，数据可能已被篡改或密钥错误".to_string())?;
+
+        String::from_utf8(plaintext).map_err(|e| format!("解密结果不是有效文本: {}", e))
+    }
+}
+
+// 密文中记录的密钥ID
+fn key_id(value: &str) -> Option<&str> {
+    value
+        .strip_prefix(PREFIX)?
+        .split_once(':')
+        .map(|(key_id, _)| key_id)
+}
+
+// 密钥文件中的一个密钥，口令模式下未解锁时 key 为 None
+struct Slot {
+    entry: KeyEntry,
+    key: Option<SecretKey>,
+}
+
+impl Slot {
+    fn load(entry: KeyEntry) -> Result<Self, String> {
+        let key = match &entry {
+            KeyEntry::Random { key } => Some(SecretKey::from_bytes(&decode_key(key)?)),
+            // 口令模式需要用户解锁
+            KeyEntry::Passphrase { .. } => None,
+        };
+        Ok(Self { entry, key })
+    }
+
+    // 口令正确时解锁，返回是否解锁成功
+    fn unlock(&mut self, passphrase: &str) -> Result<bool, String> {
+        let (salt, check) = match &self.entry {
+            KeyEntry::Passphrase { salt, check } if self.key.is_none() => (salt, check),
+            _ => return Ok(false),
+        };
+        let salt = hex::decode(salt).map_err(|e| format!("密钥文件格式错误: {}", e))?;
+        let key = SecretKey::from_passphrase(passphrase, &salt)?;
+        if key.decrypt(check, CHECK_PLAINTEXT).as_deref() != Ok(CHECK_PLAINTEXT) {
+            return Ok(false);
+        }
+        self.key = Some(key);
+        Ok(true)
+    }
+}
+
+struct KeyRing {
+    current: Slot,
+    retiring: Option<Slot>,
+}
+
+impl KeyRing {
+    fn slots(&self) -> impl Iterator<Item = &Slot> {
+        std::iter::once(&self.current).chain(self.retiring.as_ref())
+    }
+
+    // 按密文中的密钥ID查找已解锁的密钥
+    fn find(&self, value: &str) -> Option<&SecretKey> {
+        let key_id = key_id(value)?;
+        self.slots()
+            .filter_map(|slot| slot.key.as_ref())
+            .find(|key| key.id == key_id)
+    }
+
+    fn key_file(&self) -> KeyFile {
+        KeyFile {
+            current: self.current.entry.clone(),
+            retiring: self.retiring.as_ref().map(|slot| slot.entry.clone()),
+        }
+    }
+}
+
+/// 重新加密单条数据的结果
+pub enum Refresh {
+    // 已使用当前密钥加密
+    Unchanged,
+    // 使用当前密钥重新加密后的密文
+    Updated(String),
+    // 所需的密钥未解锁或已不存在
+    Unavailable,
+}
+
+/// 敏感数据加密器
+pub struct Secrets {
+    key_path: PathBuf,
+    keys: RwLock<KeyRing>,
+}
+
+impl fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Secrets")
+            .field("key_path", &self.key_path)
+            .field("locked", &self.is_locked())
+            .finish()
+    }
+}
+
+impl Secrets {
+    /// 加载密钥文件，不存在时生成新的随机密钥
+    pub fn load(key_path: &Path) -> Result<Self, String> {
+        let keys = if key_path.exists() {
+            let content = fs::read_to_string(key_path)
+                .map_err(|e| format!("读取密钥文件失败: {}", e))?;
+            let key_file = serde_json::from_str::<KeyFile>(&content)
+                .map_err(|e| format!("解析密钥文件失败: {}", e))?;
+            KeyRing {
+                current: Slot::load(key_file.current)?,
+                retiring: key_file.retiring.map(Slot::load).transpose()?,
+            }
+        } else {
+            let (key, entry) = Self::generate(KeySource::KeyFile)?;
+            let keys = KeyRing {
+                current: Slot {
+                    entry,
+                    key: Some(key),
+                },
+                retiring: None,
+            };
+            write_key_file(key_path, &keys.key_file())?;
+            info!(target: "database", "已生成新的数据加密密钥");
+            keys
+        };
+
+        Ok(Self {
+            key_path: key_path.to_path_buf(),
+            keys: RwLock::new(keys),
+        })
+    }
+
+    /// 判断值是否为密文
+    pub fn is_encrypted(value: &str) -> bool {
+        value.starts_with(PREFIX)
+    }
+
+    /// 是否有需要口令解锁的密钥
+    pub fn is_locked(&self) -> bool {
+        self.keys.read().unwrap().slots().any(|slot| slot.key.is_none())
+    }
+
+    /// 当前密钥是否由口令派生
+    pub fn uses_passphrase(&self) -> bool {
+        matches!(
+            self.keys.read().unwrap().current.entry,
+            KeyEntry::Passphrase { .. }
+        )
+    }
+
+    /// 是否有未完成的密钥轮换
+    pub fn has_retiring_key(&self) -> bool {
+        self.keys.read().unwrap().retiring.is_some()
+    }
+
+    /// 使用口令解锁，轮换未完成时也可以用旧口令解锁旧密钥
+    pub fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        let mut keys = self.keys.write().unwrap();
+        if !keys.slots().any(|slot| slot.key.is_none()) {
+            return Ok(());
+        }
+
+        let mut unlocked = keys.current.unlock(passphrase)?;
+        if let Some(retiring) = keys.retiring.as_mut() {
+            unlocked |= retiring.unlock(passphrase)?;
+        }
+        if !unlocked {
+            return Err("口令错误".to_string());
+        }
+        info!(target: "database", "数据加密密钥已解锁");
+        Ok(())
+    }
+
+    /// 加密数据，aad 用于绑定数据所在的位置
+    pub fn encrypt(&self, plaintext: &str, aad: &str) -> Result<String, String> {
+        match self.keys.read().unwrap().current.key.as_ref() {
+            Some(key) => key.encrypt(plaintext, aad),
+            None => Err("数据加密密钥未解锁".to_string()),
+        }
+    }
+
+    /// 解密数据，未加密的旧数据原样返回
+    pub fn decrypt(&self, value: &str, aad: &str) -> Result<String, String> {
+        if !Self::is_encrypted(value) {
+            return Ok(value.to_string());
+        }
+        let keys = self.keys.read().unwrap();
+        match keys.find(value) {
+            Some(key) => key.decrypt(value, aad).map_err(|e| {
+                error!(target: "database", "解密数据失败: {}", e);
+                e
+            }),
+            None if keys.slots().any(|slot| slot.key.is_none()) => {
+                Err("数据加密密钥未解锁".to_string())
+            }
+            None => Err(format!(
+                "加密数据使用了未知密钥: {}",
+                key_id(value).unwrap_or_default()
+            )),
+        }
+    }
+
+    /// 将明文或旧密钥加密的数据改为使用当前密钥加密
+    pub fn refresh(&self, value: &str, aad: &str) -> Result<Refresh, String> {
+        let keys = self.keys.read().unwrap();
+        let current = match keys.current.key.as_ref() {
+            Some(current) => current,
+            None => return Ok(Refresh::Unavailable),
+        };
+        if !Self::is_encrypted(value) {
+            return current.encrypt(value, aad).map(Refresh::Updated);
+        }
+        if key_id(value) == Some(current.id.as_str()) {
+            return Ok(Refresh::Unchanged);
+        }
+        match keys.find(value) {
+            Some(key) => {
+                let plaintext = key.decrypt(value, aad)?;
+                current.encrypt(&plaintext, aad).map(Refresh::Updated)
+            }
+            None => Ok(Refresh::Unavailable),
+        }
+    }
+
+    /// 开始密钥轮换：先把新密钥和旧密钥一起写入密钥文件，再切换到新密钥
+    ///
+    /// 旧密钥在所有数据重新加密后通过 retire_old_key 移除，中途失败时两个密钥都可以解密
+    pub fn begin_rotation(&self, source: KeySource) -> Result<(), String> {
+        let mut keys = self.keys.write().unwrap();
+        if keys.current.key.is_none() {
+            return Err("数据加密密钥未解锁".to_string());
+        }
+        if keys.retiring.is_some() {
+            return Err("上一次密钥轮换尚未完成".to_string());
+        }
+
+        let (key, entry) = Self::generate(source)?;
+        let key_file = KeyFile {
+            current: entry.clone(),
+            retiring: Some(keys.current.entry.clone()),
+        };
+        write_key_file(&self.key_path, &key_file)?;
+
+        let previous = std::mem::replace(
+            &mut keys.current,
+            Slot {
+                entry,
+                key: Some(key),
+            },
+        );
+        keys.retiring = Some(previous);
+        info!(target: "database", "已生成新的数据加密密钥，等待重新加密数据");
+        Ok(())
+    }
+
+    /// 所有数据都已使用当前密钥加密后移除旧密钥
+    pub fn retire_old_key(&self) -> Result<(), String> {
+        let mut keys = self.keys.write().unwrap();
+        if keys.retiring.is_none() {
+            return Ok(());
+        }
+        let key_file = KeyFile {
+            current: keys.current.entry.clone(),
+            retiring: None,
+        };
+        write_key_file(&self.key_path, &key_file)?;
+        keys.retiring = None;
+        info!(target: "database", "数据加密密钥已轮换");
+        Ok(())
+    }
+
+    fn generate(source: KeySource) -> Result<(SecretKey, KeyEntry), String> {
+        match source {
+            KeySource::KeyFile => {
+                let mut bytes = [0u8; 32];
+                rand::thread_rng().fill_bytes(&mut bytes);
+                let entry = KeyEntry::Random {
+                    key: hex::encode(bytes),
+                };
+                Ok((SecretKey::from_bytes(&bytes), entry))
+            }
+            KeySource::Passphrase(passphrase) => {
+                if passphrase.is_empty() {
+                    return Err("口令不能为空".to_string());
+                }
+                let mut salt = [0u8; SALT_LEN];
+                rand::thread_rng().fill_bytes(&mut salt);
+                let key = SecretKey::from_passphrase(&passphrase, &salt)?;
+                let check = key.encrypt(CHECK_PLAINTEXT, CHECK_PLAINTEXT)?;
+                let entry = KeyEntry::Passphrase {
+                    salt: hex::encode(salt),
+                    check,
+                };
+                Ok((key, entry))
+            }
+        }
+    }
+}
+
+fn decode_key(key: &str) -> Result<[u8; 32], String> {
+    hex::decode(key)
+        .map_err(|e| format!("密钥文件格式错误: {}", e))?
+        .try_into()
+        .map_err(|_| "密钥长度错误".to_string())
+}
+
+// 先写入临时文件再替换，避免写入中断导致密钥丢失
+fn write_key_file(path: &Path, key_file: &KeyFile) -> Result<(), String> {
+    let content =
+        serde_json::to_string_pretty(key_file).map_err(|e| format!("序列化密钥失败: {}", e))?;
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content).map_err(|e| format!("写入密钥文件失败: {}", e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600));
+    }
+
+    fs::rename(&tmp_path, path).map_err(|e| format!("保存密钥文件失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = Secrets::load(&dir.path().join("secret.key")).unwrap();
+
+        let encrypted = secrets.encrypt("token", "item:user.info.token").unwrap();
+        assert!(Secrets::is_encrypted(&encrypted));
+        assert_ne!(encrypted, "token");
+        assert_eq!(
+            secrets.decrypt(&encrypted, "item:user.info.token").unwrap(),
+            "token"
+        );
+        // 绑定位置不一致时解密失败
+        assert!(secrets.decrypt(&encrypted, "item:other").is_err());
+        // 旧的明文数据原样返回
+        assert_eq!(secrets.decrypt("plain", "item:other").unwrap(), "plain");
+    }
+
+    #[test]
+    fn test_key_file_is_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.key");
+        let encrypted = Secrets::load(&path).unwrap().encrypt("token", "aad").unwrap();
+
+        let reloaded = Secrets::load(&path).unwrap();
+        assert_eq!(reloaded.decrypt(&encrypted, "aad").unwrap(), "token");
+    }
+
+    // 按轮换的实际顺序重新加密，返回新的密文
+    fn rotate(secrets: &Secrets, source: KeySource, value: &str) -> String {
+        secrets.begin_rotation(source).unwrap();
+        match secrets.refresh(value, "aad").unwrap() {
+            Refresh::Updated(value) => value,
+            _ => panic!("数据没有重新加密"),
+        }
+    }
+
+    #[test]
+    fn test_passphrase_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.key");
+        let secrets = Secrets::load(&path).unwrap();
+        let old = secrets.encrypt("token", "aad").unwrap();
+
+        let rotated = rotate(
+            &secrets,
+            KeySource::Passphrase("correct horse".to_string()),
+            &old,
+        );
+        secrets.retire_old_key().unwrap();
+        assert_eq!(secrets.decrypt(&rotated, "aad").unwrap(), "token");
+        assert!(secrets.decrypt(&old, "aad").is_err());
+
+        // 重新加载后需要口令解锁
+        let reloaded = Secrets::load(&path).unwrap();
+        assert!(reloaded.is_locked());
+        assert!(reloaded.uses_passphrase());
+        assert!(reloaded.unlock("wrong").is_err());
+        reloaded.unlock("correct horse").unwrap();
+        assert_eq!(reloaded.decrypt(&rotated, "aad").unwrap(), "token");
+    }
+
+    #[test]
+    fn test_interrupted_rotation_keeps_both_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.key");
+        let secrets = Secrets::load(&path).unwrap();
+        let old = secrets.encrypt("token", "aad").unwrap();
+
+        // 新密钥已写入密钥文件，但数据尚未全部重新加密
+        let rotated = rotate(&secrets, KeySource::KeyFile, &old);
+        let reloaded = Secrets::load(&path).unwrap();
+        assert!(reloaded.has_retiring_key());
+        assert_eq!(reloaded.decrypt(&old, "aad").unwrap(), "token");
+        assert_eq!(reloaded.decrypt(&rotated, "aad").unwrap(), "token");
+        assert!(matches!(reloaded.refresh(&rotated, "aad").unwrap(), Refresh::Unchanged));
+        assert!(matches!(reloaded.refresh(&old, "aad").unwrap(), Refresh::Updated(_)));
+
+        reloaded.retire_old_key().unwrap();
+        assert!(!Secrets::load(&path).unwrap().has_retiring_key());
+    }
+
+    #[test]
+    fn test_legacy_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.key");
+        fs::write(&path, format!(r#"{{"mode":"random","key":"{}"}}"#, "11".repeat(32))).unwrap();
+
+        let secrets = Secrets::load(&path).unwrap();
+        assert!(!secrets.is_locked());
+        assert!(!secrets.has_retiring_key());
+        let encrypted = secrets.encrypt("token", "aad").unwrap();
+        assert_eq!(secrets.decrypt(&encrypted, "aad").unwrap(), "token");
+    }
+}
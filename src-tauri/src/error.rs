@@ -0,0 +1,278 @@
+//! 统一的命令错误类型，前端根据 code 判断错误种类
+
+use crate::database::DatabaseError;
+use crate::scheduler::TaskError;
+use crate::settings::SettingsError;
+use crate::utils::paths::PathError;
+use serde::Serialize;
+use std::fmt;
+
+/// 错误分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Network,
+    Database,
+    Filesystem,
+    Permission,
+    Process,
+    Auth,
+    // 客户端版本过低
+    Version,
+    // 参数或设置值无效
+    Validation,
+    // 程序内部错误
+    Internal,
+}
+
+/// 稳定的错误码，序列化后供前端匹配，已发布的错误码不要修改
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    // 后端接口请求失败
+    NetworkError,
+    // 后端接口返回的数据无法解析
+    InvalidResponse,
+    // 请求 Cursor 接口失败
+    CursorNetworkError,
+    // Cursor 接口返回的数据无法解析
+    CursorDataError,
+    // 无法从 Cursor 数据库读取账户信息
+    CursorDbError,
+    // 本地数据库错误
+    DatabaseError,
+    // 加密数据未解锁或密钥错误
+    SecretsError,
+    // 文件读写失败
+    FilesystemError,
+    // 找不到 Cursor 的 main.js
+    MainJsNotFound,
+    // 权限不足
+    PermissionDenied,
+    // Cursor 正在运行
+    CursorRunning,
+    // 进程操作失败
+    ProcessError,
+    // 未登录或登录已失效
+    Unauthorized,
+    // 客户端版本低于远程配置要求的最低版本
+    UpdateRequired,
+    // 参数无效
+    InvalidInput,
+    // 其他内部错误
+    InternalError,
+}
+
+impl ErrorCode {
+    /// 错误码所属分类
+    pub fn category(self) -> ErrorCategory {
+        match self {
+            ErrorCode::NetworkError
+            | ErrorCode::InvalidResponse
+            | ErrorCode::CursorNetworkError
+            | ErrorCode::CursorDataError => ErrorCategory::Network,
+            ErrorCode::DatabaseError | ErrorCode::CursorDbError => ErrorCategory::Database,
+            ErrorCode::FilesystemError | ErrorCode::MainJsNotFound => ErrorCategory::Filesystem,
+            ErrorCode::PermissionDenied => ErrorCategory::Permission,
+            ErrorCode::CursorRunning | ErrorCode::ProcessError => ErrorCategory::Process,
+            ErrorCode::Unauthorized | ErrorCode::SecretsError => ErrorCategory::Auth,
+            ErrorCode::UpdateRequired => ErrorCategory::Version,
+            ErrorCode::InvalidInput => ErrorCategory::Validation,
+            ErrorCode::InternalError => ErrorCategory::Internal,
+        }
+    }
+}
+
+/// 命令错误
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub category: ErrorCategory,
+    // 面向用户的提示信息
+    pub message: String,
+    // 排查问题用的详细信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            category: code.category(),
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// 附加详细信息
+    pub fn with_details(mut self, details: impl fmt::Display) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NetworkError, message)
+    }
+
+    pub fn invalid_response(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidResponse, message)
+    }
+
+    pub fn database(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::DatabaseError, message)
+    }
+
+    pub fn filesystem(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::FilesystemError, message)
+    }
+
+    pub fn permission(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::PermissionDenied, message)
+    }
+
+    pub fn process(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::ProcessError, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InternalError, message)
+    }
+
+    pub fn cursor_running() -> Self {
+        Self::new(ErrorCode::CursorRunning, "Cursor进程正在运行, 请先关闭Cursor")
+    }
+
+    pub fn update_required(min_version: &str) -> Self {
+        Self::new(
+            ErrorCode::UpdateRequired,
+            format!("当前版本过低，请更新到 {} 或更高版本后再使用", min_version),
+        )
+    }
+
+    pub fn main_js_not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::MainJsNotFound, message)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.details {
+            Some(details) => write!(f, "{}: {}", self.message, details),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<DatabaseError> for AppError {
+    fn from(e: DatabaseError) -> Self {
+        match e {
+            DatabaseError::Secrets(msg) => Self::new(ErrorCode::SecretsError, msg),
+            DatabaseError::Io(io) => io.into(),
+            other => Self::database("数据库操作失败").with_details(other),
+        }
+    }
+}
+
+impl From<TaskError> for AppError {
+    fn from(e: TaskError) -> Self {
+        match e {
+            TaskError::Invalid(msg) => Self::invalid_input(msg),
+            TaskError::Unavailable(msg) => Self::internal(msg),
+            TaskError::Storage(msg) => Self::database(msg),
+        }
+    }
+}
+
+impl From<SettingsError> for AppError {
+    fn from(e: SettingsError) -> Self {
+        match e {
+            SettingsError::Invalid(msg) => Self::invalid_input(msg),
+            SettingsError::Storage(msg) => Self::database(msg),
+        }
+    }
+}
+
+impl From<PathError> for AppError {
+    fn from(e: PathError) -> Self {
+        match e {
+            PathError::Environment(msg) => Self::internal("无法确定Cursor目录").with_details(msg),
+            PathError::MainJsNotFound(msg) => Self::main_js_not_found(msg),
+            PathError::Io(io) => io.into(),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            Self::permission("没有权限访问文件").with_details(e)
+        } else {
+            Self::filesystem("文件操作失败").with_details(e)
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            Self::invalid_response("服务器返回的数据格式错误").with_details(e)
+        } else {
+            Self::network("链接服务器失败，请稍后再试").with_details(e)
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::invalid_response("服务器返回的数据格式错误").with_details(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_shape() {
+        let value = serde_json::to_value(
+            AppError::main_js_not_found("找不到Cursor的main.js文件").with_details("/tmp/main.js"),
+        )
+        .unwrap();
+        assert_eq!(value["code"], "MAIN_JS_NOT_FOUND");
+        assert_eq!(value["category"], "filesystem");
+        assert_eq!(value["message"], "找不到Cursor的main.js文件");
+        assert_eq!(value["details"], "/tmp/main.js");
+
+        let value = serde_json::to_value(AppError::cursor_running()).unwrap();
+        assert_eq!(value["code"], "CURSOR_RUNNING");
+        assert_eq!(value["category"], "process");
+        assert!(value.get("details").is_none());
+    }
+
+    #[test]
+    fn test_settings_error_codes() {
+        let invalid = AppError::from(SettingsError::Invalid("间隔无效".to_string()));
+        assert_eq!(invalid.code, ErrorCode::InvalidInput);
+        let storage = AppError::from(SettingsError::Storage("保存设置失败".to_string()));
+        assert_eq!(storage.code, ErrorCode::DatabaseError);
+    }
+
+    #[test]
+    fn test_path_error_codes() {
+        let missing = AppError::from(PathError::MainJsNotFound("未找到main.js文件".to_string()));
+        assert_eq!(missing.code, ErrorCode::MainJsNotFound);
+        let denied = AppError::from(PathError::Io(std::io::Error::from(
+            std::io::ErrorKind::PermissionDenied,
+        )));
+        assert_eq!(denied.code, ErrorCode::PermissionDenied);
+        let io = AppError::from(PathError::Io(std::io::Error::from(std::io::ErrorKind::Other)));
+        assert_eq!(io.code, ErrorCode::FilesystemError);
+    }
+}
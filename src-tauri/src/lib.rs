@@ -7,7 +7,8 @@ use std::env;
 use std::error::Error as StdError;
 use std::path::PathBuf;
 use tauri::{generate_context, generate_handler, Manager};
-use tracing::{debug, error, info};
+use tauri_plugin_notification::NotificationExt;
+use tracing::{debug, error, info, warn};
 use utils::{get_app_log_dir, init_logger, LogConfig};
 
 pub mod api;
@@ -15,7 +16,10 @@ pub mod auth;
 pub mod config;
 pub mod cursor_reset;
 pub mod database;
+pub mod error;
 pub mod scheduler;
+pub mod settings;
+pub mod shutdown;
 pub mod tray;
 pub mod utils;
 
@@ -47,8 +51,13 @@ pub fn run() {
         .plugin(tauri_plugin_positioner::init())
         .setup(|app| {
             // 初始化配置
-            if let Err(e) = config::init_config() {
-                eprintln!("初始化配置失败: {}", e);
+            match app.path().app_data_dir() {
+                Ok(app_dir) => {
+                    if let Err(e) = config::init_config(&app_dir) {
+                        eprintln!("初始化配置失败，使用默认配置: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("获取应用数据目录失败，使用默认配置: {}", e),
             }
 
             // 初始化日志系统
@@ -64,11 +73,7 @@ pub fn run() {
             let log_config = LogConfig {
                 log_dir,
                 console_output: true,
-                log_level: if cfg!(debug_assertions) {
-                    "debug,hyper=off,reqwest=off,tauri_plugin_updater=off".to_string()
-                } else {
-                    "info,hyper=off,reqwest=off,tauri_plugin_updater=off".to_string()
-                },
+                log_level: config::CONFIG.read().unwrap().log.level.clone(),
                 json_format: false,
             };
 
@@ -81,6 +86,12 @@ pub fn run() {
             info!("应用启动");
             debug!("调试模式: {}", cfg!(debug_assertions));
 
+            // 监视配置变更，无需重启即可生效
+            if let Ok(app_dir) = app.path().app_data_dir() {
+                shutdown::spawn(config::watch_config_file(app_dir));
+            }
+            shutdown::spawn(utils::logger::watch_log_level());
+
             // 初始化数据库
             let db = match Database::new(app.handle()) {
                 Ok(db) => {
@@ -92,11 +103,46 @@ pub fn run() {
                     return Err(Box::<dyn StdError>::from(e.to_string()));
                 }
             };
+            // 数据库损坏并自动恢复时提示用户
+            if let Some(report) = db.recovery_report() {
+                warn!("{}", report.message());
+                if let Err(e) = app
+                    .notification()
+                    .builder()
+                    .title("Cursor Pool")
+                    .body(report.message())
+                    .show()
+                {
+                    error!("发送数据库恢复通知失败: {}", e);
+                }
+            }
+            // 之后创建的HTTP客户端都使用保存的代理设置，设置无效时改用系统代理
+            match db.blocking().wait(settings::proxy(&db)) {
+                Ok(proxy) => match proxy.validate() {
+                    Ok(()) => utils::http::set_proxy(proxy),
+                    Err(e) => {
+                        warn!("保存的代理设置无效，改用系统代理: {}", e);
+                        utils::http::set_proxy(utils::http::ProxySettings::default());
+                    }
+                },
+                // 代理设置加密存储，解锁后由 unlock_secrets 重新应用
+                Err(e) => warn!("无法读取代理设置，解锁前使用系统代理: {}", e),
+            }
             app.manage(db);
 
+            // 先创建API客户端，线路配置更新后同步到客户端的线路列表
+            let api_client = match ApiClient::new(Some(app.handle().clone())) {
+                Ok(client) => client,
+                Err(e) => {
+                    error!("创建API客户端失败: {}", e);
+                    return Err(Box::<dyn StdError>::from(e));
+                }
+            };
+            app.manage(api_client);
+
             // 异步初始化线路配置
             let app_handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
+            shutdown::spawn(async move {
                 if let Err(e) = api::inbound::init_inbound_config(&app_handle).await {
                     error!("初始化线路配置失败: {}", e);
                 } else {
@@ -107,9 +153,6 @@ pub fn run() {
             // 延迟一小段时间，以便线路配置初始化完成
             std::thread::sleep(std::time::Duration::from_millis(100));
 
-            let api_client = ApiClient::new(Some(app.handle().clone()));
-            app.manage(api_client);
-
             // 初始化系统托盘
             if let Err(e) = tray::setup_system_tray(app) {
                 error!("初始化系统托盘失败: {}", e);
@@ -122,7 +165,7 @@ pub fn run() {
             app.manage(scheduler.clone());
             
             // 在单独的任务中启动调度器，避免阻塞主线程
-            tauri::async_runtime::spawn(async move {
+            shutdown::spawn(async move {
                 if let Err(e) = scheduler.start().await {
                     error!("启动任务调度器失败: {}", e);
                 } else {
@@ -154,6 +197,32 @@ pub fn run() {
             api::set_user_data,
             api::get_user_data,
             api::del_user_data,
+            api::get_secrets_status,
+            api::unlock_secrets,
+            api::rotate_secrets_key,
+            api::export_settings,
+            api::import_settings,
+            api::get_effective_config,
+            api::reload_config,
+            api::get_request_metrics,
+            // 线路
+            api::get_version_status,
+            api::get_inbound_lines,
+            api::pin_inbound_line,
+            api::get_inbound_probe_results,
+            api::probe_inbound_lines,
+            // 网络代理
+            api::get_proxy_settings,
+            api::set_proxy_settings,
+            api::test_proxy_connection,
+            // 定时任务
+            api::get_scheduled_tasks,
+            api::pause_scheduled_task,
+            api::resume_scheduled_task,
+            api::reschedule_scheduled_task,
+            api::set_task_schedule,
+            api::run_scheduled_task,
+            api::get_task_runs,
             // 换号
             cursor_reset::commands::reset_machine_id,
             cursor_reset::commands::switch_account,
@@ -176,7 +245,11 @@ pub fn run() {
             cursor_reset::commands::log_info,
             // devtools
             cursor_reset::commands::open_devtools,
+            // 应用
+            api::restart_app,
         ])
-        .run(generate_context!())
-        .expect("error while running tauri application")
+        .build(generate_context!())
+        .expect("error while building tauri application")
+        // 所有退出请求都先停止后台任务、关闭数据库并刷新日志
+        .run(shutdown::handle_run_event)
 }
@@ -0,0 +1,212 @@
+//! 用量警告：每个级别在一个用量周期内只提醒一次，用量恢复或周期重置后重新提醒
+
+use crate::database::UsageAlert;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+// 剩余比例回升超过级别这么多后才视为已恢复，避免在级别附近反复提醒
+const RECOVERY_MARGIN: f64 = 0.02;
+
+// 请求上限达到这个值视为不限量
+const UNLIMITED_REQUESTS: f64 = 9990.0;
+
+/// 模型的请求用量
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelUsage {
+    pub model: String,
+    pub used: f64,
+    pub limit: f64,
+}
+
+impl ModelUsage {
+    /// 剩余比例(0-1)，不限量时返回 None
+    pub fn remaining_ratio(&self) -> Option<f64> {
+        if self.limit <= 0.0 || self.limit >= UNLIMITED_REQUESTS {
+            return None;
+        }
+        Some(((self.limit - self.used) / self.limit).clamp(0.0, 1.0))
+    }
+}
+
+/// 需要发送的警告
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub model: String,
+    pub level: f64,
+    pub remaining_ratio: f64,
+}
+
+/// 从用量数据中读取各模型的请求数和上限，没有上限的模型会被忽略
+pub fn parse_usage(usage: &Value) -> Vec<ModelUsage> {
+    let models = match usage.as_object() {
+        Some(models) => models,
+        None => return Vec::new(),
+    };
+    models
+        .iter()
+        .filter_map(|(model, usage)| {
+            Some(ModelUsage {
+                model: model.clone(),
+                used: usage.get("numRequests")?.as_f64()?,
+                limit: usage.get("maxRequestUsage")?.as_f64()?,
+            })
+        })
+        .collect()
+}
+
+/// 根据当前用量更新已发送的警告，返回需要发送的警告
+///
+/// 同时越过多个级别时只提醒其中最低的级别，其余级别也记为已提醒
+pub fn evaluate(
+    usages: &[ModelUsage],
+    levels: &BTreeMap<String, Vec<f64>>,
+    fired: &mut Vec<UsageAlert>,
+    cycle_start: &str,
+    now: &str,
+) -> Vec<Alert> {
+    // 用量周期重置或级别设置变更后之前的警告不再有效
+    fired.retain(|alert| {
+        alert.cycle_start == cycle_start
+            && levels
+                .get(&alert.model)
+                .map_or(false, |levels| levels.contains(&alert.level))
+    });
+
+    let mut alerts = Vec::new();
+    for usage in usages {
+        let (levels, remaining) = match (levels.get(&usage.model), usage.remaining_ratio()) {
+            (Some(levels), Some(remaining)) => (levels, remaining),
+            _ => continue,
+        };
+
+        // 用量已恢复的级别可以再次提醒
+        fired.retain(|alert| alert.model != usage.model || remaining <= alert.level + RECOVERY_MARGIN);
+
+        let crossed: Vec<f64> = levels
+            .iter()
+            .copied()
+            .filter(|level| remaining <= *level)
+            .filter(|level| {
+                !fired
+                    .iter()
+                    .any(|alert| alert.model == usage.model && alert.level == *level)
+            })
+            .collect();
+        let lowest = match crossed.iter().copied().reduce(f64::min) {
+            Some(lowest) => lowest,
+            None => continue,
+        };
+
+        fired.extend(crossed.into_iter().map(|level| UsageAlert {
+            model: usage.model.clone(),
+            level,
+            cycle_start: cycle_start.to_string(),
+            fired_at: now.to_string(),
+        }));
+        alerts.push(Alert {
+            model: usage.model.clone(),
+            level: lowest,
+            remaining_ratio: remaining,
+        });
+    }
+    alerts
+}
+
+/// 通知发送失败时撤销该模型本次新增的警告记录，下次检查会重新提醒
+pub fn revert(fired: &mut Vec<UsageAlert>, saved: &[UsageAlert], model: &str) {
+    fired.retain(|alert| alert.model != model || saved.contains(alert));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn usage(used: f64) -> Vec<ModelUsage> {
+        vec![ModelUsage {
+            model: "gpt-4".to_string(),
+            used,
+            limit: 100.0,
+        }]
+    }
+
+    fn fired_levels(fired: &[UsageAlert]) -> Vec<f64> {
+        fired.iter().map(|alert| alert.level).collect()
+    }
+
+    #[test]
+    fn test_each_level_fires_once() {
+        let levels = BTreeMap::from([("gpt-4".to_string(), vec![0.5, 0.2, 0.05])]);
+        let mut fired = Vec::new();
+        let check = |used, fired: &mut Vec<UsageAlert>| {
+            evaluate(&usage(used), &levels, fired, "2025-01-01", "now")
+                .iter()
+                .map(|alert| alert.level)
+                .collect::<Vec<f64>>()
+        };
+
+        assert!(check(40.0, &mut fired).is_empty());
+        assert_eq!(check(55.0, &mut fired), vec![0.5]);
+        assert!(check(60.0, &mut fired).is_empty());
+        // 同时越过两个级别只提醒最低的
+        assert_eq!(check(96.0, &mut fired), vec![0.05]);
+        assert_eq!(fired_levels(&fired), vec![0.5, 0.2, 0.05]);
+        assert!(check(99.0, &mut fired).is_empty());
+
+        // 在级别附近波动不重复提醒，明显恢复后重新提醒
+        assert!(check(94.0, &mut fired).is_empty());
+        assert!(check(90.0, &mut fired).is_empty());
+        assert_eq!(fired_levels(&fired), vec![0.5, 0.2]);
+        assert_eq!(check(95.0, &mut fired), vec![0.05]);
+
+        // 周期重置后重新计算
+        let alerts = evaluate(&usage(60.0), &levels, &mut fired, "2025-02-01", "now");
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(fired_levels(&fired), vec![0.5]);
+    }
+
+    #[test]
+    fn test_revert_failed_model() {
+        let levels = BTreeMap::from([
+            ("gpt-4".to_string(), vec![0.5, 0.2]),
+            ("claude".to_string(), vec![0.5]),
+        ]);
+        let mut usages = usage(60.0);
+        usages.push(ModelUsage {
+            model: "claude".to_string(),
+            used: 60.0,
+            limit: 100.0,
+        });
+        let mut fired = Vec::new();
+        evaluate(&usage(60.0), &levels, &mut fired, "2025-01-01", "now");
+        let saved = fired.clone();
+
+        let alerts = evaluate(&usages, &levels, &mut fired, "2025-01-01", "now");
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].model, "claude");
+
+        // 发送失败的模型只撤销本次新增的记录
+        revert(&mut fired, &saved, "claude");
+        assert_eq!(fired, saved);
+        revert(&mut fired, &saved, "gpt-4");
+        assert_eq!(fired, saved);
+    }
+
+    #[test]
+    fn test_parse_usage() {
+        let usages = parse_usage(&json!({
+            "gpt-4": {"numRequests": 120, "maxRequestUsage": 150},
+            "gpt-3.5-turbo": {"numRequests": 5, "maxRequestUsage": null},
+            "startOfMonth": "2025-01-01T00:00:00.000Z"
+        }));
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].remaining_ratio(), Some(0.2));
+
+        let unlimited = ModelUsage {
+            model: "gpt-4".to_string(),
+            used: 1.0,
+            limit: 9999.0,
+        };
+        assert_eq!(unlimited.remaining_ratio(), None);
+    }
+}
@@ -0,0 +1,311 @@
+//! 日历计划：cron 表达式、静默时段和用量周期，均按本地时区计算
+
+use crate::database::Database;
+use crate::settings::{self, SettingKey};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, Months, NaiveDate, NaiveDateTime,
+    NaiveTime, TimeZone, Timelike,
+};
+use std::fmt;
+use std::str::FromStr;
+use tracing::error;
+
+// 查找下次执行时间时最多向后搜索的天数，超过后视为不会再执行
+const MAX_SEARCH_DAYS: u32 = 366 * 5;
+
+/// cron 表达式：分 时 日 月 周
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // 日和周都有限制时满足任一即可，与标准 cron 一致
+    any_day: bool,
+    any_weekday: bool,
+}
+
+// 解析单个字段，支持 *、a、a-b、*/n、a-b/n 以及逗号分隔的列表
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("步长无效: {}", part))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+        let value = |v: &str| {
+            v.parse::<u32>()
+                .ok()
+                .filter(|v| (min..=max).contains(v))
+                .ok_or_else(|| format!("取值必须在 {} 到 {} 之间: {}", min, max, part))
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // a/n 表示从 a 开始到最大值
+                None if step.is_some() => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if start > end {
+            return Err(format!("范围无效: {}", part));
+        }
+        for v in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+impl FromStr for CronExpr {
+    type Err = String;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        let source = expr.trim();
+        let expanded = match source {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            _ => source,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("cron 表达式需要 5 个字段(分 时 日 月 周): {}", source));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        // 7 和 0 都表示周日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        let cron = Self {
+            source: source.to_string(),
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        };
+        if cron.next_after(Local::now()).is_none() {
+            return Err(format!("cron 表达式没有匹配的时间: {}", source));
+        }
+        Ok(cron)
+    }
+}
+
+impl CronExpr {
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// 指定时间之后的第一个匹配时间
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)?
+            + ChronoDuration::minutes(1);
+        let mut date = start.date();
+        for _ in 0..MAX_SEARCH_DAYS {
+            if self.matches_day(date) {
+                let first_day = date == start.date();
+                for hour in (0..24).filter(|h| self.hours & (1 << h) != 0) {
+                    if first_day && hour < start.hour() {
+                        continue;
+                    }
+                    for minute in (0..60).filter(|m| self.minutes & (1 << m) != 0) {
+                        if first_day && hour == start.hour() && minute < start.minute() {
+                            continue;
+                        }
+                        // 夏令时跳过的时间不存在，直接忽略
+                        let time = date
+                            .and_hms_opt(hour, minute, 0)
+                            .and_then(|naive| Local.from_local_datetime(&naive).earliest());
+                        if let Some(time) = time.filter(|time| *time > after) {
+                            return Some(time);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+impl fmt::Display for CronExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// 每天在指定时间执行
+pub fn next_daily(time: NaiveTime, after: DateTime<Local>) -> Option<DateTime<Local>> {
+    let mut date = after.date_naive();
+    // 当天的时间已过或者因为夏令时不存在时顺延到下一天
+    for _ in 0..3 {
+        let next = Local
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+            .filter(|next| *next > after);
+        if next.is_some() {
+            return next;
+        }
+        date = date.succ_opt()?;
+    }
+    None
+}
+
+/// 每月在用量周期开始的时间执行，没有对应日期的月份在月末执行
+pub fn next_cycle_start(anchor: NaiveDateTime, after: DateTime<Local>) -> Option<DateTime<Local>> {
+    let now = after.naive_local();
+    let elapsed = (now.year() - anchor.year()) * 12 + now.month() as i32 - anchor.month() as i32;
+    let first = elapsed.max(1) as u32 - 1;
+    (first..first + 3)
+        .filter_map(|months| anchor.checked_add_months(Months::new(months)))
+        .filter_map(|naive| Local.from_local_datetime(&naive).earliest())
+        .find(|next| *next > after)
+}
+
+/// 静默时段，跨越午夜时结束时间小于开始时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl FromStr for QuietHours {
+    type Err = String;
+
+    // 格式为 HH:MM-HH:MM
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (start, end) = value
+            .split_once('-')
+            .ok_or_else(|| format!("静默时段格式应为 HH:MM-HH:MM: {}", value))?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|e| format!("静默时段时间无效: {} ({})", time.trim(), e))
+        };
+        let quiet_hours = Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        };
+        if quiet_hours.start == quiet_hours.end {
+            return Err("静默时段的开始和结束时间不能相同".to_string());
+        }
+        Ok(quiet_hours)
+    }
+}
+
+impl QuietHours {
+    /// 指定时间是否在静默时段内
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+/// 最近一次获取到的用量周期开始时间(本地时间)
+pub async fn usage_cycle_start(db: &Database) -> Option<NaiveDateTime> {
+    let value = settings::get(db, SettingKey::UsageCycleStart).await.ok()??;
+    DateTime::parse_from_rfc3339(&value)
+        .ok()
+        .map(|time| time.with_timezone(&Local).naive_local())
+}
+
+/// 保存用量周期开始时间，未变化时不重复保存，格式无效时忽略
+pub async fn save_usage_cycle_start(db: &Database, start_of_month: &str) {
+    let saved = settings::get(db, SettingKey::UsageCycleStart).await.ok().flatten();
+    if saved.as_deref() == Some(start_of_month) {
+        return;
+    }
+    if let Err(e) = settings::set(db, SettingKey::UsageCycleStart, start_of_month).await {
+        error!("保存用量周期失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let cron: CronExpr = "*/15 9-17 * * 1-5".parse().unwrap();
+        // 2025-01-10 是周五
+        assert_eq!(cron.next_after(local(2025, 1, 10, 9, 7)), Some(local(2025, 1, 10, 9, 15)));
+        assert_eq!(cron.next_after(local(2025, 1, 10, 9, 15)), Some(local(2025, 1, 10, 9, 30)));
+        assert_eq!(cron.next_after(local(2025, 1, 10, 17, 50)), Some(local(2025, 1, 13, 9, 0)));
+
+        // 日和周都有限制时满足任一即可
+        let cron: CronExpr = "0 8 1 * 7".parse().unwrap();
+        assert_eq!(cron.next_after(local(2025, 1, 10, 12, 0)), Some(local(2025, 1, 12, 8, 0)));
+
+        let daily: CronExpr = "@daily".parse().unwrap();
+        assert_eq!(daily.next_after(local(2025, 1, 10, 0, 0)), Some(local(2025, 1, 11, 0, 0)));
+
+        for invalid in ["* * * *", "60 * * * *", "5-1 * * * *", "*/0 * * * *", "0 0 30 2 *"] {
+            assert!(invalid.parse::<CronExpr>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_calendar_schedules() {
+        let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        assert_eq!(next_daily(nine, local(2025, 1, 10, 8, 0)), Some(local(2025, 1, 10, 9, 0)));
+        assert_eq!(next_daily(nine, local(2025, 1, 10, 9, 0)), Some(local(2025, 1, 11, 9, 0)));
+
+        // 31 日开始的周期在较短的月份落在月末
+        let anchor = local(2024, 10, 31, 10, 0).naive_local();
+        assert_eq!(next_cycle_start(anchor, local(2025, 1, 31, 10, 0)), Some(local(2025, 2, 28, 10, 0)));
+        assert_eq!(next_cycle_start(anchor, local(2025, 2, 28, 11, 0)), Some(local(2025, 3, 31, 10, 0)));
+        assert_eq!(next_cycle_start(anchor, local(2024, 9, 1, 0, 0)), Some(local(2024, 10, 31, 10, 0)));
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let night: QuietHours = "22:00-08:00".parse().unwrap();
+        assert!(night.contains(at(23, 30)) && night.contains(at(7, 59)));
+        assert!(!night.contains(at(8, 0)) && !night.contains(at(12, 0)));
+
+        let lunch: QuietHours = "12:00 - 13:30".parse().unwrap();
+        assert!(lunch.contains(at(12, 0)) && !lunch.contains(at(13, 30)));
+        assert_eq!(lunch.to_string(), "12:00-13:30");
+
+        assert!("08:00".parse::<QuietHours>().is_err());
+        assert!("08:00-08:00".parse::<QuietHours>().is_err());
+    }
+}
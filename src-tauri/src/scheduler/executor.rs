@@ -1,231 +1,137 @@
-use crate::config;
 use crate::database::Database;
-use crate::scheduler::tasks;
-use std::collections::HashMap;
+use crate::scheduler::registry::{TaskError, TaskInfo, TaskRegistry};
+use crate::scheduler::tasks::{
+    AccountLimitCheckTask, ConfigRefreshTask, DashboardRefreshTask, InboundProbeTask,
+};
+use crate::settings::{self, SettingKey};
+use crate::shutdown;
 use std::sync::Arc;
-use std::time::Duration;
-use tauri::{AppHandle, Emitter, Manager};
-use tokio::task::JoinHandle;
-use tokio::time::interval;
+use tauri::{AppHandle, Manager};
+use tokio::time::Instant;
 use tracing::{error, info};
 
 /// 任务调度器状态
 pub struct SchedulerState {
-    /// 调度器任务句柄
-    tasks: HashMap<String, JoinHandle<()>>,
+    /// 已注册的任务，初始化后创建
+    registry: Option<TaskRegistry>,
     /// 应用句柄
     app_handle: Arc<AppHandle>,
-    /// 是否已初始化
-    initialized: bool,
 }
 
 impl SchedulerState {
     /// 创建新的调度器状态
     pub fn new(app_handle: AppHandle) -> Self {
         Self {
-            tasks: HashMap::new(),
+            registry: None,
             app_handle: Arc::new(app_handle),
-            initialized: false,
         }
     }
 
     /// 初始化调度器并注册默认任务
     pub async fn initialize(&mut self) -> Result<(), String> {
-        if self.initialized {
+        if self.registry.is_some() {
             return Ok(());
         }
 
         // 从数据库加载任务配置
         self.load_task_configs().await?;
-        
+
         // 注册任务
-        self.register_tasks().await?;
-        self.initialized = true;
+        let mut registry = TaskRegistry::new(
+            self.app_handle.as_ref().clone(),
+            self.database()?,
+            shutdown::token(),
+        );
+        registry.register(Arc::new(DashboardRefreshTask)).await;
+        registry.register(Arc::new(AccountLimitCheckTask)).await;
+        registry.register(Arc::new(InboundProbeTask)).await;
+        registry.register(Arc::new(ConfigRefreshTask)).await;
+        registry.listen_schedule_changes();
+        info!("所有任务已注册完成");
+
+        self.registry = Some(registry);
         Ok(())
     }
-    
-    /// 加载任务配置
-    async fn load_task_configs(&self) -> Result<(), String> {
-        // 获取数据库实例
-        let db = match self.app_handle.try_state::<Database>() {
-            Some(db) => db,
+
+    // 获取数据库实例
+    fn database(&self) -> Result<Database, String> {
+        match self.app_handle.try_state::<Database>() {
+            Some(db) => Ok(db.inner().clone()),
             None => {
                 let err = "无法获取数据库实例".to_string();
                 error!("{}", err);
-                return Err(err);
-            }
-        };
-        
-        // 尝试从数据库加载刷新间隔
-        let dashboard_refresh_key = config::get_db_key("dashboard_refresh_interval");
-        let dashboard_interval = db.inner().get_item(&dashboard_refresh_key).ok().flatten();
-        
-        // 尝试从数据库加载账户检查间隔
-        let account_check_key = config::get_db_key("account_limit_check_interval");
-        let account_check_interval = db.inner().get_item(&account_check_key).ok().flatten();
-        
-        // 尝试从数据库加载账户使用阈值
-        let account_threshold_key = config::get_db_key("account_usage_threshold");
-        let account_threshold = db.inner().get_item(&account_threshold_key).ok().flatten();
-        
-        // 如果数据库中没有设置值，则使用默认值并保存到数据库
-        let scheduler_config = config::get_scheduler_config();
-        
-        if dashboard_interval.is_none() {
-            if let Err(e) = db.inner().set_item(
-                &dashboard_refresh_key,
-                &scheduler_config.dashboard_refresh_interval.to_string(),
-            ) {
-                error!("保存仪表盘刷新间隔到数据库失败: {}", e);
-            }
-        }
-        
-        if account_check_interval.is_none() {
-            if let Err(e) = db.inner().set_item(
-                &account_check_key,
-                &scheduler_config.account_limit_check_interval.to_string(),
-            ) {
-                error!("保存账户检查间隔到数据库失败: {}", e);
+                Err(err)
             }
         }
-        
-        if account_threshold.is_none() {
-            if let Err(e) = db.inner().set_item(
-                &account_threshold_key,
-                &scheduler_config.account_usage_threshold.to_string(),
-            ) {
-                error!("保存账户使用阈值到数据库失败: {}", e);
+    }
+
+    /// 加载任务配置
+    async fn load_task_configs(&self) -> Result<(), String> {
+        let db = self.database()?;
+
+        // 如果数据库中没有设置值，则保存默认值
+        for key in [
+            SettingKey::DashboardRefreshInterval,
+            SettingKey::AccountLimitCheckInterval,
+            SettingKey::AccountUsageThreshold,
+            SettingKey::InboundProbeInterval,
+            SettingKey::ConfigRefreshInterval,
+        ] {
+            if let Err(e) = settings::persist_default(&db, key).await {
+                error!("保存默认任务配置 {:?} 到数据库失败: {}", key, e);
             }
         }
-        
+
         info!("已加载任务配置");
         Ok(())
     }
 
-    /// 注册所有任务
-    async fn register_tasks(&mut self) -> Result<(), String> {
-        // 注册刷新任务
-        self.register_refresh_task().await?;
-        
-        // 注册账户使用限制检查任务
-        self.register_account_limit_check_task().await?;
-        
-        info!("所有任务已注册完成");
-        Ok(())
+    /// 停止所有任务，正在执行的任务在截止时间前可以继续完成
+    pub async fn shutdown(&mut self, deadline: Instant) {
+        if let Some(registry) = self.registry.as_mut() {
+            registry.shutdown(deadline).await;
+        }
     }
 
-    /// 注册刷新任务 - 使用数据库中的间隔配置
-    async fn register_refresh_task(&mut self) -> Result<(), String> {
-        let app_handle = self.app_handle.clone();
-        let task_id = "refresh_dashboard".to_string();
-        
-        // 获取数据库实例
-        let db = match self.app_handle.try_state::<Database>() {
-            Some(db) => db,
-            None => {
-                let err = "无法获取数据库实例".to_string();
-                error!("{}", err);
-                return Err(err);
-            }
-        };
-        
-        // 从数据库获取刷新间隔
-        let dashboard_refresh_key = config::get_db_key("dashboard_refresh_interval");
-        let refresh_interval_str = match db.inner().get_item(&dashboard_refresh_key) {
-            Ok(Some(val)) => val,
-            _ => {
-                // 使用默认值
-                let default_interval = config::get_scheduler_config().dashboard_refresh_interval;
-                default_interval.to_string()
-            }
-        };
-        
-        // 解析刷新间隔
-        let refresh_interval = match refresh_interval_str.parse::<u64>() {
-            Ok(val) => val,
-            Err(e) => {
-                let err = format!("解析仪表盘刷新间隔失败: {}", e);
-                error!("{}", err);
-                // 使用默认值
-                config::get_scheduler_config().dashboard_refresh_interval
-            }
-        };
-        
-        info!("仪表盘刷新间隔设置为 {} 秒", refresh_interval);
-        
-        let handle = tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(refresh_interval));
-            
-            loop {
-                interval.tick().await;
-                
-                // 通知前端刷新仪表盘
-                if let Some(window) = app_handle.as_ref().get_webview_window("main") {
-                    if let Err(e) = window.emit("refresh-dashboard", ()) {
-                        error!("发送刷新事件失败: {}", e);
-                    }
-                }
-            }
-        });
-        
-        self.tasks.insert(task_id, handle);
-        info!("已注册仪表盘刷新任务");
-        Ok(())
+    fn registry(&self) -> Result<&TaskRegistry, TaskError> {
+        self.registry
+            .as_ref()
+            .ok_or_else(|| TaskError::Unavailable("任务调度器尚未启动".to_string()))
     }
-    
-    /// 注册账户使用限制检查任务 - 使用数据库中的间隔配置
-    async fn register_account_limit_check_task(&mut self) -> Result<(), String> {
-        let app_handle = self.app_handle.clone();
-        let task_id = "check_account_limit".to_string();
-        
-        // 获取数据库实例
-        let db = match self.app_handle.try_state::<Database>() {
-            Some(db) => db,
-            None => {
-                let err = "无法获取数据库实例".to_string();
-                error!("{}", err);
-                return Err(err);
-            }
-        };
-        
-        // 从数据库获取检查间隔
-        let account_check_key = config::get_db_key("account_limit_check_interval");
-        let check_interval_str = match db.inner().get_item(&account_check_key) {
-            Ok(Some(val)) => val,
-            _ => {
-                // 使用默认值
-                let default_interval = config::get_scheduler_config().account_limit_check_interval;
-                default_interval.to_string()
-            }
-        };
-        
-        // 解析检查间隔
-        let check_interval = match check_interval_str.parse::<u64>() {
-            Ok(val) => val,
-            Err(e) => {
-                let err = format!("解析账户检查间隔失败: {}", e);
-                error!("{}", err);
-                // 使用默认值
-                config::get_scheduler_config().account_limit_check_interval
-            }
-        };
-        
-        info!("账户检查间隔设置为 {} 秒", check_interval);
-        
-        let handle = tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(check_interval));
-            
-            loop {
-                interval.tick().await;
-                // 执行账户检查
-                if let Err(e) = tasks::check_account_limit(&app_handle).await {
-                    error!("检查账户使用限制失败: {}", e);
-                }
-            }
-        });
-        
-        self.tasks.insert(task_id, handle);
-        info!("已注册账户使用限制检查任务");
-        Ok(())
+
+    /// 所有任务的运行状态
+    pub fn list(&self) -> Vec<TaskInfo> {
+        self.registry
+            .as_ref()
+            .map(TaskRegistry::list)
+            .unwrap_or_default()
+    }
+
+    /// 暂停或恢复任务，状态保存到数据库，重启后保持
+    pub async fn set_paused(&self, task_id: &str, paused: bool) -> Result<TaskInfo, TaskError> {
+        self.registry()?.set_paused(task_id, paused).await
+    }
+
+    /// 修改任务间隔，保存到对应的设置项后按新间隔重新计时
+    pub async fn reschedule(
+        &self,
+        task_id: &str,
+        interval_secs: u64,
+    ) -> Result<TaskInfo, TaskError> {
+        self.registry()?.reschedule(task_id, interval_secs).await
     }
-} 
\ No newline at end of file
+
+    /// 设置任务的计划表达式，传入 None 时恢复为默认间隔
+    pub async fn set_schedule(
+        &self,
+        task_id: &str,
+        expr: Option<&str>,
+    ) -> Result<TaskInfo, TaskError> {
+        self.registry()?.set_schedule(task_id, expr).await
+    }
+
+    /// 立即执行一次任务
+    pub fn run_now(&self, task_id: &str) -> Result<(), TaskError> {
+        self.registry()?.run_now(task_id)
+    }
+}
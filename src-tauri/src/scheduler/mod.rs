@@ -3,10 +3,18 @@
 use std::sync::Arc;
 use tauri::AppHandle;
 use tokio::sync::Mutex;
+use tokio::time::Instant;
 use tracing::info;
 
+pub mod alerts;
 pub mod tasks;
 pub mod executor;
+pub mod calendar;
+pub mod registry;
+pub mod task;
+
+pub use registry::{TaskError, TaskInfo};
+pub use task::{Schedule, ScheduledTask};
 
 /// 任务调度器
 #[derive(Clone)]
@@ -26,4 +34,47 @@ impl Scheduler {
         info!("任务调度器已启动");
         Ok(())
     }
+
+    /// 停止所有任务，正在执行的任务在截止时间前可以继续完成
+    pub async fn shutdown(&self, deadline: Instant) {
+        self.0.lock().await.shutdown(deadline).await;
+    }
+
+    /// 所有任务的运行状态
+    pub async fn list(&self) -> Vec<TaskInfo> {
+        self.0.lock().await.list()
+    }
+
+    /// 暂停任务
+    pub async fn pause(&self, task_id: &str) -> Result<TaskInfo, TaskError> {
+        self.0.lock().await.set_paused(task_id, true).await
+    }
+
+    /// 恢复任务
+    pub async fn resume(&self, task_id: &str) -> Result<TaskInfo, TaskError> {
+        self.0.lock().await.set_paused(task_id, false).await
+    }
+
+    /// 修改任务间隔(秒)
+    pub async fn reschedule(
+        &self,
+        task_id: &str,
+        interval_secs: u64,
+    ) -> Result<TaskInfo, TaskError> {
+        self.0.lock().await.reschedule(task_id, interval_secs).await
+    }
+
+    /// 设置任务的计划表达式，传入 None 时恢复为默认间隔
+    pub async fn set_schedule(
+        &self,
+        task_id: &str,
+        expr: Option<&str>,
+    ) -> Result<TaskInfo, TaskError> {
+        self.0.lock().await.set_schedule(task_id, expr).await
+    }
+
+    /// 立即执行一次任务
+    pub async fn run_now(&self, task_id: &str) -> Result<(), TaskError> {
+        self.0.lock().await.run_now(task_id)
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,583 @@
+//! 任务注册表：统一负责任务的计时、暂停恢复、超时、异常隔离、失败退避和运行历史
+
+use super::calendar;
+use super::task::{Schedule, ScheduledTask};
+use crate::database::{Database, TaskRun};
+use crate::settings::{self, SettingKey, SettingsError};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::task::{AbortHandle, JoinHandle};
+use tokio::time::{timeout, timeout_at, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+// 连续失败多少次后开始退避
+const BACKOFF_AFTER_FAILURES: u32 = 3;
+
+// 退避后的最长等待时间，任务间隔更长时以任务间隔为准
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+// 单次等待的上限，系统休眠或调整时钟后按本地时间重新判断是否到期
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// 任务操作失败的原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskError {
+    // 任务不存在或参数无效
+    Invalid(String),
+    // 调度器尚未启动或任务已停止
+    Unavailable(String),
+    // 保存任务设置失败
+    Storage(String),
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::Invalid(msg) | TaskError::Unavailable(msg) | TaskError::Storage(msg) => {
+                write!(f, "{}", msg)
+            }
+        }
+    }
+}
+
+impl From<SettingsError> for TaskError {
+    fn from(e: SettingsError) -> Self {
+        match e {
+            SettingsError::Invalid(msg) => TaskError::Invalid(msg),
+            SettingsError::Storage(msg) => TaskError::Storage(msg),
+        }
+    }
+}
+
+/// 任务运行状态
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    pub id: String,
+    // 当前执行计划，例如 every 300s、daily 09:00、cron 表达式或 cycle
+    pub schedule: String,
+    // 固定间隔计划的间隔(秒)，其他计划为 0
+    pub interval_secs: u64,
+    pub paused: bool,
+    pub running: bool,
+    pub consecutive_failures: u32,
+    pub last_run: Option<String>,
+    pub next_run: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// 单次执行结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Success,
+    Failed,
+    TimedOut,
+    Panicked,
+    // 处于静默时段，未执行
+    Skipped,
+}
+
+impl RunStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RunStatus::Success => "success",
+            RunStatus::Failed => "failed",
+            RunStatus::TimedOut => "timed_out",
+            RunStatus::Panicked => "panicked",
+            RunStatus::Skipped => "skipped",
+        }
+    }
+}
+
+// 发送给任务循环的控制指令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Pause,
+    Resume,
+    // 立即执行一次
+    RunNow,
+    // 计划设置已变更，按新计划重新计时
+    Rearm,
+}
+
+// 已注册的任务
+struct TaskEntry {
+    task: Arc<dyn ScheduledTask>,
+    info: Arc<Mutex<TaskInfo>>,
+    control: mpsc::UnboundedSender<Control>,
+    handle: JoinHandle<()>,
+}
+
+/// 任务注册表
+pub struct TaskRegistry {
+    app_handle: AppHandle,
+    db: Database,
+    tasks: BTreeMap<&'static str, TaskEntry>,
+    // 监听计划设置变更的任务句柄
+    listener: Option<JoinHandle<()>>,
+    // 退出信号，收到后任务循环在当前执行结束后退出
+    cancel: CancellationToken,
+}
+
+impl Drop for TaskRegistry {
+    fn drop(&mut self) {
+        if let Some(listener) = self.listener.take() {
+            listener.abort();
+        }
+        for entry in self.tasks.values() {
+            entry.handle.abort();
+        }
+    }
+}
+
+// 读取任务的执行计划，任务计划设置优先于任务的默认计划
+async fn resolve_schedule(task: &dyn ScheduledTask, db: &Database) -> Schedule {
+    if let Some(expr) = settings::task_schedules(db).await.get(task.id()) {
+        match Schedule::parse(expr, calendar::usage_cycle_start(db).await) {
+            Ok(schedule) => return schedule,
+            Err(e) => warn!("任务 {} 的计划 {} 暂不可用，使用默认计划: {}", task.id(), expr, e),
+        }
+    }
+    task.schedule(db).await
+}
+
+// 下一次等待的时长
+fn sleep_until(due: DateTime<Local>) -> Duration {
+    (due - Local::now())
+        .to_std()
+        .unwrap_or(Duration::ZERO)
+        .min(MAX_SLEEP)
+}
+
+/// 连续失败后的等待时间：达到阈值后每多失败一次等待时间翻倍
+pub fn retry_delay(period: Duration, consecutive_failures: u32) -> Duration {
+    if consecutive_failures < BACKOFF_AFTER_FAILURES {
+        return period;
+    }
+    let exponent = (consecutive_failures - BACKOFF_AFTER_FAILURES + 1).min(16);
+    period
+        .saturating_mul(1 << exponent)
+        .min(period.max(MAX_BACKOFF))
+}
+
+// 释放时取消任务，任务循环被取消时一并取消正在执行的任务
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+// 取出 panic 信息
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map_or_else(|| "未知错误".to_string(), |message| message.to_string()),
+    }
+}
+
+/// 在独立的任务中执行，超时后取消，panic 不会影响任务循环
+pub async fn execute<F>(job: F, limit: Duration) -> (RunStatus, Option<String>)
+where
+    F: Future<Output = Result<(), String>> + Send + 'static,
+{
+    let mut handle = tokio::spawn(job);
+    // 超时或者调用方被取消时取消执行
+    let _guard = AbortOnDrop(handle.abort_handle());
+    match timeout(limit, &mut handle).await {
+        Ok(Ok(Ok(()))) => (RunStatus::Success, None),
+        Ok(Ok(Err(e))) => (RunStatus::Failed, Some(e)),
+        Ok(Err(e)) if e.is_panic() => (RunStatus::Panicked, Some(panic_message(e.into_panic()))),
+        Ok(Err(e)) => (RunStatus::Failed, Some(e.to_string())),
+        Err(_) => (
+            RunStatus::TimedOut,
+            Some(format!("执行超过 {} 秒，已取消", limit.as_secs())),
+        ),
+    }
+}
+
+impl TaskRegistry {
+    pub fn new(app_handle: AppHandle, db: Database, cancel: CancellationToken) -> Self {
+        Self {
+            app_handle,
+            db,
+            tasks: BTreeMap::new(),
+            listener: None,
+            cancel,
+        }
+    }
+
+    /// 停止所有任务，正在执行的任务在截止时间前可以继续完成，超时后取消
+    pub async fn shutdown(&mut self, deadline: Instant) {
+        self.cancel.cancel();
+        if let Some(listener) = self.listener.take() {
+            listener.abort();
+        }
+        for (id, mut entry) in std::mem::take(&mut self.tasks) {
+            if timeout_at(deadline, &mut entry.handle).await.is_err() {
+                warn!("任务 {} 未在退出前完成，已取消", id);
+                entry.handle.abort();
+            }
+        }
+        info!("所有任务已停止");
+    }
+
+    /// 注册并启动任务，按计划等到第一次执行时间后再执行
+    pub async fn register(&mut self, task: Arc<dyn ScheduledTask>) {
+        let id = task.id();
+        let schedule = resolve_schedule(task.as_ref(), &self.db).await;
+        let paused = settings::paused_tasks(&self.db).await.contains(id);
+        info!(
+            "已注册任务 {} - 计划: {}{}",
+            id,
+            schedule,
+            if paused { "，已暂停" } else { "" }
+        );
+
+        let info = Arc::new(Mutex::new(TaskInfo {
+            id: id.to_string(),
+            schedule: schedule.to_string(),
+            interval_secs: schedule.interval().map_or(0, |period| period.as_secs()),
+            paused,
+            running: false,
+            consecutive_failures: 0,
+            last_run: None,
+            next_run: None,
+            last_error: None,
+        }));
+        let (control, commands) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(run_loop(
+            task.clone(),
+            self.app_handle.clone(),
+            self.db.clone(),
+            info.clone(),
+            commands,
+            self.cancel.clone(),
+            paused,
+        ));
+
+        if let Some(old) = self.tasks.insert(
+            id,
+            TaskEntry {
+                task,
+                info,
+                control,
+                handle,
+            },
+        ) {
+            warn!("任务 {} 重复注册，已停止旧任务", id);
+            old.handle.abort();
+        }
+    }
+
+    /// 计划设置变更时通知对应的任务重新计时
+    pub fn listen_schedule_changes(&mut self) {
+        let controls: Vec<(Option<SettingKey>, mpsc::UnboundedSender<Control>)> = self
+            .tasks
+            .values()
+            .map(|entry| (entry.task.schedule_key(), entry.control.clone()))
+            .collect();
+
+        if let Some(old) = self.listener.replace(tokio::spawn(async move {
+            let mut changes = settings::subscribe();
+            loop {
+                let key = match changes.recv().await {
+                    Ok(change) => Some(change.key),
+                    // 错过了部分通知，所有任务重新读取一次
+                    Err(RecvError::Lagged(_)) => None,
+                    Err(RecvError::Closed) => break,
+                };
+                for (schedule_key, control) in &controls {
+                    let affected = key.map_or(true, |key| {
+                        key == SettingKey::TaskSchedules || Some(key) == *schedule_key
+                    });
+                    if affected {
+                        let _ = control.send(Control::Rearm);
+                    }
+                }
+            }
+        })) {
+            old.abort();
+        }
+    }
+
+    /// 所有任务的运行状态
+    pub fn list(&self) -> Vec<TaskInfo> {
+        self.tasks
+            .values()
+            .map(|entry| entry.info.lock().unwrap().clone())
+            .collect()
+    }
+
+    fn entry(&self, task_id: &str) -> Result<&TaskEntry, TaskError> {
+        self.tasks
+            .get(task_id)
+            .ok_or_else(|| TaskError::Invalid(format!("任务不存在: {}", task_id)))
+    }
+
+    fn send(&self, entry: &TaskEntry, control: Control) -> Result<(), TaskError> {
+        entry
+            .control
+            .send(control)
+            .map_err(|_| TaskError::Unavailable(format!("任务已停止: {}", entry.task.id())))
+    }
+
+    /// 暂停或恢复任务，状态保存到数据库，重启后保持
+    pub async fn set_paused(&self, task_id: &str, paused: bool) -> Result<TaskInfo, TaskError> {
+        let entry = self.entry(task_id)?;
+        settings::set_task_paused(&self.db, task_id, paused).await?;
+        self.send(entry, if paused { Control::Pause } else { Control::Resume })?;
+        info!("任务 {} 已{}", task_id, if paused { "暂停" } else { "恢复" });
+
+        let mut info = entry.info.lock().unwrap();
+        info.paused = paused;
+        Ok(info.clone())
+    }
+
+    /// 修改任务间隔，保存到对应的设置项后按新间隔重新计时，同时清除任务的计划设置
+    pub async fn reschedule(
+        &self,
+        task_id: &str,
+        interval_secs: u64,
+    ) -> Result<TaskInfo, TaskError> {
+        let entry = self.entry(task_id)?;
+        let key = entry
+            .task
+            .schedule_key()
+            .ok_or_else(|| TaskError::Invalid(format!("任务 {} 不支持修改间隔", task_id)))?;
+        let value = interval_secs.to_string();
+        key.validate(&value).map_err(TaskError::Invalid)?;
+        settings::set(&self.db, key, &value).await?;
+        if settings::task_schedules(&self.db).await.contains_key(task_id) {
+            settings::set_task_schedule(&self.db, task_id, None).await?;
+        }
+
+        let mut info = entry.info.lock().unwrap();
+        info.schedule = Schedule::Interval(Duration::from_secs(interval_secs)).to_string();
+        info.interval_secs = interval_secs;
+        Ok(info.clone())
+    }
+
+    /// 设置任务的计划表达式，传入 None 时恢复为默认间隔
+    pub async fn set_schedule(
+        &self,
+        task_id: &str,
+        expr: Option<&str>,
+    ) -> Result<TaskInfo, TaskError> {
+        let entry = self.entry(task_id)?;
+        if let Some(expr) = expr {
+            Schedule::validate(expr.trim())
+                .map_err(|e| TaskError::Invalid(format!("任务 {} 的计划无效: {}", task_id, e)))?;
+        }
+        settings::set_task_schedule(&self.db, task_id, expr).await?;
+
+        let schedule = resolve_schedule(entry.task.as_ref(), &self.db).await;
+        let mut info = entry.info.lock().unwrap();
+        info.schedule = schedule.to_string();
+        info.interval_secs = schedule.interval().map_or(0, |period| period.as_secs());
+        Ok(info.clone())
+    }
+
+    /// 立即执行一次任务，任务正在执行时拒绝
+    pub fn run_now(&self, task_id: &str) -> Result<(), TaskError> {
+        let entry = self.entry(task_id)?;
+        if entry.info.lock().unwrap().running {
+            return Err(TaskError::Invalid(format!("任务 {} 正在执行", task_id)));
+        }
+        self.send(entry, Control::RunNow)
+    }
+}
+
+// 任务循环：同一任务同一时间只会执行一次
+async fn run_loop(
+    task: Arc<dyn ScheduledTask>,
+    app_handle: AppHandle,
+    db: Database,
+    info: Arc<Mutex<TaskInfo>>,
+    mut commands: mpsc::UnboundedReceiver<Control>,
+    cancel: CancellationToken,
+    mut paused: bool,
+) {
+    let id = task.id();
+    let mut failures = 0u32;
+    let mut schedule = resolve_schedule(task.as_ref(), &db).await;
+    // 下次计划执行的时间，启动后按计划等待，不立即执行
+    let mut due = schedule.next_run(None, Local::now());
+    if !paused {
+        info.lock().unwrap().next_run = due.map(|due| due.to_rfc3339());
+    }
+
+    loop {
+        let command = match due.filter(|_| !paused) {
+            Some(due) => {
+                let next = cancel.run_until_cancelled(timeout(sleep_until(due), commands.recv()));
+                match next.await {
+                    // 收到退出信号
+                    None => break,
+                    // 等待结束但还未到执行时间
+                    Some(Err(_)) if Local::now() < due => continue,
+                    Some(Err(_)) => None,
+                    Some(Ok(Some(command))) => Some(command),
+                    Some(Ok(None)) => break,
+                }
+            }
+            None => match cancel.run_until_cancelled(commands.recv()).await {
+                Some(Some(command)) => Some(command),
+                _ => break,
+            },
+        };
+
+        match command {
+            None | Some(Control::RunNow) => {
+                // 手动执行不受静默时段限制，也不影响计划的执行时间
+                let manual = command.is_some();
+                let started_at = Local::now();
+                let quiet = !manual
+                    && task.notifies()
+                    && settings::quiet_hours(&db)
+                        .await
+                        .map_or(false, |quiet_hours| quiet_hours.contains(started_at.time()));
+
+                let (status, error, duration) = if quiet {
+                    info!("任务 {} 处于静默时段，跳过本次执行", id);
+                    (RunStatus::Skipped, None, Duration::ZERO)
+                } else {
+                    info.lock().unwrap().running = true;
+                    let start = Instant::now();
+                    let job = {
+                        let task = task.clone();
+                        let app_handle = app_handle.clone();
+                        async move { task.run(&app_handle).await }
+                    };
+                    let (status, error) = execute(job, task.timeout()).await;
+                    (status, error, start.elapsed())
+                };
+
+                match status {
+                    RunStatus::Success => failures = 0,
+                    RunStatus::Skipped => {}
+                    _ => {
+                        failures += 1;
+                        error!(
+                            "任务 {} 执行失败({}) - 连续失败: {}, 错误: {}",
+                            id,
+                            status.as_str(),
+                            failures,
+                            error.as_deref().unwrap_or_default()
+                        );
+                    }
+                }
+                let run = TaskRun {
+                    task_id: id.to_string(),
+                    status: status.as_str().to_string(),
+                    started_at: started_at.to_rfc3339(),
+                    duration_ms: duration.as_millis() as u64,
+                    error: error.clone(),
+                };
+                if let Err(e) = db.record_task_run(&run).await {
+                    error!("保存任务 {} 的运行记录失败: {}", id, e);
+                }
+
+                // 重新读取计划，用量周期等信息可能在执行过程中更新
+                schedule = resolve_schedule(task.as_ref(), &db).await;
+                if !manual {
+                    let now = Local::now();
+                    due = match schedule.interval() {
+                        Some(period) if failures >= BACKOFF_AFTER_FAILURES => {
+                            chrono::Duration::from_std(retry_delay(period, failures))
+                                .ok()
+                                .map(|delay| now + delay)
+                        }
+                        _ => schedule.next_run(due, now),
+                    };
+                }
+
+                let mut info = info.lock().unwrap();
+                info.running = false;
+                info.consecutive_failures = failures;
+                info.schedule = schedule.to_string();
+                if !quiet {
+                    info.last_run = Some(started_at.to_rfc3339());
+                    info.last_error = error;
+                }
+                if !paused {
+                    info.next_run = due.map(|due| due.to_rfc3339());
+                }
+            }
+            Some(Control::Pause) => {
+                paused = true;
+                info.lock().unwrap().next_run = None;
+            }
+            Some(Control::Resume) | Some(Control::Rearm) => {
+                if command == Some(Control::Resume) {
+                    paused = false;
+                }
+                schedule = resolve_schedule(task.as_ref(), &db).await;
+                due = schedule.next_run(None, Local::now());
+
+                let mut info = info.lock().unwrap();
+                info.schedule = schedule.to_string();
+                info.interval_secs = schedule.interval().map_or(0, |period| period.as_secs());
+                if !paused {
+                    info.next_run = due.map(|due| due.to_rfc3339());
+                    info!("任务 {} 的计划已更新为 {}", id, schedule);
+                }
+            }
+        }
+    }
+    warn!("任务 {} 已停止", id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        let period = Duration::from_secs(60);
+        assert_eq!(retry_delay(period, 0), period);
+        assert_eq!(retry_delay(period, 2), period);
+        assert_eq!(retry_delay(period, 3), period * 2);
+        assert_eq!(retry_delay(period, 4), period * 4);
+        assert_eq!(retry_delay(period, 100), MAX_BACKOFF);
+
+        // 间隔本身超过退避上限时保持原间隔
+        let daily = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(retry_delay(daily, 10), daily);
+    }
+
+    #[tokio::test]
+    async fn test_execute_isolates_failures() {
+        let limit = Duration::from_millis(200);
+        assert_eq!(execute(async { Ok(()) }, limit).await, (RunStatus::Success, None));
+        assert_eq!(
+            execute(async { Err("接口错误".to_string()) }, limit).await,
+            (RunStatus::Failed, Some("接口错误".to_string()))
+        );
+
+        let (status, error) = execute(async { panic!("越界") }, limit).await;
+        assert_eq!((status, error.as_deref()), (RunStatus::Panicked, Some("越界")));
+
+        let (status, _) = execute(
+            async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(())
+            },
+            limit,
+        )
+        .await;
+        assert_eq!(status, RunStatus::TimedOut);
+    }
+}
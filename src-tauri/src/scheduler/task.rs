@@ -0,0 +1,145 @@
+//! 定时任务接口：新任务只需要实现 ScheduledTask 并注册到 TaskRegistry
+
+use super::calendar::{self, CronExpr};
+use crate::database::Database;
+use crate::settings::SettingKey;
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime};
+use std::fmt;
+use std::time::Duration;
+use tauri::AppHandle;
+
+// 默认的单次执行超时时间
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 执行计划
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    // 固定间隔
+    Interval(Duration),
+    // cron 表达式
+    Cron(CronExpr),
+    // 每天在指定时间执行
+    Daily(NaiveTime),
+    // 每月在用量周期开始时执行
+    UsageCycle(NaiveDateTime),
+}
+
+impl Schedule {
+    /// 解析计划表达式，支持 cron 表达式、daily HH:MM 和 cycle(用量周期开始时)
+    pub fn parse(expr: &str, cycle_start: Option<NaiveDateTime>) -> Result<Self, String> {
+        let expr = expr.trim();
+        if expr == "cycle" {
+            return cycle_start
+                .map(Schedule::UsageCycle)
+                .ok_or_else(|| "尚未获取到用量周期".to_string());
+        }
+        if let Some(time) = expr.strip_prefix("daily ") {
+            return NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map(Schedule::Daily)
+                .map_err(|e| format!("时间无效: {} ({})", time.trim(), e));
+        }
+        expr.parse().map(Schedule::Cron)
+    }
+
+    /// 校验计划表达式，用量周期未知时也视为有效
+    pub fn validate(expr: &str) -> Result<(), String> {
+        Self::parse(expr, Some(NaiveDateTime::default())).map(|_| ())
+    }
+
+    /// 固定间隔计划的间隔时间
+    pub fn interval(&self) -> Option<Duration> {
+        match self {
+            Schedule::Interval(period) => Some(*period),
+            _ => None,
+        }
+    }
+
+    /// 下次执行时间，没有匹配的时间时返回 None
+    ///
+    /// 固定间隔从上次计划的执行时间开始计算，不受执行耗时影响，错过的执行不再补齐
+    pub fn next_run(
+        &self,
+        previous: Option<DateTime<Local>>,
+        now: DateTime<Local>,
+    ) -> Option<DateTime<Local>> {
+        match self {
+            Schedule::Interval(period) => {
+                let period = chrono::Duration::from_std(*period).ok()?;
+                if period <= chrono::Duration::zero() {
+                    return None;
+                }
+                let mut next = previous.unwrap_or(now) + period;
+                while next <= now {
+                    next += period;
+                }
+                Some(next)
+            }
+            Schedule::Cron(cron) => cron.next_after(now),
+            Schedule::Daily(time) => calendar::next_daily(*time, now),
+            Schedule::UsageCycle(anchor) => calendar::next_cycle_start(*anchor, now),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Interval(period) => write!(f, "every {}s", period.as_secs()),
+            Schedule::Cron(cron) => write!(f, "{}", cron),
+            Schedule::Daily(time) => write!(f, "daily {}", time.format("%H:%M")),
+            Schedule::UsageCycle(_) => f.write_str("cycle"),
+        }
+    }
+}
+
+/// 定时任务
+#[async_trait]
+pub trait ScheduledTask: Send + Sync + 'static {
+    /// 任务ID，用于控制任务和记录运行历史
+    fn id(&self) -> &'static str;
+
+    /// 默认的执行计划，任务启动和计划设置变更时读取，可以被任务计划设置覆盖
+    async fn schedule(&self, db: &Database) -> Schedule;
+
+    /// 保存执行间隔的设置项，变更后按新计划重新计时
+    fn schedule_key(&self) -> Option<SettingKey> {
+        None
+    }
+
+    /// 单次执行的超时时间，超时后取消本次执行
+    fn timeout(&self) -> Duration {
+        DEFAULT_TIMEOUT
+    }
+
+    /// 是否会发送通知，会发送通知的任务在静默时段内不执行
+    fn notifies(&self) -> bool {
+        false
+    }
+
+    /// 执行一次任务
+    async fn run(&self, app_handle: &AppHandle) -> Result<(), String>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_interval_does_not_drift() {
+        let at = |h, m, s| Local.with_ymd_and_hms(2025, 1, 10, h, m, s).unwrap();
+        let schedule = Schedule::Interval(Duration::from_secs(300));
+
+        assert_eq!(schedule.next_run(None, at(9, 0, 0)), Some(at(9, 5, 0)));
+        // 执行耗时不影响下次执行时间
+        assert_eq!(schedule.next_run(Some(at(9, 5, 0)), at(9, 5, 40)), Some(at(9, 10, 0)));
+        // 休眠后跳过错过的执行
+        assert_eq!(schedule.next_run(Some(at(9, 5, 0)), at(9, 31, 0)), Some(at(9, 35, 0)));
+
+        assert_eq!(Schedule::parse(" daily 09:00 ", None).unwrap().to_string(), "daily 09:00");
+        assert!(Schedule::parse("cycle", None).is_err());
+        assert!(Schedule::validate("cycle").is_ok());
+        assert!(Schedule::validate("daily 25:00").is_err());
+    }
+}
@@ -1,9 +1,16 @@
+use super::alerts::{self, Alert};
+use super::calendar;
+use super::task::{Schedule, ScheduledTask};
 use crate::api::client::ApiClient;
+use crate::api::inbound;
 use crate::config;
 use crate::cursor_reset::commands;
 use crate::database::Database;
+use crate::settings::{self, SettingKey};
 use crate::utils::ErrorReporter;
 use crate::utils::retry;
+use async_trait::async_trait;
+use chrono::Local;
 use serde_json::Value;
 use std::time::Duration;
 use tauri::{AppHandle, Emitter, Manager};
@@ -17,10 +24,9 @@ pub async fn check_account_limit(app_handle: &AppHandle) -> Result<(), String> {
     // 使用 retry 函数获取机器码信息
     let info = retry::retry(
         || commands::get_machine_ids(db.clone()),
-        3,
-        Duration::from_millis(500),
+        &config::get_retry_policy(),
         "获取机器码信息"
-    ).await?;
+    ).await.map_err(|e| e.to_string())?;
     
     // 提取当前账户信息
     let current_account = match info.get("currentAccount") {
@@ -54,6 +60,8 @@ pub async fn check_account_limit(app_handle: &AppHandle) -> Result<(), String> {
     let usage_data = match usage_result {
         Ok(response) => {
             if let Some(data) = response.data {
+                // 记录用量周期，按用量周期执行的任务据此计算执行时间
+                calendar::save_usage_cycle_start(db.inner(), &data.start_of_month).await;
                 Value::Object(serde_json::to_value(data).unwrap_or_default().as_object().unwrap_or(&serde_json::Map::new()).clone())
             } else {
                 let err_msg = format!("获取使用情况失败: {}", response.message);
@@ -77,66 +85,52 @@ pub async fn check_account_limit(app_handle: &AppHandle) -> Result<(), String> {
         }
     };
     
-    // 从数据库获取警告阈值
-    let account_threshold_key = config::get_db_key("account_usage_threshold");
-    let account_usage_threshold = match db.inner().get_item(&account_threshold_key) {
-        Ok(Some(val)) => match val.parse::<f64>() {
-            Ok(v) => v,
-            Err(_) => {
-                // 使用默认值
-                config::get_account_usage_threshold()
-            }
-        },
-        _ => {
-            // 使用默认值
-            config::get_account_usage_threshold()
-        }
-    };
-    
-    // 检查GPT-4使用情况
-    if let Some(gpt4_usage) = usage_data.get("gpt-4") {
-        if let (Some(Value::Number(used)), Some(Value::Number(total))) = 
-            (gpt4_usage.get("numRequests"), gpt4_usage.get("maxRequestUsage")) 
-        {
-            let used_f = used.as_f64().unwrap_or(0.0);
-            let mut total_f = total.as_f64().unwrap_or(1.0);
-            
-            if total_f == 150.0 {
-                if used_f < 51.0 {
-                    // 如果使用量小于52，将最大数量调整为50
-                    total_f = 50.0;
-                } else if used_f < 101.0 {
-                    // 如果使用量小于102，将最大数量调整为100
-                    total_f = 100.0;
-                }
-            }
-            
-            // 只有当total不为0或接近无限大的值时才进行计算
-            if total_f > 0.0 && total_f < 9990.0 {
-                let remaining_ratio = (total_f - used_f) / total_f;
-                
-                if remaining_ratio <= account_usage_threshold {
-                    // 确保百分比不小于0
-                    let remaining_percentage = (remaining_ratio * 100.0).round().max(0.0);
-                    
-                    // 发送通知到前端，直接传递账户名和剩余百分比
-                    send_notification(app_handle, &current_account, remaining_percentage as i64).await?;
-                }
-            }
+    // 按模型检查各个警告级别，每个级别在一个用量周期内只提醒一次
+    let cycle_start = usage_data
+        .get("startOfMonth")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let usages = alerts::parse_usage(&usage_data);
+    let levels = settings::usage_alert_levels(db.inner()).await;
+    let mut fired = db
+        .get_usage_alerts(&current_account)
+        .await
+        .map_err(|e| format!("读取用量警告记录失败: {}", e))?;
+    let saved = fired.clone();
+    let now = Local::now().to_rfc3339();
+    let pending = alerts::evaluate(&usages, &levels, &mut fired, &cycle_start, &now);
+
+    // 发送失败的警告不保存，下次检查会重新提醒；已发送的警告即使后面的发送失败也要保存
+    let mut send_error = None;
+    for alert in &pending {
+        if let Err(e) = send_notification(app_handle, &current_account, alert).await {
+            alerts::revert(&mut fired, &saved, &alert.model);
+            send_error.get_or_insert(e);
         }
     }
-    Ok(())
+    if fired != saved {
+        db.replace_usage_alerts(&current_account, &fired)
+            .await
+            .map_err(|e| format!("保存用量警告记录失败: {}", e))?;
+    }
+    match send_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
 }
 
 /// 发送通知到前端
-async fn send_notification(app_handle: &AppHandle, account: &str, remaining_percentage: i64) -> Result<(), String> {
-    // 只向前端发送账户数据和剩余使用量百分比
+async fn send_notification(app_handle: &AppHandle, account: &str, alert: &Alert) -> Result<(), String> {
+    // 只向前端发送账户、模型、触发的级别和剩余使用量百分比
     if let Some(window) = app_handle.get_webview_window("main") {
         if let Err(e) = window.emit("account-usage-warning", 
             serde_json::json!({
                 "data": {
                     "account": account,
-                    "remaining_percentage": remaining_percentage
+                    "model": alert.model,
+                    "level": (alert.level * 100.0).round() as i64,
+                    "remaining_percentage": (alert.remaining_ratio * 100.0).round() as i64
                 }
             })
         ) {
@@ -150,4 +144,131 @@ async fn send_notification(app_handle: &AppHandle, account: &str, remaining_perc
         error!("{}", err_msg);
         Err(err_msg)
     }
-} 
\ No newline at end of file
+}
+
+/// 测试所有线路，当前线路不可用时切换
+pub async fn probe_inbound_lines(app_handle: &AppHandle) -> Result<(), String> {
+    let probes = inbound::probe_lines(app_handle).await;
+    if !probes.is_empty() && probes.iter().all(|probe| probe.median_ms.is_none()) {
+        return Err("所有线路均不可用".to_string());
+    }
+    Ok(())
+}
+
+/// 刷新远程配置，线路发生变化时重新测速
+pub async fn refresh_remote_config(app_handle: &AppHandle) -> Result<(), String> {
+    let (_, changed) = inbound::refresh_inbound_config(app_handle, false).await?;
+    if changed {
+        inbound::probe_lines(app_handle).await;
+    }
+    Ok(())
+}
+
+/// 仪表盘刷新任务
+pub struct DashboardRefreshTask;
+
+#[async_trait]
+impl ScheduledTask for DashboardRefreshTask {
+    fn id(&self) -> &'static str {
+        "refresh_dashboard"
+    }
+
+    async fn schedule(&self, db: &Database) -> Schedule {
+        Schedule::Interval(Duration::from_secs(settings::dashboard_refresh_interval(db).await))
+    }
+
+    fn schedule_key(&self) -> Option<SettingKey> {
+        Some(SettingKey::DashboardRefreshInterval)
+    }
+
+    // 只是通知前端，不应该耗时
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    async fn run(&self, app_handle: &AppHandle) -> Result<(), String> {
+        // 通知前端刷新仪表盘
+        match app_handle.get_webview_window("main") {
+            Some(window) => window
+                .emit("refresh-dashboard", ())
+                .map_err(|e| format!("发送刷新事件失败: {}", e)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 账户使用限制检查任务
+pub struct AccountLimitCheckTask;
+
+#[async_trait]
+impl ScheduledTask for AccountLimitCheckTask {
+    fn id(&self) -> &'static str {
+        "check_account_limit"
+    }
+
+    async fn schedule(&self, db: &Database) -> Schedule {
+        Schedule::Interval(Duration::from_secs(settings::account_limit_check_interval(db).await))
+    }
+
+    fn schedule_key(&self) -> Option<SettingKey> {
+        Some(SettingKey::AccountLimitCheckInterval)
+    }
+
+    fn notifies(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, app_handle: &AppHandle) -> Result<(), String> {
+        check_account_limit(app_handle)
+            .await
+            .map_err(|e| format!("检查账户使用限制失败: {}", e))
+    }
+}
+
+/// 线路测速任务，定期检查线路健康状态，让失败的线路有机会恢复
+pub struct InboundProbeTask;
+
+#[async_trait]
+impl ScheduledTask for InboundProbeTask {
+    fn id(&self) -> &'static str {
+        "probe_inbound_lines"
+    }
+
+    async fn schedule(&self, db: &Database) -> Schedule {
+        Schedule::Interval(Duration::from_secs(settings::inbound_probe_interval(db).await))
+    }
+
+    fn schedule_key(&self) -> Option<SettingKey> {
+        Some(SettingKey::InboundProbeInterval)
+    }
+
+    async fn run(&self, app_handle: &AppHandle) -> Result<(), String> {
+        probe_inbound_lines(app_handle)
+            .await
+            .map_err(|e| format!("线路测速失败: {}", e))
+    }
+}
+
+/// 远程配置刷新任务，缓存过期后在后台重新获取线路配置
+pub struct ConfigRefreshTask;
+
+#[async_trait]
+impl ScheduledTask for ConfigRefreshTask {
+    fn id(&self) -> &'static str {
+        "refresh_remote_config"
+    }
+
+    async fn schedule(&self, db: &Database) -> Schedule {
+        Schedule::Interval(Duration::from_secs(settings::config_refresh_interval(db).await))
+    }
+
+    fn schedule_key(&self) -> Option<SettingKey> {
+        Some(SettingKey::ConfigRefreshInterval)
+    }
+
+    async fn run(&self, app_handle: &AppHandle) -> Result<(), String> {
+        refresh_remote_config(app_handle)
+            .await
+            .map_err(|e| format!("刷新远程配置失败: {}", e))
+    }
+}
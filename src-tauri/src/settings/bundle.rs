@@ -0,0 +1,277 @@
+//! 设置导入导出
+
+use super::SettingKey;
+use crate::database::Database;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::{error, info};
+
+// 设置文件格式版本
+const BUNDLE_VERSION: u32 = 1;
+
+/// 导出的设置文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub version: u32,
+    pub app_version: String,
+    pub exported_at: String,
+    pub settings: BTreeMap<SettingKey, String>,
+}
+
+/// 导入方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    // 只覆盖文件中包含的设置项
+    Merge,
+    // 文件中不包含的设置项恢复为默认值
+    Replace,
+}
+
+/// 单个设置项的变更
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingDiff {
+    pub key: SettingKey,
+    pub current: Option<String>,
+    // 导入后的值，None 表示恢复默认值
+    pub incoming: Option<String>,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub changes: Vec<SettingDiff>,
+}
+
+impl SettingsBundle {
+    /// 从数据库收集可导出的设置，敏感项和运行状态不导出
+    pub async fn collect(db: &Database) -> Result<Self, String> {
+        let mut settings = BTreeMap::new();
+        for (key, value) in stored_settings(db).await? {
+            if key.is_portable() {
+                settings.insert(key, value);
+            }
+        }
+
+        Ok(Self {
+            version: BUNDLE_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: Local::now().to_rfc3339(),
+            settings,
+        })
+    }
+
+    /// 从文件读取并校验设置
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("读取设置文件失败: {}", e))?;
+        let bundle: Self =
+            serde_json::from_str(&content).map_err(|e| format!("设置文件格式错误: {}", e))?;
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    /// 保存到文件
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content =
+            serde_json::to_string_pretty(self).map_err(|e| format!("序列化设置失败: {}", e))?;
+        fs::write(path, content).map_err(|e| format!("写入设置文件失败: {}", e))
+    }
+
+    /// 校验版本和所有设置值
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version == 0 || self.version > BUNDLE_VERSION {
+            return Err(format!("不支持的设置文件版本: {}", self.version));
+        }
+
+        let errors: Vec<String> = self
+            .settings
+            .iter()
+            .filter_map(|(key, value)| {
+                if key.is_secret() {
+                    Some(format!("{:?}: 敏感设置项不能导入", key))
+                } else if !key.is_portable() {
+                    Some(format!("{:?}: 该设置项不能导入", key))
+                } else {
+                    key.validate(value).err().map(|e| format!("{:?}: {}", key, e))
+                }
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("设置文件校验失败: {}", errors.join("; ")))
+        }
+    }
+
+    /// 计算导入后会发生的变更
+    pub fn diff(&self, current: &BTreeMap<SettingKey, String>, mode: ImportMode) -> Vec<SettingDiff> {
+        SettingKey::ALL
+            .into_iter()
+            .filter(|key| key.is_portable())
+            .filter_map(|key| {
+                let incoming = match (self.settings.get(&key), mode) {
+                    (Some(value), _) => Some(value.clone()),
+                    // 合并模式下文件中没有的设置项保持不变
+                    (None, ImportMode::Merge) => return None,
+                    (None, ImportMode::Replace) => None,
+                };
+                let current = current.get(&key).cloned();
+                if current == incoming {
+                    None
+                } else {
+                    Some(SettingDiff {
+                        key,
+                        current,
+                        incoming,
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+// 读取数据库中已保存的设置项
+async fn stored_settings(db: &Database) -> Result<BTreeMap<SettingKey, String>, String> {
+    let mut settings = BTreeMap::new();
+    for key in SettingKey::ALL {
+        if let Some(value) = db
+            .get_item(&key.db_key())
+            .await
+            .map_err(|e| format!("读取设置失败: {}", e))?
+        {
+            settings.insert(key, value);
+        }
+    }
+    Ok(settings)
+}
+
+/// 导出设置到文件
+pub async fn export(db: &Database, path: &Path) -> Result<(), String> {
+    let bundle = SettingsBundle::collect(db).await?;
+    bundle.save(path)?;
+    info!(target: "settings", "已导出 {} 项设置到: {:?}", bundle.settings.len(), path);
+    Ok(())
+}
+
+/// 从文件导入设置，dry_run 时只返回变更不写入
+pub async fn import(
+    db: &Database,
+    path: &Path,
+    mode: ImportMode,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let bundle = SettingsBundle::load(path)?;
+    let changes = bundle.diff(&stored_settings(db).await?, mode);
+
+    if !dry_run {
+        for change in &changes {
+            let result = match &change.incoming {
+                Some(value) => super::set(db, change.key, value).await,
+                None => super::remove(db, change.key).await,
+            };
+            result.map_err(|e| {
+                error!(target: "settings", "导入设置失败 - 键: {:?}, 错误: {}", change.key, e);
+                e.to_string()
+            })?;
+        }
+        info!(target: "settings", "已从 {:?} 导入 {} 项设置", path, changes.len());
+    }
+
+    Ok(ImportReport { dry_run, changes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(settings: &[(SettingKey, &str)]) -> SettingsBundle {
+        SettingsBundle {
+            version: BUNDLE_VERSION,
+            app_version: "test".to_string(),
+            exported_at: String::new(),
+            settings: settings
+                .iter()
+                .map(|(k, v)| (*k, v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_values() {
+        assert!(bundle(&[(SettingKey::DashboardRefreshInterval, "60")])
+            .validate()
+            .is_ok());
+        assert!(bundle(&[(SettingKey::DashboardRefreshInterval, "1")])
+            .validate()
+            .is_err());
+        assert!(bundle(&[(SettingKey::Token, "secret")]).validate().is_err());
+        assert!(bundle(&[(SettingKey::UsageCycleStart, "2024-01-01 00:00:00")])
+            .validate()
+            .is_err());
+
+        let mut future = bundle(&[]);
+        future.version = BUNDLE_VERSION + 1;
+        assert!(future.validate().is_err());
+    }
+
+    #[test]
+    fn test_rejects_inbound_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let content = serde_json::json!({
+            "version": BUNDLE_VERSION,
+            "app_version": "test",
+            "exported_at": "",
+            "settings": {
+                "inbound_config": r#"{"inbound":[{"name":"线路","url":"https://evil.example"}]}"#
+            }
+        });
+        fs::write(&path, content.to_string()).unwrap();
+
+        let err = SettingsBundle::load(&path).unwrap_err();
+        assert!(err.contains("InboundConfig"));
+    }
+
+    #[test]
+    fn test_diff_modes() {
+        let current: BTreeMap<SettingKey, String> = [
+            (SettingKey::Lang, "zh-CN".to_string()),
+            (SettingKey::CurrentInbound, "1".to_string()),
+            (SettingKey::Token, "secret".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let incoming = bundle(&[(SettingKey::Lang, "en"), (SettingKey::CurrentInbound, "1")]);
+
+        let merged = incoming.diff(&current, ImportMode::Merge);
+        assert_eq!(
+            merged,
+            vec![SettingDiff {
+                key: SettingKey::Lang,
+                current: Some("zh-CN".to_string()),
+                incoming: Some("en".to_string()),
+            }]
+        );
+
+        // 替换模式不会影响敏感设置项
+        assert_eq!(incoming.diff(&current, ImportMode::Replace), merged);
+
+        let replaced = bundle(&[]).diff(&current, ImportMode::Replace);
+        assert_eq!(replaced.len(), 2);
+        assert!(replaced.iter().all(|d| d.incoming.is_none()));
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        bundle(&[(SettingKey::Lang, "en")]).save(&path).unwrap();
+
+        let loaded = SettingsBundle::load(&path).unwrap();
+        assert_eq!(loaded.settings.get(&SettingKey::Lang).map(String::as_str), Some("en"));
+    }
+}
@@ -0,0 +1,583 @@
+//! 类型化的设置层，封装 item 表中的设置项
+
+use crate::config;
+use crate::database::{Database, DatabaseError};
+use crate::scheduler::calendar::QuietHours;
+use crate::scheduler::Schedule;
+use crate::utils::http::{self, ProxySettings};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tokio::sync::broadcast;
+use tracing::{error, warn};
+
+mod bundle;
+
+pub use bundle::{export, import, ImportMode, ImportReport, SettingDiff, SettingsBundle};
+
+// 任务间隔允许范围(秒)
+const MIN_INTERVAL_SECS: u64 = 10;
+const MAX_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+/// 设置项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingKey {
+    // 线路配置
+    InboundConfig,
+    // 当前线路索引
+    CurrentInbound,
+    // 手动固定的线路索引，固定后不自动切换
+    PinnedInbound,
+    // Cursor main.js 路径
+    CursorMainJsPath,
+    // 后端API令牌
+    Token,
+    // 界面语言
+    Lang,
+    // 历史账户记录，包含 Cursor 令牌
+    HistoryAccounts,
+    // 仪表盘刷新间隔(秒)
+    DashboardRefreshInterval,
+    // 账户限制检查间隔(秒)
+    AccountLimitCheckInterval,
+    // 账户使用量警告阈值(0-1)
+    AccountUsageThreshold,
+    // 各模型的用量警告级别，模型名到剩余比例(0-1)列表的映射，未设置时使用 AccountUsageThreshold 检查 gpt-4
+    UsageAlertLevels,
+    // 线路测速间隔(秒)
+    InboundProbeInterval,
+    // 远程配置刷新间隔(秒)
+    ConfigRefreshInterval,
+    // 任务计划，任务ID到计划表达式的映射，覆盖任务的默认间隔
+    TaskSchedules,
+    // 已暂停的任务ID列表
+    PausedTasks,
+    // 静默时段(HH:MM-HH:MM)，时段内不执行会发送通知的任务
+    QuietHours,
+    // 最近一次获取到的用量周期开始时间(RFC 3339)
+    UsageCycleStart,
+    // 网络代理，可能包含代理认证信息
+    Proxy,
+}
+
+impl SettingKey {
+    /// 所有设置项
+    pub const ALL: [SettingKey; 18] = [
+        SettingKey::InboundConfig,
+        SettingKey::CurrentInbound,
+        SettingKey::PinnedInbound,
+        SettingKey::CursorMainJsPath,
+        SettingKey::Token,
+        SettingKey::Lang,
+        SettingKey::HistoryAccounts,
+        SettingKey::DashboardRefreshInterval,
+        SettingKey::AccountLimitCheckInterval,
+        SettingKey::AccountUsageThreshold,
+        SettingKey::UsageAlertLevels,
+        SettingKey::InboundProbeInterval,
+        SettingKey::ConfigRefreshInterval,
+        SettingKey::TaskSchedules,
+        SettingKey::PausedTasks,
+        SettingKey::QuietHours,
+        SettingKey::UsageCycleStart,
+        SettingKey::Proxy,
+    ];
+
+    /// 获取设置项在数据库中的键名
+    pub fn db_key(self) -> String {
+        let config = config::CONFIG.read().unwrap();
+        let keys = &config.db_keys;
+        match self {
+            SettingKey::InboundConfig => keys.inbound_config_key.clone(),
+            SettingKey::CurrentInbound => keys.current_inbound_key.clone(),
+            SettingKey::PinnedInbound => keys.pinned_inbound_key.clone(),
+            SettingKey::CursorMainJsPath => keys.cursor_main_js_path_key.clone(),
+            SettingKey::Token => keys.token_key.clone(),
+            SettingKey::Lang => keys.lang_key.clone(),
+            SettingKey::HistoryAccounts => keys.history_accounts_key.clone(),
+            SettingKey::DashboardRefreshInterval => keys.dashboard_refresh_interval_key.clone(),
+            SettingKey::AccountLimitCheckInterval => {
+                keys.account_limit_check_interval_key.clone()
+            }
+            SettingKey::AccountUsageThreshold => keys.account_usage_threshold_key.clone(),
+            SettingKey::UsageAlertLevels => keys.usage_alert_levels_key.clone(),
+            SettingKey::InboundProbeInterval => keys.inbound_probe_interval_key.clone(),
+            SettingKey::ConfigRefreshInterval => keys.config_refresh_interval_key.clone(),
+            SettingKey::TaskSchedules => keys.task_schedules_key.clone(),
+            SettingKey::PausedTasks => keys.paused_tasks_key.clone(),
+            SettingKey::QuietHours => keys.quiet_hours_key.clone(),
+            SettingKey::UsageCycleStart => keys.usage_cycle_start_key.clone(),
+            SettingKey::Proxy => keys.proxy_key.clone(),
+        }
+    }
+
+    /// 是否为敏感设置项，敏感项加密存储且不参与导出
+    pub fn is_secret(self) -> bool {
+        matches!(
+            self,
+            SettingKey::Token | SettingKey::Proxy | SettingKey::HistoryAccounts
+        )
+    }
+
+    /// 是否可以导入导出。敏感项、签名校验后才能应用的线路配置和运行时记录的状态不参与
+    pub fn is_portable(self) -> bool {
+        !self.is_secret()
+            && !matches!(self, SettingKey::InboundConfig | SettingKey::UsageCycleStart)
+    }
+
+    /// 根据数据库键名查找设置项
+    pub fn from_db_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.db_key() == key)
+    }
+
+    /// 获取设置项的默认值
+    pub fn default_value(self) -> Option<String> {
+        let scheduler = config::get_scheduler_config();
+        match self {
+            SettingKey::CurrentInbound => Some("0".to_string()),
+            SettingKey::Lang => Some("zh-CN".to_string()),
+            SettingKey::DashboardRefreshInterval => {
+                Some(scheduler.dashboard_refresh_interval.to_string())
+            }
+            SettingKey::AccountLimitCheckInterval => {
+                Some(scheduler.account_limit_check_interval.to_string())
+            }
+            SettingKey::AccountUsageThreshold => {
+                Some(scheduler.account_usage_threshold.to_string())
+            }
+            SettingKey::InboundProbeInterval => Some(scheduler.inbound_probe_interval.to_string()),
+            SettingKey::ConfigRefreshInterval => {
+                Some(scheduler.config_refresh_interval.to_string())
+            }
+            SettingKey::InboundConfig
+            | SettingKey::PinnedInbound
+            | SettingKey::CursorMainJsPath
+            | SettingKey::Token
+            | SettingKey::HistoryAccounts
+            | SettingKey::UsageAlertLevels
+            | SettingKey::TaskSchedules
+            | SettingKey::PausedTasks
+            | SettingKey::QuietHours
+            | SettingKey::UsageCycleStart
+            | SettingKey::Proxy => None,
+        }
+    }
+
+    /// 校验设置值
+    pub fn validate(self, value: &str) -> Result<(), String> {
+        match self {
+            SettingKey::InboundConfig => {
+                serde_json::from_str::<crate::api::InboundConfig>(value)
+                    .map(|_| ())
+                    .map_err(|e| format!("线路配置格式错误: {}", e))
+            }
+            SettingKey::CurrentInbound | SettingKey::PinnedInbound => value
+                .parse::<usize>()
+                .map(|_| ())
+                .map_err(|e| format!("线路索引无效: {}", e)),
+            SettingKey::CursorMainJsPath | SettingKey::Token | SettingKey::Lang => {
+                if value.trim().is_empty() {
+                    Err(format!("{} 不能为空", self.db_key()))
+                } else {
+                    Ok(())
+                }
+            }
+            SettingKey::HistoryAccounts => {
+                serde_json::from_str::<Vec<crate::api::types::HistoryAccountRecord>>(value)
+                    .map(|_| ())
+                    .map_err(|e| format!("历史账户格式错误: {}", e))
+            }
+            SettingKey::DashboardRefreshInterval
+            | SettingKey::AccountLimitCheckInterval
+            | SettingKey::InboundProbeInterval
+            | SettingKey::ConfigRefreshInterval => {
+                let interval = value
+                    .parse::<u64>()
+                    .map_err(|e| format!("任务间隔无效: {}", e))?;
+                if (MIN_INTERVAL_SECS..=MAX_INTERVAL_SECS).contains(&interval) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "任务间隔必须在 {} 到 {} 秒之间",
+                        MIN_INTERVAL_SECS, MAX_INTERVAL_SECS
+                    ))
+                }
+            }
+            SettingKey::TaskSchedules => {
+                let schedules = serde_json::from_str::<BTreeMap<String, String>>(value)
+                    .map_err(|e| format!("任务计划格式错误: {}", e))?;
+                schedules.iter().try_for_each(|(task_id, expr)| {
+                    Schedule::validate(expr).map_err(|e| format!("任务 {} 的计划无效: {}", task_id, e))
+                })
+            }
+            SettingKey::PausedTasks => serde_json::from_str::<BTreeSet<String>>(value)
+                .map(|_| ())
+                .map_err(|e| format!("已暂停任务格式错误: {}", e)),
+            SettingKey::UsageAlertLevels => {
+                let levels = serde_json::from_str::<BTreeMap<String, Vec<f64>>>(value)
+                    .map_err(|e| format!("用量警告级别格式错误: {}", e))?;
+                for (model, levels) in &levels {
+                    if model.trim().is_empty() || levels.is_empty() {
+                        return Err("用量警告级别需要指定模型和至少一个级别".to_string());
+                    }
+                    if levels.iter().any(|level| !(0.0..=1.0).contains(level)) {
+                        return Err(format!("模型 {} 的用量警告级别必须在 0 到 1 之间", model));
+                    }
+                }
+                Ok(())
+            }
+            SettingKey::QuietHours => value.parse::<QuietHours>().map(|_| ()),
+            SettingKey::UsageCycleStart => chrono::DateTime::parse_from_rfc3339(value)
+                .map(|_| ())
+                .map_err(|e| format!("用量周期开始时间格式无效: {}", e)),
+            SettingKey::Proxy => serde_json::from_str::<ProxySettings>(value)
+                .map_err(|e| format!("代理设置格式错误: {}", e))?
+                .validate(),
+            SettingKey::AccountUsageThreshold => {
+                let threshold = value
+                    .parse::<f64>()
+                    .map_err(|e| format!("使用量阈值无效: {}", e))?;
+                if (0.0..=1.0).contains(&threshold) {
+                    Ok(())
+                } else {
+                    Err("使用量阈值必须在 0 到 1 之间".to_string())
+                }
+            }
+        }
+    }
+}
+
+/// 设置变更通知
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingChange {
+    pub key: SettingKey,
+    // 新值，删除时为 None
+    pub value: Option<String>,
+}
+
+lazy_static! {
+    static ref CHANGES: broadcast::Sender<SettingChange> = broadcast::channel(32).0;
+}
+
+/// 订阅设置变更
+pub fn subscribe() -> broadcast::Receiver<SettingChange> {
+    CHANGES.subscribe()
+}
+
+fn announce(key: SettingKey, value: Option<String>) {
+    // 没有订阅者时发送会失败，可以忽略
+    let _ = CHANGES.send(SettingChange { key, value });
+}
+
+/// 设置保存失败的原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsError {
+    // 设置值无效
+    Invalid(String),
+    // 读写数据库失败
+    Storage(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Invalid(msg) | SettingsError::Storage(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// 读取设置值，未设置时返回默认值
+///
+/// 读取失败时返回错误，敏感设置项的密钥未解锁时也返回错误而不是视为未设置
+pub async fn get(db: &Database, key: SettingKey) -> Result<Option<String>, DatabaseError> {
+    match db.get_item(&key.db_key()).await {
+        Ok(value) => Ok(value.or_else(|| key.default_value())),
+        Err(e) => {
+            error!(target: "settings", "读取设置失败 - 键: {:?}, 错误: {}", key, e);
+            Err(e)
+        }
+    }
+}
+
+// 读取设置值，读取失败时回退到默认值
+async fn get_or_default(db: &Database, key: SettingKey) -> Option<String> {
+    get(db, key).await.unwrap_or_else(|_| key.default_value())
+}
+
+/// 校验并保存设置值，成功后通知订阅者
+pub async fn set(db: &Database, key: SettingKey, value: &str) -> Result<(), SettingsError> {
+    key.validate(value).map_err(SettingsError::Invalid)?;
+    db.set_item(&key.db_key(), value).await.map_err(|e| {
+        error!(target: "settings", "保存设置失败 - 键: {:?}, 错误: {}", key, e);
+        SettingsError::Storage(format!("保存设置失败: {}", e))
+    })?;
+    announce(key, Some(value.to_string()));
+    Ok(())
+}
+
+/// 删除设置值，恢复为默认值
+pub async fn remove(db: &Database, key: SettingKey) -> Result<(), SettingsError> {
+    db.delete_item(&key.db_key()).await.map_err(|e| {
+        error!(target: "settings", "删除设置失败 - 键: {:?}, 错误: {}", key, e);
+        SettingsError::Storage(format!("删除设置失败: {}", e))
+    })?;
+    announce(key, key.default_value());
+    Ok(())
+}
+
+/// 将未设置的项写入默认值
+pub async fn persist_default(db: &Database, key: SettingKey) -> Result<(), SettingsError> {
+    if let (Ok(None), Some(default)) = (db.get_item(&key.db_key()).await, key.default_value()) {
+        db.set_item(&key.db_key(), &default)
+            .await
+            .map_err(|e| SettingsError::Storage(format!("保存默认设置失败: {}", e)))?;
+    }
+    Ok(())
+}
+
+// 读取并解析设置值，无效时回退到默认值
+async fn get_parsed<T: FromStr>(db: &Database, key: SettingKey, fallback: T) -> T {
+    let stored = get_or_default(db, key).await;
+    if let Some(value) = stored.as_deref() {
+        if key.validate(value).is_ok() {
+            if let Ok(parsed) = value.parse::<T>() {
+                return parsed;
+            }
+        }
+        warn!(target: "settings", "设置值无效，使用默认值 - 键: {:?}, 值: {}", key, value);
+    }
+    key.default_value()
+        .and_then(|v| v.parse::<T>().ok())
+        .unwrap_or(fallback)
+}
+
+/// 仪表盘刷新间隔(秒)
+pub async fn dashboard_refresh_interval(db: &Database) -> u64 {
+    get_parsed(
+        db,
+        SettingKey::DashboardRefreshInterval,
+        config::get_scheduler_config().dashboard_refresh_interval,
+    )
+    .await
+}
+
+/// 设置仪表盘刷新间隔(秒)
+pub async fn set_dashboard_refresh_interval(db: &Database, secs: u64) -> Result<(), SettingsError> {
+    set(db, SettingKey::DashboardRefreshInterval, &secs.to_string()).await
+}
+
+/// 账户限制检查间隔(秒)
+pub async fn account_limit_check_interval(db: &Database) -> u64 {
+    get_parsed(
+        db,
+        SettingKey::AccountLimitCheckInterval,
+        config::get_scheduler_config().account_limit_check_interval,
+    )
+    .await
+}
+
+/// 设置账户限制检查间隔(秒)
+pub async fn set_account_limit_check_interval(db: &Database, secs: u64) -> Result<(), SettingsError> {
+    set(db, SettingKey::AccountLimitCheckInterval, &secs.to_string()).await
+}
+
+/// 账户使用量警告阈值(0-1)
+pub async fn account_usage_threshold(db: &Database) -> f64 {
+    get_parsed(
+        db,
+        SettingKey::AccountUsageThreshold,
+        config::get_account_usage_threshold(),
+    )
+    .await
+}
+
+/// 设置账户使用量警告阈值(0-1)
+pub async fn set_account_usage_threshold(db: &Database, threshold: f64) -> Result<(), SettingsError> {
+    set(db, SettingKey::AccountUsageThreshold, &threshold.to_string()).await
+}
+
+/// 各模型的用量警告级别(剩余比例，从高到低)，未设置时只检查 gpt-4 的 AccountUsageThreshold
+pub async fn usage_alert_levels(db: &Database) -> BTreeMap<String, Vec<f64>> {
+    let stored = get_or_default(db, SettingKey::UsageAlertLevels).await;
+    let mut levels = match stored.as_deref().map(|value| {
+        SettingKey::UsageAlertLevels.validate(value)?;
+        serde_json::from_str::<BTreeMap<String, Vec<f64>>>(value).map_err(|e| e.to_string())
+    }) {
+        Some(Ok(levels)) => levels,
+        Some(Err(e)) => {
+            warn!(target: "settings", "用量警告级别设置无效，使用默认阈值 - 错误: {}", e);
+            BTreeMap::new()
+        }
+        None => BTreeMap::new(),
+    };
+    if levels.is_empty() {
+        levels.insert("gpt-4".to_string(), vec![account_usage_threshold(db).await]);
+    }
+    for levels in levels.values_mut() {
+        levels.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        levels.dedup();
+    }
+    levels
+}
+
+/// 当前线路索引
+pub async fn current_inbound(db: &Database) -> usize {
+    get_parsed(db, SettingKey::CurrentInbound, 0).await
+}
+
+/// 设置当前线路索引
+pub async fn set_current_inbound(db: &Database, index: usize) -> Result<(), SettingsError> {
+    set(db, SettingKey::CurrentInbound, &index.to_string()).await
+}
+
+/// 手动固定的线路索引
+pub async fn pinned_inbound(db: &Database) -> Option<usize> {
+    get_or_default(db, SettingKey::PinnedInbound)
+        .await
+        .and_then(|v| v.parse().ok())
+}
+
+/// 固定线路，传入 None 时取消固定
+pub async fn set_pinned_inbound(db: &Database, index: Option<usize>) -> Result<(), SettingsError> {
+    match index {
+        Some(index) => set(db, SettingKey::PinnedInbound, &index.to_string()).await,
+        None => remove(db, SettingKey::PinnedInbound).await,
+    }
+}
+
+/// 线路测速间隔(秒)
+pub async fn inbound_probe_interval(db: &Database) -> u64 {
+    get_parsed(
+        db,
+        SettingKey::InboundProbeInterval,
+        config::get_scheduler_config().inbound_probe_interval,
+    )
+    .await
+}
+
+/// 远程配置刷新间隔(秒)
+pub async fn config_refresh_interval(db: &Database) -> u64 {
+    get_parsed(
+        db,
+        SettingKey::ConfigRefreshInterval,
+        config::get_scheduler_config().config_refresh_interval,
+    )
+    .await
+}
+
+/// 任务计划，任务ID到计划表达式的映射
+pub async fn task_schedules(db: &Database) -> BTreeMap<String, String> {
+    let stored = get_or_default(db, SettingKey::TaskSchedules).await;
+    if let Some(value) = stored.as_deref() {
+        match serde_json::from_str(value) {
+            Ok(schedules) => return schedules,
+            Err(_) => warn!(target: "settings", "任务计划设置无效，使用默认计划"),
+        }
+    }
+    BTreeMap::new()
+}
+
+/// 设置任务计划，传入 None 时恢复为任务的默认间隔
+pub async fn set_task_schedule(
+    db: &Database,
+    task_id: &str,
+    expr: Option<&str>,
+) -> Result<(), SettingsError> {
+    let mut schedules = task_schedules(db).await;
+    match expr {
+        Some(expr) => schedules.insert(task_id.to_string(), expr.trim().to_string()),
+        None => schedules.remove(task_id),
+    };
+    let value = serde_json::to_string(&schedules)
+        .map_err(|e| SettingsError::Invalid(format!("序列化任务计划失败: {}", e)))?;
+    set(db, SettingKey::TaskSchedules, &value).await
+}
+
+/// 已暂停的任务
+pub async fn paused_tasks(db: &Database) -> BTreeSet<String> {
+    let stored = get_or_default(db, SettingKey::PausedTasks).await;
+    if let Some(value) = stored.as_deref() {
+        match serde_json::from_str(value) {
+            Ok(paused) => return paused,
+            Err(_) => warn!(target: "settings", "已暂停任务设置无效，视为没有暂停的任务"),
+        }
+    }
+    BTreeSet::new()
+}
+
+/// 保存任务的暂停状态
+pub async fn set_task_paused(db: &Database, task_id: &str, paused: bool) -> Result<(), SettingsError> {
+    let mut paused_tasks = paused_tasks(db).await;
+    if paused {
+        paused_tasks.insert(task_id.to_string());
+    } else {
+        paused_tasks.remove(task_id);
+    }
+    let value = serde_json::to_string(&paused_tasks)
+        .map_err(|e| SettingsError::Invalid(format!("序列化已暂停任务失败: {}", e)))?;
+    set(db, SettingKey::PausedTasks, &value).await
+}
+
+/// 静默时段，未设置或无效时不启用
+pub async fn quiet_hours(db: &Database) -> Option<QuietHours> {
+    let value = get_or_default(db, SettingKey::QuietHours).await?;
+    match value.parse() {
+        Ok(quiet_hours) => Some(quiet_hours),
+        Err(e) => {
+            warn!(target: "settings", "静默时段设置无效，不启用 - 错误: {}", e);
+            None
+        }
+    }
+}
+
+/// 网络代理设置，未设置或无效时使用系统代理
+///
+/// 代理设置加密存储，密钥未解锁时返回错误，由调用方决定如何处理
+pub async fn proxy(db: &Database) -> Result<ProxySettings, DatabaseError> {
+    let stored = get(db, SettingKey::Proxy).await?;
+    if let Some(value) = stored.as_deref() {
+        if SettingKey::Proxy.validate(value).is_ok() {
+            if let Ok(proxy) = serde_json::from_str(value) {
+                return Ok(proxy);
+            }
+        }
+        warn!(target: "settings", "代理设置无效，使用系统代理");
+    }
+    Ok(ProxySettings::default())
+}
+
+/// 保存网络代理设置并立即生效
+pub async fn set_proxy(db: &Database, proxy: &ProxySettings) -> Result<(), SettingsError> {
+    let value = serde_json::to_string(proxy)
+        .map_err(|e| SettingsError::Invalid(format!("序列化代理设置失败: {}", e)))?;
+    set(db, SettingKey::Proxy, &value).await?;
+    http::set_proxy(proxy.clone());
+    Ok(())
+}
+
+/// 界面语言
+pub async fn lang(db: &Database) -> String {
+    get_or_default(db, SettingKey::Lang)
+        .await
+        .unwrap_or_else(|| "zh-CN".to_string())
+}
+
+/// 后端API令牌，密钥未解锁时无法使用令牌，视为未登录
+pub async fn token(db: &Database) -> Option<String> {
+    get(db, SettingKey::Token)
+        .await
+        .ok()
+        .flatten()
+        .filter(|t| !t.is_empty())
+}
+
+/// 保存的 Cursor main.js 路径
+pub async fn cursor_main_js_path(db: &Database) -> Option<PathBuf> {
+    get_or_default(db, SettingKey::CursorMainJsPath)
+        .await
+        .map(PathBuf::from)
+}
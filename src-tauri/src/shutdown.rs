@@ -0,0 +1,141 @@
+//! 退出协调：通知后台任务停止，在限定时间内等待完成，然后关闭数据库并刷新日志
+//!
+//! 所有退出路径(托盘退出、前端退出和重启、关闭最后一个窗口)都经过这里
+
+use crate::database::Database;
+use crate::scheduler::Scheduler;
+use crate::utils::logger;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Manager, RunEvent};
+use tokio::time::{timeout_at, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+// 等待后台任务和数据库写入完成的最长时间
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+// 与 tauri 重启应用时使用的退出码一致
+const RESTART_EXIT_CODE: i32 = i32::MAX;
+
+lazy_static! {
+    // 退出信号
+    static ref TOKEN: CancellationToken = CancellationToken::new();
+    // 需要在退出时等待的后台任务
+    static ref TASKS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+}
+
+// 是否已开始退出
+static STARTED: AtomicBool = AtomicBool::new(false);
+// 清理是否已完成，完成后不再拦截退出请求
+static FINISHED: AtomicBool = AtomicBool::new(false);
+
+/// 退出信号，后台任务在收到信号后停止
+pub fn token() -> CancellationToken {
+    TOKEN.child_token()
+}
+
+/// 启动后台任务，退出时取消并在宽限期内等待结束
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let token = token();
+    let handle = async_runtime::spawn(async move {
+        token.run_until_cancelled(future).await;
+    });
+    let mut tasks = TASKS.lock().unwrap();
+    tasks.retain(|task| !task.inner().is_finished());
+    tasks.push(handle);
+}
+
+/// 清理后重启应用
+pub fn restart(app: &AppHandle) {
+    exit(app, RESTART_EXIT_CODE);
+}
+
+/// 清理后退出应用
+pub fn exit(app: &AppHandle, code: i32) {
+    if FINISHED.load(Ordering::SeqCst) {
+        finish_exit(app, code);
+        return;
+    }
+    // 已经在退出
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        shutdown(&app).await;
+        finish_exit(&app, code);
+    });
+}
+
+fn finish_exit(app: &AppHandle, code: i32) {
+    if code == RESTART_EXIT_CODE {
+        app.restart();
+    } else {
+        app.exit(code);
+    }
+}
+
+/// 处理应用事件，拦截尚未清理的退出请求
+pub fn handle_run_event(app: &AppHandle, event: RunEvent) {
+    match event {
+        RunEvent::ExitRequested { code, api, .. } if !FINISHED.load(Ordering::SeqCst) => {
+            api.prevent_exit();
+            exit(app, code.unwrap_or(0));
+        }
+        // 未经过 exit 的退出(例如直接重启)只能同步关闭数据库并刷新日志
+        RunEvent::Exit if !FINISHED.load(Ordering::SeqCst) => {
+            TOKEN.cancel();
+            close(app);
+        }
+        _ => {}
+    }
+}
+
+// 通知后台任务停止并等待，超过宽限期后取消
+async fn shutdown(app: &AppHandle) {
+    info!("正在退出，等待后台任务结束");
+    TOKEN.cancel();
+    let deadline = Instant::now() + GRACE_PERIOD;
+
+    if let Some(scheduler) = app.try_state::<Scheduler>() {
+        scheduler.shutdown(deadline).await;
+    }
+
+    let tasks = std::mem::take(&mut *TASKS.lock().unwrap());
+    for mut task in tasks {
+        if timeout_at(deadline, &mut task).await.is_err() {
+            warn!("后台任务未在 {} 秒内结束，已取消", GRACE_PERIOD.as_secs());
+            task.abort();
+        }
+    }
+
+    // 数据库线程会先完成已提交的写入再关闭连接
+    let app_handle = app.clone();
+    let closing = async_runtime::spawn_blocking(move || close(&app_handle));
+    if timeout_at(deadline + GRACE_PERIOD, closing).await.is_err() {
+        error!("关闭数据库超时");
+    }
+}
+
+// 关闭数据库并刷新日志，只执行一次
+fn close(app: &AppHandle) {
+    if FINISHED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if let Some(db) = app.try_state::<Database>() {
+        if let Err(e) = db.close() {
+            error!("关闭数据库失败: {}", e);
+        }
+    }
+    info!("应用已退出");
+    logger::flush();
+}
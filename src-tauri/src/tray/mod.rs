@@ -1,11 +1,21 @@
 use tauri::{
     // Emitter,
     menu::{Menu, MenuItem},
-    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent},
     App,
     AppHandle,
+    Listener,
     Manager,
 };
+use tokio::sync::broadcast::error::RecvError;
+use tracing::error;
+
+use crate::auth::SESSION_EXPIRED_EVENT;
+use crate::settings::{self, SettingKey};
+use crate::shutdown;
+
+const TOOLTIP: &str = "Cursor Pool";
+const SESSION_EXPIRED_TOOLTIP: &str = "Cursor Pool - 登录已过期";
 
 // use crate::cursor_reset::reset_machine_id;
 
@@ -34,11 +44,11 @@ pub fn setup_system_tray(app: &App) -> Result<(), Box<dyn std::error::Error>> {
         ],
     )?;
 
-    let _tray = TrayIconBuilder::new()
+    let tray = TrayIconBuilder::new()
         .icon(app.default_window_icon().unwrap().clone())
         .menu(&menu)
         .show_menu_on_left_click(false)
-        .tooltip("Cursor Pool")
+        .tooltip(TOOLTIP)
         .on_tray_icon_event(|tray, event| {
             if let TrayIconEvent::Click {
                 button: MouseButton::Left,
@@ -52,7 +62,7 @@ pub fn setup_system_tray(app: &App) -> Result<(), Box<dyn std::error::Error>> {
         .on_menu_event(move |app, event| {
             match event.id.as_ref() {
                 "quit" => {
-                    app.exit(0);
+                    shutdown::exit(app, 0);
                 }
                 "show" => {
                     show_and_focus_window(app.app_handle());
@@ -99,5 +109,32 @@ pub fn setup_system_tray(app: &App) -> Result<(), Box<dyn std::error::Error>> {
         })
         .build(app)?;
 
+    watch_session(app, tray);
+
     Ok(())
 }
+
+// 登录失效时在托盘提示，重新登录后恢复
+fn watch_session(app: &App, tray: TrayIcon) {
+    let expired_tray = tray.clone();
+    app.listen(SESSION_EXPIRED_EVENT, move |_| {
+        if let Err(e) = expired_tray.set_tooltip(Some(SESSION_EXPIRED_TOOLTIP)) {
+            error!(target: "tray", "更新托盘提示失败: {}", e);
+        }
+    });
+
+    shutdown::spawn(async move {
+        let mut changes = settings::subscribe();
+        loop {
+            match changes.recv().await {
+                Ok(change) if change.key == SettingKey::Token && change.value.is_some() => {
+                    if let Err(e) = tray.set_tooltip(Some(TOOLTIP)) {
+                        error!(target: "tray", "更新托盘提示失败: {}", e);
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
@@ -6,7 +6,7 @@ pub struct CursorVersion;
 impl CursorVersion {
     /// 从 SQLite 数据库中获取 Cursor 版本号
     pub fn get_version() -> Result<String, String> {
-        let paths = AppPaths::new()?;
+        let paths = AppPaths::new().map_err(|e| e.to_string())?;
 
         // 检查数据库文件是否存在
         if !paths.db.exists() {
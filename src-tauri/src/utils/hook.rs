@@ -1,6 +1,7 @@
 use crate::api::client::ApiClient;
 use crate::database::Database;
-use crate::utils::paths::AppPaths;
+use crate::error::AppError;
+use crate::utils::paths::{AppPaths, PathError};
 use crate::utils::ErrorReporter;
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -28,6 +29,8 @@ pub struct Hook;
 pub enum HookError {
     // 找不到main.js路径
     MainJsNotFound(String),
+    // 无法确定应用路径
+    Paths(PathError),
     // 其他错误
     Other(String),
 }
@@ -35,7 +38,8 @@ pub enum HookError {
 impl fmt::Display for HookError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            HookError::MainJsNotFound(msg) => write!(f, "MAIN_JS_NOT_FOUND:{}", msg),
+            HookError::MainJsNotFound(msg) => write!(f, "{}", msg),
+            HookError::Paths(e) => write!(f, "{}", e),
             HookError::Other(msg) => write!(f, "{}", msg),
         }
     }
@@ -54,14 +58,15 @@ impl Hook {
 
     /// 读取 main.js 文件内容
     fn get_main_js_content(db: Option<&Database>) -> Result<(String, PathBuf), HookError> {
-        // 使用带数据库参数的AppPaths创建方法
-        let paths = AppPaths::new_with_db(db).map_err(|e| {
-            if e.contains("找不到Cursor的main.js文件") {
-                error!(target: "hook", "找不到Cursor的main.js文件: {}", e);
-                HookError::MainJsNotFound(e)
-            } else {
+        // 使用带数据库参数的AppPaths创建方法，找不到main.js时让用户选择路径
+        let paths = AppPaths::new_with_db(db).map_err(|e| match e {
+            PathError::MainJsNotFound(msg) => {
+                error!(target: "hook", "找不到Cursor的main.js文件: {}", msg);
+                HookError::MainJsNotFound(msg)
+            }
+            e => {
                 error!(target: "hook", "创建应用路径失败: {}", e);
-                HookError::Other(format!("创建应用路径失败: {}", e))
+                HookError::Paths(e)
             }
         })?;
 
@@ -87,15 +92,16 @@ impl Hook {
     pub async fn update_main_js_content(
         client: Option<State<'_, ApiClient>>,
         db: Option<State<'_, Database>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), AppError> {
         // 获取文件内容和路径
         let (content, file_path) = match Self::get_main_js_content(db.as_ref().map(|d| d.inner())) {
             Ok(result) => result,
             Err(e) => match e {
                 HookError::MainJsNotFound(msg) => {
                     error!(target: "hook", "找不到main.js: {}", msg);
-                    return Err(format!("MAIN_JS_NOT_FOUND:{}", msg));
+                    return Err(AppError::main_js_not_found(msg));
                 }
+                HookError::Paths(e) => return Err(e.into()),
                 HookError::Other(msg) => {
                     error!(target: "hook", "读取main.js失败: {}", msg);
                     if let Some(ref client) = client {
@@ -108,7 +114,7 @@ impl Hook {
                         )
                         .await;
                     }
-                    return Err(msg);
+                    return Err(AppError::filesystem(msg));
                 }
             },
         };
@@ -129,7 +135,7 @@ impl Hook {
                     )
                     .await;
                 }
-                return Err(err_msg);
+                return Err(AppError::filesystem(err_msg));
             }
         }
 
@@ -140,7 +146,7 @@ impl Hook {
         if machine_id_matches == 0 || mac_machine_id_matches == 0 {
             let err_msg = "无法找到匹配的 machineId 或 macMachineId 函数".to_string();
             error!(target: "hook", "{}", err_msg);
-            return Err(err_msg);
+            return Err(AppError::internal(err_msg));
         }
 
         // 替换 machineId
@@ -177,7 +183,7 @@ impl Hook {
                 )
                 .await;
             }
-            return Err(err_msg);
+            return Err(AppError::filesystem(err_msg));
         }
 
         // 保存成功找到的路径到数据库
@@ -195,15 +201,16 @@ impl Hook {
     pub async fn restore_from_backup(
         client: Option<State<'_, ApiClient>>,
         db: Option<State<'_, Database>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), AppError> {
         // 获取文件路径
         let (_, file_path) = match Self::get_main_js_content(db.as_ref().map(|d| d.inner())) {
             Ok(result) => result,
             Err(e) => match e {
                 HookError::MainJsNotFound(msg) => {
                     error!(target: "hook", "找不到main.js: {}", msg);
-                    return Err(format!("MAIN_JS_NOT_FOUND:{}", msg));
+                    return Err(AppError::main_js_not_found(msg));
                 }
+                HookError::Paths(e) => return Err(e.into()),
                 HookError::Other(msg) => {
                     error!(target: "hook", "获取main.js路径失败: {}", msg);
                     if let Some(ref client) = client {
@@ -216,7 +223,7 @@ impl Hook {
                         )
                         .await;
                     }
-                    return Err(msg);
+                    return Err(AppError::filesystem(msg));
                 }
             },
         };
@@ -236,7 +243,7 @@ impl Hook {
                 )
                 .await;
             }
-            return Err(err_msg);
+            return Err(AppError::filesystem(err_msg));
         }
 
         let backup_content = match fs::read_to_string(&backup_path) {
@@ -254,7 +261,7 @@ impl Hook {
                     )
                     .await;
                 }
-                return Err(err_msg);
+                return Err(AppError::filesystem(err_msg));
             }
         };
 
@@ -271,7 +278,7 @@ impl Hook {
                 )
                 .await;
             }
-            return Err(err_msg);
+            return Err(AppError::filesystem(err_msg));
         }
 
         if let Err(e) = fs::remove_file(backup_path) {
@@ -0,0 +1,182 @@
+//! 共享的 HTTP 客户端工厂，所有对外请求统一设置证书校验和代理
+
+use super::tls;
+use crate::config::{self, ApiConfig};
+use lazy_static::lazy_static;
+use reqwest::{Client, Proxy};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// 代理模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    // 直连，忽略系统代理
+    None,
+    // 使用系统代理和 HTTP_PROXY/HTTPS_PROXY/ALL_PROXY 环境变量
+    System,
+    // 手动指定代理服务器
+    Manual,
+}
+
+/// 手动代理的协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyProtocol {
+    Http,
+    // 域名通过代理服务器解析
+    Socks5,
+}
+
+/// 代理设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    #[serde(default = "default_protocol")]
+    pub protocol: ProxyProtocol,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default)]
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+fn default_protocol() -> ProxyProtocol {
+    ProxyProtocol::Http
+}
+
+impl Default for ProxySettings {
+    // 默认与之前的行为一致，使用系统代理
+    fn default() -> Self {
+        Self {
+            mode: ProxyMode::System,
+            protocol: default_protocol(),
+            host: String::new(),
+            port: 0,
+            username: None,
+            password: None,
+        }
+    }
+}
+
+impl ProxySettings {
+    /// 校验手动代理的地址和认证信息
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mode != ProxyMode::Manual {
+            return Ok(());
+        }
+        let host = self.host.trim();
+        if host.is_empty() || host.contains(['/', '@', ' ']) {
+            return Err(format!("代理服务器地址无效: {}", self.host));
+        }
+        if self.port == 0 {
+            return Err("代理服务器端口无效".to_string());
+        }
+        if self.password.is_some() && self.username().is_none() {
+            return Err("设置了代理密码但缺少用户名".to_string());
+        }
+        Proxy::all(self.url()).map_err(|e| format!("代理地址无效: {}", e))?;
+        Ok(())
+    }
+
+    fn username(&self) -> Option<&str> {
+        self.username.as_deref().filter(|u| !u.is_empty())
+    }
+
+    /// 代理地址，不包含认证信息，可用于日志
+    pub fn url(&self) -> String {
+        let scheme = match self.protocol {
+            ProxyProtocol::Http => "http",
+            ProxyProtocol::Socks5 => "socks5h",
+        };
+        format!("{}://{}:{}", scheme, self.host.trim(), self.port)
+    }
+
+    // 手动代理转换为 reqwest 的代理配置
+    fn to_proxy(&self) -> Result<Proxy, String> {
+        self.validate()?;
+        let proxy = Proxy::all(self.url()).map_err(|e| format!("代理地址无效: {}", e))?;
+        Ok(match self.username() {
+            Some(username) => {
+                proxy.basic_auth(username, self.password.as_deref().unwrap_or_default())
+            }
+            None => proxy,
+        })
+    }
+}
+
+lazy_static! {
+    static ref PROXY_CHANGES: watch::Sender<ProxySettings> =
+        watch::channel(ProxySettings::default()).0;
+}
+
+/// 当前生效的代理设置
+pub fn current_proxy() -> ProxySettings {
+    PROXY_CHANGES.borrow().clone()
+}
+
+/// 更新代理设置，之后创建的客户端都会使用新设置
+pub fn set_proxy(proxy: ProxySettings) {
+    PROXY_CHANGES.send_replace(proxy);
+}
+
+/// 订阅代理设置变更
+pub fn subscribe_proxy() -> watch::Receiver<ProxySettings> {
+    PROXY_CHANGES.subscribe()
+}
+
+/// 按指定的配置和代理创建客户端
+pub fn build_client_with(
+    timeout: Duration,
+    api: &ApiConfig,
+    proxy: &ProxySettings,
+) -> Result<Client, String> {
+    let builder = tls::configure(Client::builder().timeout(timeout), api)?;
+    let builder = match proxy.mode {
+        ProxyMode::None => builder.no_proxy(),
+        ProxyMode::System => builder,
+        ProxyMode::Manual => builder.proxy(proxy.to_proxy()?),
+    };
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// 按当前配置和代理设置创建客户端
+pub fn build_client(timeout: Duration) -> Result<Client, String> {
+    build_client_with(timeout, &config::get_api_config(), &current_proxy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_settings() {
+        let legacy: ProxySettings = serde_json::from_str(r#"{"mode":"none"}"#).unwrap();
+        assert_eq!(legacy.protocol, ProxyProtocol::Http);
+        assert!(legacy.validate().is_ok());
+
+        let mut manual: ProxySettings = serde_json::from_str(
+            r#"{"mode":"manual","protocol":"socks5","host":" 127.0.0.1 ","port":1080,
+                "username":"alice","password":"p@ss:word"}"#,
+        )
+        .unwrap();
+        assert_eq!(manual.url(), "socks5h://127.0.0.1:1080");
+        assert!(manual.to_proxy().is_ok());
+
+        manual.username = None;
+        assert!(manual.validate().is_err());
+        manual.password = None;
+        manual.port = 0;
+        assert!(manual.validate().is_err());
+        manual.port = 8080;
+        manual.host = "user@proxy.corp.local".to_string();
+        assert!(manual.validate().is_err());
+        // reqwest 无法解析的地址
+        manual.host = "[::1".to_string();
+        assert!(manual.validate().is_err());
+    }
+}
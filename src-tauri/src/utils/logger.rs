@@ -1,15 +1,27 @@
+use crate::config;
 use chrono::Local;
+use lazy_static::lazy_static;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tauri::Manager;
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
+use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{
     fmt::{self, time::UtcTime},
     prelude::*,
-    EnvFilter,
+    reload, EnvFilter, Registry,
 };
 
+lazy_static! {
+    // 日志过滤器的动态更新句柄
+    static ref FILTER_HANDLE: Mutex<Option<reload::Handle<EnvFilter, Registry>>> =
+        Mutex::new(None);
+    // 日志文件后台写入线程的句柄，释放时写入剩余日志
+    static ref FILE_GUARD: Mutex<Option<WorkerGuard>> = Mutex::new(None);
+}
+
 /// 日志系统配置选项
 pub struct LogConfig {
     /// 日志文件夹根目录
@@ -48,13 +60,17 @@ pub fn init_logger(config: LogConfig) -> Result<(), String> {
     // 日志文件名: 年-月-日.log
     let file_name = format!("{}-{}-{}.log", year, month, day);
 
-    // 创建日志文件写入器
-    let file_appender = RollingFileAppender::new(Rotation::NEVER, log_dir, file_name);
+    // 创建日志文件写入器，在后台线程写入文件
+    let (file_appender, guard) =
+        tracing_appender::non_blocking(RollingFileAppender::new(Rotation::NEVER, log_dir, file_name));
+    *FILE_GUARD.lock().unwrap() = Some(guard);
 
     // 设置日志过滤级别
     let log_level = config.log_level.clone();
     let env_filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&log_level));
+    let (env_filter, filter_handle) = reload::Layer::new(env_filter);
+    *FILTER_HANDLE.lock().unwrap() = Some(filter_handle);
 
     // 修改日志格式
     if config.json_format {
@@ -113,6 +129,39 @@ pub fn init_logger(config: LogConfig) -> Result<(), String> {
     Ok(())
 }
 
+/// 将缓冲的日志写入文件，之后的日志不再写入文件，退出应用前调用
+pub fn flush() {
+    drop(FILE_GUARD.lock().unwrap().take());
+}
+
+/// 运行时更新日志过滤规则
+pub fn set_log_level(level: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(level).map_err(|e| format!("日志级别无效: {}", e))?;
+    match FILTER_HANDLE.lock().unwrap().as_ref() {
+        Some(handle) => handle
+            .reload(filter)
+            .map_err(|e| format!("更新日志级别失败: {}", e)),
+        None => Err("日志系统未初始化".to_string()),
+    }
+}
+
+/// 跟随配置变更更新日志级别
+pub async fn watch_log_level() {
+    let mut changes = config::subscribe();
+    let mut current = changes.borrow_and_update().log.level.clone();
+    while changes.changed().await.is_ok() {
+        let level = changes.borrow_and_update().log.level.clone();
+        if level == current {
+            continue;
+        }
+        match set_log_level(&level) {
+            Ok(()) => info!("日志级别已更新: {}", level),
+            Err(e) => error!("{}", e),
+        }
+        current = level;
+    }
+}
+
 /// 获取应用数据目录下的日志目录
 pub fn get_app_log_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
     let app_data_dir = app_handle
@@ -2,12 +2,15 @@ pub mod cursor_version;
 pub mod db;
 pub mod error_reporter;
 pub mod hook;
+pub mod http;
 pub mod id_generator;
 pub mod logger;
 pub mod paths;
 pub mod privileges;
 pub mod process;
 pub mod retry;
+pub mod signature;
+pub mod tls;
 
 pub use cursor_version::CursorVersion;
 pub use db::update_sqlite_db;
@@ -1,11 +1,36 @@
 use crate::config;
 use crate::database::Database;
+use crate::settings::{self, SettingKey};
+use std::fmt;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 
-// 用于存储Cursor路径的数据库键
-const CURSOR_MAIN_JS_PATH_KEY: &str = "system.cursor.path.mainJs";
+/// 无法确定 Cursor 相关路径的原因
+#[derive(Debug)]
+pub enum PathError {
+    // 缺少环境变量或不支持当前系统
+    Environment(String),
+    // 找不到 main.js，需要用户手动选择路径
+    MainJsNotFound(String),
+    // 创建目录失败
+    Io(std::io::Error),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::Environment(msg) | PathError::MainJsNotFound(msg) => write!(f, "{}", msg),
+            PathError::Io(e) => write!(f, "创建目录失败: {}", e),
+        }
+    }
+}
+
+// 读取定位目录用的环境变量
+fn env_var(name: &str) -> Result<String, PathError> {
+    std::env::var(name)
+        .map_err(|e| PathError::Environment(format!("获取 {} 路径失败: {}", name, e)))
+}
 
 pub struct AppPaths {
     pub storage: PathBuf,
@@ -17,30 +42,32 @@ pub struct AppPaths {
 }
 
 impl AppPaths {
-    pub fn new() -> Result<Self, String> {
+    pub fn new() -> Result<Self, PathError> {
         Self::new_with_db(None)
     }
 
     // 新增：使用数据库查找保存的路径或默认路径
-    pub fn new_with_db(db: Option<&Database>) -> Result<Self, String> {
+    pub fn new_with_db(db: Option<&Database>) -> Result<Self, PathError> {
         let base_dir = if cfg!(target_os = "windows") {
             // Windows: %APPDATA%\Cursor\User\globalStorage
-            let app_data =
-                std::env::var("APPDATA").map_err(|e| format!("获取 APPDATA 路径失败: {}", e))?;
+            let app_data = env_var("APPDATA")?;
             PathBuf::from(app_data).join("Cursor")
         } else if cfg!(target_os = "macos") {
             // macOS: ~/Library/Application Support/Cursor/User/globalStorage
-            let home = std::env::var("HOME").map_err(|e| format!("获取 HOME 路径失败: {}", e))?;
+            let home = env_var("HOME")?;
             PathBuf::from(home)
                 .join("Library")
                 .join("Application Support")
                 .join("Cursor")
         } else if cfg!(target_os = "linux") {
             // Linux: ~/.config/Cursor/User/globalStorage
-            let home = std::env::var("HOME").map_err(|e| format!("获取 HOME 路径失败: {}", e))?;
+            let home = env_var("HOME")?;
             PathBuf::from(home).join(".config").join("Cursor")
         } else {
-            return Err(format!("不支持的操作系统: {}", std::env::consts::OS));
+            return Err(PathError::Environment(format!(
+                "不支持的操作系统: {}",
+                std::env::consts::OS
+            )));
         };
 
         let global_storage = base_dir.join("User").join("globalStorage");
@@ -48,8 +75,7 @@ impl AppPaths {
         // 获取 Cursor 可执行文件路径 - 添加环境变量查找逻辑
         let cursor_exe = if cfg!(target_os = "windows") {
             let default_path = {
-                let local_app_data = std::env::var("LOCALAPPDATA")
-                    .map_err(|e| format!("获取 LOCALAPPDATA 路径失败: {}", e))?;
+                let local_app_data = env_var("LOCALAPPDATA")?;
                 let default_path_str = config::CONFIG
                     .read()
                     .unwrap()
@@ -92,8 +118,7 @@ impl AppPaths {
 
         // 获取 cursor-updater 路径
         let cursor_updater = if cfg!(target_os = "windows") {
-            let local_app_data = std::env::var("LOCALAPPDATA")
-                .map_err(|e| format!("获取 LOCALAPPDATA 路径失败: {}", e))?;
+            let local_app_data = env_var("LOCALAPPDATA")?;
             let updater_path_str = config::CONFIG
                 .read()
                 .unwrap()
@@ -103,7 +128,7 @@ impl AppPaths {
                 .clone();
             PathBuf::from(updater_path_str.replace("%LOCALAPPDATA%", &local_app_data))
         } else if cfg!(target_os = "macos") {
-            let home = std::env::var("HOME").map_err(|e| format!("获取 HOME 路径失败: {}", e))?;
+            let home = env_var("HOME")?;
             let updater_path_str = config::CONFIG
                 .read()
                 .unwrap()
@@ -113,7 +138,7 @@ impl AppPaths {
                 .clone();
             PathBuf::from(updater_path_str.replace("~", &home))
         } else {
-            let home = std::env::var("HOME").map_err(|e| format!("获取 HOME 路径失败: {}", e))?;
+            let home = env_var("HOME")?;
             let updater_path_str = config::CONFIG
                 .read()
                 .unwrap()
@@ -131,13 +156,13 @@ impl AppPaths {
                 if saved_path.exists() {
                     saved_path
                 } else {
-                    Self::find_main_js_path()?
+                    Self::find_main_js_path().map_err(PathError::MainJsNotFound)?
                 }
             } else {
-                Self::find_main_js_path()?
+                Self::find_main_js_path().map_err(PathError::MainJsNotFound)?
             }
         } else {
-            Self::find_main_js_path()?
+            Self::find_main_js_path().map_err(PathError::MainJsNotFound)?
         };
 
         let paths = Self {
@@ -151,7 +176,7 @@ impl AppPaths {
 
         // 确保目录存在
         if let Some(parent) = paths.storage.parent() {
-            fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+            fs::create_dir_all(parent).map_err(PathError::Io)?;
         }
 
         Ok(paths)
@@ -437,8 +462,7 @@ impl AppPaths {
 
     // 新增：从数据库获取保存的main.js路径
     pub fn get_saved_path_from_db(db: &Database) -> Result<Option<PathBuf>, String> {
-        let cursor_main_js_path_key = config::get_db_key("cursor_main_js_path");
-        match db.get_item(&cursor_main_js_path_key) {
+        match db.blocking().get_item(&SettingKey::CursorMainJsPath.db_key()) {
             Ok(Some(path)) => Ok(Some(PathBuf::from(path))),
             Ok(None) => Ok(None),
             Err(e) => Err(format!("获取保存的main.js路径失败: {}", e)),
@@ -448,7 +472,8 @@ impl AppPaths {
     // 新增：保存main.js路径到数据库
     pub fn save_path_to_db(db: &Database, path: &Path) -> Result<(), String> {
         let path_str = path.to_string_lossy().to_string();
-        db.set_item(CURSOR_MAIN_JS_PATH_KEY, &path_str)
+        db.blocking()
+            .wait(settings::set(db, SettingKey::CursorMainJsPath, &path_str))
             .map_err(|e| format!("保存路径到数据库失败: {}", e))
     }
 
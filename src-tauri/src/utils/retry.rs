@@ -1,51 +1,198 @@
+use rand::Rng;
 use std::future::Future;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::error;
 
+/// 重试策略：指数退避 + 随机抖动，并限制总耗时
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    // 最多尝试次数（包含第一次）
+    pub max_attempts: u32,
+    // 第一次重试前的等待时间
+    pub initial_delay: Duration,
+    // 单次等待的上限
+    pub max_delay: Duration,
+    // 每次重试等待时间的增长倍数
+    pub multiplier: f64,
+    // 抖动比例(0-1)，实际等待时间在 [delay * (1 - jitter), delay] 之间
+    pub jitter: f64,
+    // 从第一次尝试开始允许的最长总耗时，超过后不再重试
+    pub max_elapsed: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(300),
+            max_delay: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_elapsed: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 指定尝试次数和初始等待时间，其余使用默认值
+    pub fn new(max_attempts: u32, initial_delay: Duration) -> Self {
+        Self {
+            max_attempts,
+            initial_delay,
+            ..Self::default()
+        }
+    }
+
+    /// 只尝试一次，不重试
+    pub fn none() -> Self {
+        Self::new(1, Duration::ZERO)
+    }
+
+    /// 第 attempt 次失败后的退避时间（attempt 从 1 开始）
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let delay = self
+            .initial_delay
+            .mul_f64(self.multiplier.max(1.0).powi(exponent))
+            .min(self.max_delay);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - jitter * rand::thread_rng().gen::<f64>())
+    }
+
+    /// 第 attempt 次失败后下一次重试前的等待时间，不应再重试时返回 None
+    ///
+    /// 服务端给出 `Retry-After` 时优先使用，但仍受最长总耗时限制
+    pub fn next_delay(
+        &self,
+        attempt: u32,
+        elapsed: Duration,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let delay = retry_after.unwrap_or_else(|| self.backoff(attempt));
+        match self.max_elapsed {
+            Some(max_elapsed) if elapsed + delay > max_elapsed => None,
+            _ => Some(delay),
+        }
+    }
+}
+
 /// 通用异步重试函数
-/// 
+///
 /// # 参数
 /// * `f` - 需要重试的异步函数
-/// * `retries` - 最大重试次数
-/// * `delay` - 每次重试之间的延迟时间
+/// * `policy` - 重试策略
 /// * `operation_name` - 操作名称，用于日志记录
 pub async fn retry<T, E, F, Fut>(
     mut f: F,
-    retries: u32,
-    delay: Duration,
+    policy: &RetryPolicy,
     operation_name: &str,
 ) -> Result<T, E>
 where
     F: FnMut() -> Fut,
     Fut: Future<Output = Result<T, E>>,
-    E: std::fmt::Debug + Clone,
+    E: std::fmt::Debug,
 {
-    // 如果重试次数为0，直接执行一次并返回结果
-    if retries == 0 {
-        return f().await;
-    }
-    
-    let mut last_err = None;
-    
-    for attempt in 1..=retries {
-        match f().await {
+    let start = Instant::now();
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        let err = match f().await {
             Ok(result) => return Ok(result),
-            Err(e) => {
-                let err_clone = e.clone();
-                last_err = Some(e);
+            Err(e) => e,
+        };
+
+        match policy.next_delay(attempt, start.elapsed(), None) {
+            Some(delay) => {
+                error!(
+                    target: "retry",
+                    "{}失败，{:?}后重试 ({}/{}): {:?}",
+                    operation_name, delay, attempt, policy.max_attempts, err
+                );
+                tokio::time::sleep(delay).await;
+            }
+            None => {
                 error!(
                     target: "retry",
-                    "{}失败，尝试重试 ({}/{}): {:?}",
-                    operation_name, attempt, retries, err_clone
+                    "{}失败，不再重试 ({}/{}): {:?}",
+                    operation_name, attempt, policy.max_attempts, err
                 );
-                
-                // 如果不是最后一次尝试，则等待
-                if attempt < retries {
-                    tokio::time::sleep(delay).await;
-                }
+                return Err(err);
             }
         }
     }
-    
-    Err(last_err.unwrap())
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            multiplier: 2.0,
+            jitter: 0.0,
+            max_elapsed: None,
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+
+        let jittered = RetryPolicy { jitter: 0.5, ..policy };
+        for _ in 0..100 {
+            let delay = jittered.backoff(2);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_next_delay_limits() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..RetryPolicy::new(3, Duration::from_millis(100))
+        };
+        assert_eq!(
+            policy.next_delay(1, Duration::ZERO, None),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(policy.next_delay(3, Duration::ZERO, None), None);
+        assert_eq!(RetryPolicy::none().next_delay(1, Duration::ZERO, None), None);
+
+        // Retry-After 优先，但不能超过总耗时限制
+        assert_eq!(
+            policy.next_delay(1, Duration::ZERO, Some(Duration::from_secs(2))),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            policy.next_delay(1, Duration::from_secs(29), Some(Duration::from_secs(2))),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_stops_after_max_attempts() {
+        let mut calls = 0;
+        let result: Result<(), &str> = retry(
+            || {
+                calls += 1;
+                async { Err("失败") }
+            },
+            &RetryPolicy::new(3, Duration::from_millis(1)),
+            "测试",
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+    }
+}
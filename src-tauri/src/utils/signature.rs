@@ -0,0 +1,174 @@
+//! 远程配置签名：ed25519 分离签名，密钥和签名均以 base64 编码
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fmt;
+
+/// 内置的远程配置签名公钥，构建时从 CURSOR_POOL_CONFIG_PUBLIC_KEY 环境变量读取
+///
+/// 密钥对由 `sign_config keygen` 生成：公钥保存在仓库的 CONFIG_SIGNING_PUBLIC_KEY secret 中，
+/// 发布流程构建时传入；私钥只用于签名配置文件，不随程序分发。
+/// 本地构建没有设置该变量时不信任任何远程配置
+pub const REMOTE_CONFIG_PUBLIC_KEY: Option<&str> =
+    match option_env!("CURSOR_POOL_CONFIG_PUBLIC_KEY") {
+        Some(key) if !key.is_empty() => Some(key),
+        _ => None,
+    };
+
+/// 签名校验错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    // 构建时没有内置公钥
+    MissingKey,
+    // 密钥格式错误
+    InvalidKey(String),
+    // 签名格式错误
+    InvalidSignature(String),
+    // 签名与内容不匹配
+    Mismatch,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::MissingKey => write!(f, "未内置远程配置签名公钥"),
+            SignatureError::InvalidKey(e) => write!(f, "密钥格式错误: {}", e),
+            SignatureError::InvalidSignature(e) => write!(f, "签名格式错误: {}", e),
+            SignatureError::Mismatch => write!(f, "签名校验失败，内容可能被篡改"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+// 解码固定长度的 base64 数据
+fn decode<const N: usize>(value: &str) -> Result<[u8; N], String> {
+    let bytes = BASE64.decode(value.trim()).map_err(|e| e.to_string())?;
+    let len = bytes.len();
+    bytes
+        .try_into()
+        .map_err(|_| format!("长度应为 {} 字节，实际为 {} 字节", N, len))
+}
+
+/// 生成新的密钥对，返回 (私钥, 公钥)
+pub fn generate_keypair() -> (String, String) {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    let key = SigningKey::from_bytes(&secret);
+    (
+        BASE64.encode(secret),
+        BASE64.encode(key.verifying_key().to_bytes()),
+    )
+}
+
+/// 根据私钥计算公钥
+pub fn public_key(secret_key: &str) -> Result<String, SignatureError> {
+    let secret = decode::<32>(secret_key).map_err(SignatureError::InvalidKey)?;
+    Ok(BASE64.encode(SigningKey::from_bytes(&secret).verifying_key().to_bytes()))
+}
+
+/// 使用私钥对内容签名，发布配置文件时使用
+pub fn sign(secret_key: &str, payload: &[u8]) -> Result<String, SignatureError> {
+    let secret = decode::<32>(secret_key).map_err(SignatureError::InvalidKey)?;
+    let signature = SigningKey::from_bytes(&secret).sign(payload);
+    Ok(BASE64.encode(signature.to_bytes()))
+}
+
+/// 使用公钥校验内容的签名
+pub fn verify(public_key: &str, payload: &[u8], signature: &str) -> Result<(), SignatureError> {
+    let public = decode::<32>(public_key).map_err(SignatureError::InvalidKey)?;
+    let key = VerifyingKey::from_bytes(&public)
+        .map_err(|e| SignatureError::InvalidKey(e.to_string()))?;
+    let signature = decode::<64>(signature).map_err(SignatureError::InvalidSignature)?;
+
+    key.verify_strict(payload, &Signature::from_bytes(&signature))
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+/// 使用内置公钥校验远程配置
+pub fn verify_remote_config(payload: &[u8], signature: &str) -> Result<(), SignatureError> {
+    let public_key = REMOTE_CONFIG_PUBLIC_KEY.ok_or(SignatureError::MissingKey)?;
+    verify(public_key, payload, signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn b64(hex_value: &str) -> String {
+        BASE64.encode(hex::decode(hex_value).unwrap())
+    }
+
+    // RFC 8032 7.1 节的测试向量: (私钥, 公钥, 内容, 签名)
+    const RFC8032_VECTORS: [(&str, &str, &str, &str); 2] = [
+        (
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        ),
+        (
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        ),
+    ];
+
+    #[test]
+    fn test_rfc8032_vectors() {
+        for (secret, public, message, expected) in RFC8032_VECTORS {
+            let message = hex::decode(message).unwrap();
+            let signature = sign(&b64(secret), &message).unwrap();
+            assert_eq!(signature, b64(expected));
+            assert_eq!(verify(&b64(public), &message, &signature), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_reject_tampered_or_malformed() {
+        let (secret, public, _, _) = RFC8032_VECTORS[0];
+        let payload = br#"{"inbound":[{"name":"a","url":"https://a.example.com"}]}"#;
+        let signature = sign(&b64(secret), payload).unwrap();
+        assert_eq!(verify(&b64(public), payload, &signature), Ok(()));
+        // 签名文件末尾的换行不影响校验
+        assert_eq!(verify(&b64(public), payload, &format!("{}\n", signature)), Ok(()));
+
+        let tampered = br#"{"inbound":[{"name":"a","url":"https://evil.example.com"}]}"#;
+        assert_eq!(verify(&b64(public), tampered, &signature), Err(SignatureError::Mismatch));
+
+        let (_, other_public, _, _) = RFC8032_VECTORS[1];
+        assert_eq!(verify(&b64(other_public), payload, &signature), Err(SignatureError::Mismatch));
+
+        assert!(matches!(
+            verify(&b64(public), payload, "not base64!"),
+            Err(SignatureError::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            verify(&b64(public), payload, &BASE64.encode([0u8; 32])),
+            Err(SignatureError::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn test_generated_keypair() {
+        let (secret, public) = generate_keypair();
+        assert_eq!(public_key(&secret).unwrap(), public);
+        let signature = sign(&secret, b"{}").unwrap();
+        assert_eq!(verify(&public, b"{}", &signature), Ok(()));
+    }
+
+    #[test]
+    fn test_builtin_public_key_is_valid() {
+        match REMOTE_CONFIG_PUBLIC_KEY {
+            Some(key) => assert!(VerifyingKey::from_bytes(&decode::<32>(key).unwrap()).is_ok()),
+            None => assert_eq!(
+                verify_remote_config(b"{}", ""),
+                Err(SignatureError::MissingKey)
+            ),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! TLS 设置：额外的 CA 证书和后端线路的公钥固定(SPKI pinning)
+
+use crate::config::ApiConfig;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use reqwest::ClientBuilder;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, RootCertStore, ServerName};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::{error, warn};
+
+/// 公钥固定规则，格式为 `域名:sha256/<base64>`，域名可以用 `*.` 匹配所有子域名
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinRule {
+    host: String,
+    hash: [u8; 32],
+}
+
+impl PinRule {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (host, pin) = value
+            .split_once(':')
+            .ok_or_else(|| format!("公钥固定规则格式应为 域名:sha256/<base64>: {}", value))?;
+        let encoded = pin
+            .trim()
+            .strip_prefix("sha256/")
+            .ok_or_else(|| format!("公钥固定规则只支持 sha256: {}", value))?;
+        let hash = BASE64
+            .decode(encoded)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| format!("公钥固定规则的摘要无效: {}", value))?;
+
+        let host = host.trim().to_ascii_lowercase();
+        if host.is_empty() {
+            return Err(format!("公钥固定规则缺少域名: {}", value));
+        }
+        Ok(Self { host, hash })
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        match self.host.strip_prefix("*.") {
+            Some(domain) => host.ends_with(&format!(".{}", domain)),
+            None => host == self.host,
+        }
+    }
+}
+
+// DER 元素: (标签, 整个元素, 内容, 剩余部分)
+type DerElement<'a> = (u8, &'a [u8], &'a [u8], &'a [u8]);
+
+// 读取一个 DER 元素
+fn read_der(input: &[u8]) -> Option<DerElement<'_>> {
+    let tag = *input.first()?;
+    let first = *input.get(1)?;
+    let (len, header) = if first & 0x80 == 0 {
+        (first as usize, 2)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let bytes = input.get(2..2 + count)?;
+        (bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize), 2 + count)
+    };
+    let element = input.get(..header.checked_add(len)?)?;
+    Some((tag, element, &element[header..], &input[element.len()..]))
+}
+
+/// 从 DER 格式的证书中取出 SubjectPublicKeyInfo
+fn spki(cert: &[u8]) -> Option<&[u8]> {
+    let (_, _, certificate, _) = read_der(cert)?;
+    let (_, _, mut tbs, _) = read_der(certificate)?;
+    // 跳过可选的版本号 [0]
+    if tbs.first() == Some(&0xa0) {
+        tbs = read_der(tbs)?.3;
+    }
+    // 依次跳过 serialNumber, signature, issuer, validity, subject
+    for _ in 0..5 {
+        tbs = read_der(tbs)?.3;
+    }
+    let (tag, element, _, _) = read_der(tbs)?;
+    if tag == 0x30 {
+        Some(element)
+    } else {
+        None
+    }
+}
+
+/// 证书公钥的 sha256 摘要
+pub fn spki_hash(cert: &[u8]) -> Option<[u8; 32]> {
+    spki(cert).map(|spki| Sha256::digest(spki).into())
+}
+
+/// 先按正常流程校验证书，再检查证书链中是否有公钥与固定规则一致
+struct PinnedVerifier {
+    inner: WebPkiVerifier,
+    pins: Vec<PinRule>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+
+        let host = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_ascii_lowercase(),
+            _ => return Ok(verified),
+        };
+        let pins: Vec<&PinRule> = self.pins.iter().filter(|pin| pin.matches_host(&host)).collect();
+        if pins.is_empty() {
+            return Ok(verified);
+        }
+
+        let pinned = std::iter::once(end_entity)
+            .chain(intermediates)
+            .filter_map(|cert| spki_hash(&cert.0))
+            .any(|hash| pins.iter().any(|pin| pin.hash == hash));
+        if pinned {
+            Ok(verified)
+        } else {
+            error!(target: "tls", "证书公钥与固定规则不一致 - 域名: {}", host);
+            Err(rustls::Error::General(format!("{} 的证书公钥与固定规则不一致", host)))
+        }
+    }
+}
+
+// 读取 PEM 格式的 CA 证书文件
+fn load_ca_bundle(path: &str) -> Result<Vec<Vec<u8>>, String> {
+    let file = File::open(path).map_err(|e| format!("无法打开CA证书文件 {}: {}", path, e))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .map_err(|e| format!("解析CA证书文件失败 {}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("CA证书文件中没有证书: {}", path));
+    }
+    Ok(certs)
+}
+
+// 系统根证书加上额外的 CA 证书
+fn root_store(extra: &[Vec<u8>]) -> RootCertStore {
+    let mut roots = RootCertStore::empty();
+    match rustls_native_certs::load_native_certs() {
+        Ok(certs) => {
+            let certs: Vec<Vec<u8>> = certs.into_iter().map(|cert| cert.0).collect();
+            roots.add_parsable_certificates(&certs);
+        }
+        Err(e) => error!(target: "tls", "加载系统根证书失败: {}", e),
+    }
+    let (_, ignored) = roots.add_parsable_certificates(extra);
+    if ignored > 0 {
+        warn!(target: "tls", "忽略了 {} 个无法解析的CA证书", ignored);
+    }
+    roots
+}
+
+/// 按配置设置证书校验方式
+///
+/// 配置了公钥固定时使用 rustls 并在校验证书链后检查公钥，否则使用系统默认的 TLS 实现
+pub fn configure(builder: ClientBuilder, config: &ApiConfig) -> Result<ClientBuilder, String> {
+    if !config.verify_ssl {
+        warn!(target: "tls", "已关闭证书校验，请求可能被中间人截获");
+        return Ok(builder.danger_accept_invalid_certs(true));
+    }
+
+    let extra = if config.ca_bundle.trim().is_empty() {
+        Vec::new()
+    } else {
+        load_ca_bundle(config.ca_bundle.trim())?
+    };
+
+    if config.spki_pins.is_empty() {
+        return extra.iter().try_fold(builder, |builder, der| {
+            reqwest::Certificate::from_der(der)
+                .map(|cert| builder.add_root_certificate(cert))
+                .map_err(|e| format!("CA证书无效: {}", e))
+        });
+    }
+
+    let pins = config
+        .spki_pins
+        .iter()
+        .map(|pin| PinRule::parse(pin))
+        .collect::<Result<Vec<_>, _>>()?;
+    let verifier = PinnedVerifier {
+        inner: WebPkiVerifier::new(root_store(&extra), None),
+        pins,
+    };
+    let tls = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(builder.use_preconfigured_tls(tls))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 自签名的测试证书，公钥摘要由 openssl 独立计算
+    const TEST_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBVzCB/aADAgECAhRXimyE4SZub4kTFG8kfs51H4TOxjAKBggqhkjOPQQDAjAb
+MRkwFwYDVQQDDBBwb29sLmV4YW1wbGUuY29tMB4XDTI0MDEwMTAwMDAwMFoXDTM0
+MDEwMTAwMDAwMFowGzEZMBcGA1UEAwwQcG9vbC5leGFtcGxlLmNvbTBZMBMGByqG
+SM49AgEGCCqGSM49AwEHA0IABJf2LcRkgSR5pLDRkRl0f6QI+ZXZLssSNKrs/Dw8
+X+YxP9KW4KK+5/3/hAYbh0vR5aTzdFJ4nLRYLqDaaJOpS9yjHzAdMBsGA1UdEQQU
+MBKCEHBvb2wuZXhhbXBsZS5jb20wCgYIKoZIzj0EAwIDSQAwRgIhAK4kilIEOsoV
++7/h9PuZ6RM4bQW+NM93WVP2pwLyU+1lAiEAljbs3tZh0FGqR7Km/QD+PC1t1Ucf
+IVJDyGvyYbsWPkI=
+-----END CERTIFICATE-----
+";
+    const TEST_PIN: &str = "sha256/Ead8R3GMxjIi0iYugx7Z+7kKx5ARjCYyxfEQnxaYsrE=";
+
+    #[test]
+    fn test_spki_hash() {
+        let der = rustls_pemfile::certs(&mut TEST_CERT.as_bytes()).unwrap().remove(0);
+        let rule = PinRule::parse(&format!("pool.example.com:{}", TEST_PIN)).unwrap();
+        assert_eq!(spki_hash(&der), Some(rule.hash));
+
+        assert_eq!(spki_hash(&der[..der.len() / 2]), None);
+        assert_eq!(spki_hash(&[]), None);
+    }
+
+    #[test]
+    fn test_pin_rule() {
+        let rule = PinRule::parse(&format!("*.Example.com:{}", TEST_PIN)).unwrap();
+        assert!(rule.matches_host("pool.example.com"));
+        assert!(rule.matches_host("a.b.example.com"));
+        assert!(!rule.matches_host("example.com"));
+        assert!(!rule.matches_host("evilexample.com"));
+
+        assert!(PinRule::parse(TEST_PIN).is_err());
+        assert!(PinRule::parse("pool.example.com:sha1/AAAA").is_err());
+        assert!(PinRule::parse("pool.example.com:sha256/AAAA").is_err());
+        assert!(PinRule::parse(&format!(":{}", TEST_PIN)).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 import { invoke } from '@tauri-apps/api/core'
 import type {
   ApiResponse,
+  AppError,
   LoginResponse,
   UserInfo,
   AccountPoolInfo,
@@ -11,6 +12,12 @@ import type {
   HistoryAccountRecord,
   Article,
   RegisterResponse,
+  VersionStatus,
+  ProxySettings,
+  ProxyTestResult,
+  TaskInfo,
+  TaskRun,
+  SecretsStatus,
 } from './types'
 import Logger from '../utils/logger'
 
@@ -29,6 +36,19 @@ function handleApiResponse<T>(response: ApiResponse<T>): T {
   throw new ApiError(response.msg || '链接服务器失败，请稍后再试')
 }
 
+// 判断是否为后端命令返回的错误
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error
+}
+
+// 获取错误信息
+function errorMessage(error: unknown, fallback: string): string {
+  if (error instanceof Error || isAppError(error)) {
+    return error.message
+  }
+  return fallback
+}
+
 // API 错误类
 export class ApiError extends Error {
   public statusCode?: number
@@ -47,7 +67,7 @@ export async function checkUser(email: string): Promise<ApiResponse<any>> {
     const response = await invoke<ApiResponse<any>>('check_user', { email })
     return response
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '检查用户失败')
+    throw new ApiError(errorMessage(error, '检查用户失败'))
   }
 }
 
@@ -59,7 +79,7 @@ export async function sendCode(email: string, type: string): Promise<void> {
     })
     handleApiResponse(response)
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '发送验证码失败')
+    throw new ApiError(errorMessage(error, '发送验证码失败'))
   }
 }
 
@@ -93,7 +113,7 @@ export async function register(
     }
     return handleApiResponse(response)
   } catch (error) {
-    const errorMsg = error instanceof Error ? error.message : '注册失败'
+    const errorMsg = errorMessage(error, '注册失败')
     if (errorMsg.includes('invalid type: map, expected unit')) {
       console.warn('注册API返回格式错误，但注册可能已成功，尝试继续流程')
       return {} as LoginResponse
@@ -115,7 +135,7 @@ export async function login(
     })
     return handleApiResponse(response)
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '登录失败')
+    throw new ApiError(errorMessage(error, '登录失败'))
   }
 }
 
@@ -131,7 +151,7 @@ export async function getUserInfo(): Promise<UserInfo> {
       throw error
     }
     // 否则包装成ApiError
-    throw new ApiError(error instanceof Error ? error.message : '链接服务器失败')
+    throw new ApiError(errorMessage(error, '链接服务器失败'))
   }
 }
 
@@ -143,7 +163,7 @@ export async function getAccount(account?: string, usageCount?: string): Promise
     })
     return handleApiResponse(response)
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '获取账户信息失败')
+    throw new ApiError(errorMessage(error, '获取账户信息失败'))
   }
 }
 
@@ -156,13 +176,13 @@ export async function getUsage(token: string): Promise<UsageInfo> {
     return handleApiResponse(response)
   } catch (error) {
     // 检查错误类型
-    const errorMsg = error instanceof Error ? error.message : String(error)
+    const code = isAppError(error) ? error.code : ''
 
-    if (errorMsg === 'cursor_db_error') {
+    if (code === 'CURSOR_DB_ERROR') {
       throw new ApiError('cursor_db_error')
-    } else if (errorMsg === 'cursor_network_error') {
+    } else if (code === 'CURSOR_NETWORK_ERROR') {
       throw new ApiError('cursor_network_error')
-    } else if (errorMsg === 'cursor_data_error') {
+    } else if (code === 'CURSOR_DATA_ERROR') {
       throw new ApiError('cursor_data_error')
     } else {
       throw new ApiError('cursor_unknown_error')
@@ -176,7 +196,155 @@ export async function getPublicInfo(): Promise<PublicInfo> {
     const response = await invoke<ApiResponse<PublicInfo>>('get_public_info')
     return handleApiResponse(response)
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '获取公共信息失败')
+    throw new ApiError(errorMessage(error, '获取公共信息失败'))
+  }
+}
+
+// 获取客户端版本状态
+export async function getVersionStatus(): Promise<VersionStatus> {
+  try {
+    const response = await invoke<ApiResponse<VersionStatus>>('get_version_status')
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '获取版本状态失败'))
+  }
+}
+
+// 网络代理相关 API
+export async function getProxySettings(): Promise<ProxySettings> {
+  try {
+    const response = await invoke<ApiResponse<ProxySettings>>('get_proxy_settings')
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '获取代理设置失败'))
+  }
+}
+
+export async function setProxySettings(proxy: ProxySettings): Promise<void> {
+  try {
+    const response = await invoke<ApiResponse<void>>('set_proxy_settings', { proxy })
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '保存代理设置失败'))
+  }
+}
+
+// 不传 proxy 时测试已保存的设置
+export async function testProxyConnection(proxy?: ProxySettings): Promise<ProxyTestResult> {
+  try {
+    const response = await invoke<ApiResponse<ProxyTestResult>>('test_proxy_connection', {
+      proxy: proxy ?? null,
+    })
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '代理连接测试失败'))
+  }
+}
+
+// 数据加密相关 API
+export async function getSecretsStatus(): Promise<SecretsStatus> {
+  try {
+    const response = await invoke<ApiResponse<SecretsStatus>>('get_secrets_status')
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '获取加密状态失败'))
+  }
+}
+
+export async function unlockSecrets(passphrase: string): Promise<void> {
+  try {
+    const response = await invoke<ApiResponse<void>>('unlock_secrets', { passphrase })
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '解锁加密数据失败'))
+  }
+}
+
+// 不传口令时改用随机密钥文件
+export async function rotateSecretsKey(passphrase?: string): Promise<void> {
+  try {
+    const response = await invoke<ApiResponse<void>>('rotate_secrets_key', {
+      passphrase: passphrase ?? null,
+    })
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '轮换数据加密密钥失败'))
+  }
+}
+
+// 定时任务相关 API
+export async function getScheduledTasks(): Promise<TaskInfo[]> {
+  try {
+    const response = await invoke<ApiResponse<TaskInfo[]>>('get_scheduled_tasks')
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '获取定时任务失败'))
+  }
+}
+
+export async function pauseScheduledTask(taskId: string): Promise<TaskInfo> {
+  try {
+    const response = await invoke<ApiResponse<TaskInfo>>('pause_scheduled_task', { taskId })
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '暂停任务失败'))
+  }
+}
+
+export async function resumeScheduledTask(taskId: string): Promise<TaskInfo> {
+  try {
+    const response = await invoke<ApiResponse<TaskInfo>>('resume_scheduled_task', { taskId })
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '恢复任务失败'))
+  }
+}
+
+export async function rescheduleScheduledTask(
+  taskId: string,
+  intervalSecs: number,
+): Promise<TaskInfo> {
+  try {
+    const response = await invoke<ApiResponse<TaskInfo>>('reschedule_scheduled_task', {
+      taskId,
+      intervalSecs,
+    })
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '修改任务间隔失败'))
+  }
+}
+
+export async function setTaskSchedule(
+  taskId: string,
+  expression: string | null,
+): Promise<TaskInfo> {
+  try {
+    const response = await invoke<ApiResponse<TaskInfo>>('set_task_schedule', {
+      taskId,
+      expression,
+    })
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '设置任务计划失败'))
+  }
+}
+
+export async function runScheduledTask(taskId: string): Promise<void> {
+  try {
+    const response = await invoke<ApiResponse<void>>('run_scheduled_task', { taskId })
+    handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '执行任务失败'))
+  }
+}
+
+export async function getTaskRuns(taskId?: string, limit?: number): Promise<TaskRun[]> {
+  try {
+    const response = await invoke<ApiResponse<TaskRun[]>>('get_task_runs', { taskId, limit })
+    return handleApiResponse(response)
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '获取任务运行记录失败'))
   }
 }
 
@@ -186,7 +354,7 @@ export async function activate(code: string): Promise<void> {
     const response = await invoke<ApiResponse<void>>('activate', { code })
     handleApiResponse(response)
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '激活失败')
+    throw new ApiError(errorMessage(error, '激活失败'))
   }
 }
 
@@ -198,7 +366,7 @@ export async function changePassword(oldPassword: string, newPassword: string):
     })
     handleApiResponse(response)
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '修改密码失败')
+    throw new ApiError(errorMessage(error, '修改密码失败'))
   }
 }
 
@@ -216,7 +384,7 @@ export async function resetMachineId(
     })
   } catch (error) {
     await Logger.error('重置机器码失败', { file: 'api/index.ts' })
-    throw new ApiError(error instanceof Error ? error.message : '重置机器码失败')
+    throw new ApiError(errorMessage(error, '重置机器码失败'))
   }
 }
 
@@ -239,8 +407,7 @@ export async function switchAccount(
     await Logger.error(`切换账户失败: ${email}, ${error}`, {
       file: 'api/index.ts',
     })
-    const errorMsg = error instanceof Error ? error.message : '切换账户失败'
-    if (errorMsg.includes('Cursor进程正在运行')) {
+    if (isAppError(error) && error.code === 'CURSOR_RUNNING') {
       throw new Error('请先关闭 Cursor 或选择强制终止进程')
     }
     throw error
@@ -252,7 +419,7 @@ export async function getMachineIds(): Promise<MachineInfo> {
     return await invoke<MachineInfo>('get_machine_ids')
   } catch (error) {
     await Logger.error('获取机器码失败', { file: 'api/index.ts' })
-    throw new ApiError(error instanceof Error ? error.message : '获取机器码失败')
+    throw new ApiError(errorMessage(error, '获取机器码失败'))
   }
 }
 
@@ -261,7 +428,7 @@ export async function checkCursorRunning(): Promise<boolean> {
     return await invoke<boolean>('check_cursor_running')
   } catch (error) {
     await Logger.error('检查Cursor状态失败', { file: 'api/index.ts' })
-    throw new ApiError(error instanceof Error ? error.message : '检查Cursor状态失败')
+    throw new ApiError(errorMessage(error, '检查Cursor状态失败'))
   }
 }
 
@@ -270,7 +437,7 @@ export async function checkAdminPrivileges(): Promise<boolean> {
   try {
     return await invoke<boolean>('check_admin_privileges')
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '检查管理员权限失败')
+    throw new ApiError(errorMessage(error, '检查管理员权限失败'))
   }
 }
 
@@ -290,10 +457,10 @@ export async function applyHook(forceKill: boolean = false): Promise<void> {
       forceKill,
     })
   } catch (error) {
-    const errorMsg = error instanceof Error ? error.message : String(error)
+    const errorMsg = errorMessage(error, String(error))
     await Logger.error(`应用hook失败: ${errorMsg}`, { file: 'api/index.ts' })
 
-    if (errorMsg.includes('Cursor进程正在运行')) {
+    if (isAppError(error) && error.code === 'CURSOR_RUNNING') {
       throw new Error('请先关闭 Cursor 或选择强制终止进程')
     }
 
@@ -316,10 +483,10 @@ export async function restoreHook(forceKill: boolean = false): Promise<void> {
       forceKill,
     })
   } catch (error) {
-    const errorMsg = error instanceof Error ? error.message : String(error)
+    const errorMsg = errorMessage(error, String(error))
     await Logger.error(`恢复hook失败: ${errorMsg}`, { file: 'api/index.ts' })
 
-    if (errorMsg.includes('Cursor进程正在运行')) {
+    if (isAppError(error) && error.code === 'CURSOR_RUNNING') {
       throw new Error('请先关闭 Cursor 或选择强制终止进程')
     }
 
@@ -336,7 +503,7 @@ export async function resetPassword(email: string, code: string, password: strin
     })
     handleApiResponse(response)
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '重置密码失败')
+    throw new ApiError(errorMessage(error, '重置密码失败'))
   }
 }
 
@@ -345,7 +512,7 @@ export async function checkIsWindows(): Promise<boolean> {
   try {
     return await invoke<boolean>('check_is_windows')
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '检查系统是否为Windows失败')
+    throw new ApiError(errorMessage(error, '检查系统是否为Windows失败'))
   }
 }
 
@@ -364,7 +531,7 @@ export async function logout(): Promise<void> {
     const response = await invoke<ApiResponse<void>>('logout')
     handleApiResponse(response)
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '登出失败')
+    throw new ApiError(errorMessage(error, '登出失败'))
   }
 }
 
@@ -386,7 +553,7 @@ export async function saveHistoryRecord(record: HistoryRecord): Promise<void> {
     await setUserData('user.history', JSON.stringify(records))
   } catch (error) {
     console.error('保存历史记录失败:', error)
-    throw new ApiError(error instanceof Error ? error.message : '保存历史记录失败')
+    throw new ApiError(errorMessage(error, '保存历史记录失败'))
   }
 }
 
@@ -406,7 +573,7 @@ export async function saveHistoryRecords(records: HistoryRecord[]): Promise<void
     await setUserData('user.history', JSON.stringify(existingRecords))
   } catch (error) {
     console.error('批量保存历史记录失败:', error)
-    throw new ApiError(error instanceof Error ? error.message : '保存历史记录失败')
+    throw new ApiError(errorMessage(error, '保存历史记录失败'))
   }
 }
 
@@ -429,7 +596,7 @@ export async function getHistoryRecords(): Promise<HistoryRecord[]> {
     }
   } catch (error) {
     console.error('获取历史记录失败:', error)
-    throw new ApiError(error instanceof Error ? error.message : '获取历史记录失败')
+    throw new ApiError(errorMessage(error, '获取历史记录失败'))
   }
 }
 
@@ -441,7 +608,7 @@ export async function clearHistoryRecords(): Promise<void> {
     await delUserData('user.history')
   } catch (error) {
     console.error('清除历史记录失败:', error)
-    throw new ApiError(error instanceof Error ? error.message : '清除历史记录失败')
+    throw new ApiError(errorMessage(error, '清除历史记录失败'))
   }
 }
 
@@ -462,7 +629,7 @@ export async function getHistoryAccounts(): Promise<HistoryAccountRecord[]> {
       return []
     }
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '获取历史账户失败')
+    throw new ApiError(errorMessage(error, '获取历史账户失败'))
   }
 }
 
@@ -481,7 +648,7 @@ export async function removeHistoryAccount(email: string): Promise<void> {
     // 保存回数据库
     await setUserData('user.history.accounts', JSON.stringify(accounts))
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '删除历史账户失败')
+    throw new ApiError(errorMessage(error, '删除历史账户失败'))
   }
 }
 
@@ -493,7 +660,7 @@ export async function clearHistoryAccounts(): Promise<void> {
     await delUserData('user.history.accounts')
   } catch (error) {
     console.error('清除历史账户失败:', error)
-    throw new ApiError(error instanceof Error ? error.message : '清除历史账户失败')
+    throw new ApiError(errorMessage(error, '清除历史账户失败'))
   }
 }
 
@@ -506,7 +673,7 @@ export async function saveUserApiToken(token: string): Promise<void> {
     await setUserData('user.info.token', token)
   } catch (error) {
     console.error('保存API Token失败:', error)
-    throw new ApiError(error instanceof Error ? error.message : '保存API Token失败')
+    throw new ApiError(errorMessage(error, '保存API Token失败'))
   }
 }
 
@@ -519,7 +686,7 @@ export async function getUserApiToken(): Promise<string | null> {
     return await getUserData('user.info.token')
   } catch (error) {
     console.error('获取API Token失败:', error)
-    throw new ApiError(error instanceof Error ? error.message : '获取API Token失败')
+    throw new ApiError(errorMessage(error, '获取API Token失败'))
   }
 }
 
@@ -531,7 +698,7 @@ export async function clearUserApiToken(): Promise<void> {
     await delUserData('user.info.token')
   } catch (error) {
     console.error('清除API Token失败:', error)
-    throw new ApiError(error instanceof Error ? error.message : '清除API Token失败')
+    throw new ApiError(errorMessage(error, '清除API Token失败'))
   }
 }
 
@@ -547,7 +714,7 @@ export async function setUserData(key: string, value: string): Promise<void> {
   try {
     await invoke<ApiResponse<any>>('set_user_data', { key, value })
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '设置用户数据失败')
+    throw new ApiError(errorMessage(error, '设置用户数据失败'))
   }
 }
 
@@ -566,7 +733,7 @@ export async function getUserData(key: string): Promise<string | null> {
     const result = handleApiResponse(response)
     return result.value
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '获取用户数据失败')
+    throw new ApiError(errorMessage(error, '获取用户数据失败'))
   }
 }
 
@@ -578,7 +745,7 @@ export async function delUserData(key: string): Promise<void> {
   try {
     await invoke<ApiResponse<any>>('del_user_data', { key })
   } catch (error) {
-    throw new ApiError(error instanceof Error ? error.message : '删除用户数据失败')
+    throw new ApiError(errorMessage(error, '删除用户数据失败'))
   }
 }
 
@@ -672,6 +839,17 @@ export async function markArticleRead(articleId: number): Promise<void> {
   }
 }
 
+/**
+ * 停止后台任务并关闭数据库后重启应用
+ */
+export async function restartApp(): Promise<void> {
+  try {
+    await invoke<ApiResponse<void>>('restart_app')
+  } catch (error) {
+    throw new ApiError(errorMessage(error, '重启应用失败'))
+  }
+}
+
 /**
  * 打开开发者工具
  */
@@ -6,6 +6,76 @@ export interface ApiResponse<T> {
   code?: string
 }
 
+// 后端命令返回的错误
+export interface AppError {
+  code: string
+  category: string
+  message: string
+  details?: string
+}
+
+// 客户端版本状态
+export interface VersionStatus {
+  current_version: string
+  min_version?: string
+  recommended_version?: string
+  // none: 无需更新, recommended: 建议更新, required: 必须更新
+  requirement: 'none' | 'recommended' | 'required'
+  features: Record<string, boolean>
+}
+
+// 网络代理设置
+export interface ProxySettings {
+  // none: 直连, system: 系统代理和环境变量, manual: 手动指定
+  mode: 'none' | 'system' | 'manual'
+  protocol: 'http' | 'socks5'
+  host: string
+  port: number
+  username?: string
+  password?: string
+}
+
+// 代理连接测试结果
+export interface ProxyTestResult {
+  url: string
+  proxy?: string
+  status: number
+  latency_ms: number
+}
+
+// 定时任务运行状态
+export interface TaskInfo {
+  id: string
+  // every 300s、daily 09:00、cron 表达式或 cycle
+  schedule: string
+  interval_secs: number
+  paused: boolean
+  running: boolean
+  consecutive_failures: number
+  last_run?: string
+  next_run?: string
+  last_error?: string
+}
+
+// 定时任务运行记录
+export interface TaskRun {
+  task_id: string
+  status: 'success' | 'failed' | 'timed_out' | 'panicked' | 'skipped'
+  started_at: string
+  duration_ms: number
+  error?: string
+}
+
+// 数据加密密钥状态
+export interface SecretsStatus {
+  // 是否有需要口令解锁的密钥
+  locked: boolean
+  // 当前密钥是否由口令派生
+  passphrase: boolean
+  // 是否有未完成的密钥轮换
+  rotation_pending: boolean
+}
+
 // 用户信息
 export interface UserInfo {
   totalCount: number
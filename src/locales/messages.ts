@@ -166,6 +166,35 @@ export const messages = {
         restoreHook: '恢复客户端',
       },
     },
+    secrets: {
+      title: '数据加密',
+      keyFileMode: '密钥文件',
+      passphraseMode: '口令保护',
+      locked: '未解锁',
+      rotationPending: '轮换未完成',
+      rotate: '轮换密钥',
+      setPassphrase: '设置口令',
+      changePassphrase: '修改口令',
+      removePassphrase: '移除口令',
+      passphrase: '口令',
+      newPassphrase: '新口令',
+      confirmPassphrase: '确认口令',
+      passphraseHint: '设置后每次启动需要输入口令才能使用已保存的令牌和代理设置，忘记口令将无法恢复这些数据',
+      passphraseRequired: '请输入口令',
+      passphraseMismatch: '两次输入的口令不一致',
+      passphraseSet: '口令已设置',
+      passphraseRemoved: '口令已移除',
+      rotateSuccess: '密钥已轮换',
+      rotateFailed: '轮换密钥失败',
+      unlockTitle: '解锁加密数据',
+      unlockMessage: '本地保存的令牌和代理设置已使用口令加密，请输入口令解锁',
+      unlock: '解锁',
+      later: '稍后',
+      unlockSuccess: '解锁成功',
+      unlockFailed: '解锁失败',
+      confirm: '确认',
+      cancel: '取消',
+    },
     about: {
       title: '关于',
       version: '版本',
@@ -425,6 +454,37 @@ export const messages = {
         restoreHook: 'Restaurar hook',
       },
     },
+    secrets: {
+      title: 'Data Encryption',
+      keyFileMode: 'Key File',
+      passphraseMode: 'Passphrase',
+      locked: 'Locked',
+      rotationPending: 'Rotation Pending',
+      rotate: 'Rotate Key',
+      setPassphrase: 'Set Passphrase',
+      changePassphrase: 'Change Passphrase',
+      removePassphrase: 'Remove Passphrase',
+      passphrase: 'Passphrase',
+      newPassphrase: 'New passphrase',
+      confirmPassphrase: 'Confirm passphrase',
+      passphraseHint:
+        'Once set, the passphrase is required at every start to use saved tokens and proxy settings. A forgotten passphrase cannot be recovered',
+      passphraseRequired: 'Please enter the passphrase',
+      passphraseMismatch: 'The passphrases do not match',
+      passphraseSet: 'Passphrase set',
+      passphraseRemoved: 'Passphrase removed',
+      rotateSuccess: 'Key rotated',
+      rotateFailed: 'Failed to rotate key',
+      unlockTitle: 'Unlock Encrypted Data',
+      unlockMessage:
+        'Saved tokens and proxy settings are protected by a passphrase. Enter it to unlock',
+      unlock: 'Unlock',
+      later: 'Later',
+      unlockSuccess: 'Unlocked',
+      unlockFailed: 'Failed to unlock',
+      confirm: 'Confirm',
+      cancel: 'Cancel',
+    },
     about: {
       title: 'About',
       version: 'Version',
@@ -679,6 +739,35 @@ export const messages = {
         restoreHook: 'フックを復元',
       },
     },
+    secrets: {
+      title: 'データ暗号化',
+      keyFileMode: '鍵ファイル',
+      passphraseMode: 'パスフレーズ',
+      locked: 'ロック中',
+      rotationPending: 'ローテーション未完了',
+      rotate: '鍵をローテーション',
+      setPassphrase: 'パスフレーズを設定',
+      changePassphrase: 'パスフレーズを変更',
+      removePassphrase: 'パスフレーズを削除',
+      passphrase: 'パスフレーズ',
+      newPassphrase: '新しいパスフレーズ',
+      confirmPassphrase: 'パスフレーズの確認',
+      passphraseHint: '設定すると起動のたびに保存済みのトークンとプロキシ設定を使うためにパスフレーズが必要です。忘れた場合は復元できません',
+      passphraseRequired: 'パスフレーズを入力してください',
+      passphraseMismatch: 'パスフレーズが一致しません',
+      passphraseSet: 'パスフレーズを設定しました',
+      passphraseRemoved: 'パスフレーズを削除しました',
+      rotateSuccess: '鍵をローテーションしました',
+      rotateFailed: '鍵のローテーションに失敗しました',
+      unlockTitle: '暗号化データのロック解除',
+      unlockMessage: '保存済みのトークンとプロキシ設定はパスフレーズで保護されています。入力してロックを解除してください',
+      unlock: 'ロック解除',
+      later: '後で',
+      unlockSuccess: 'ロックを解除しました',
+      unlockFailed: 'ロック解除に失敗しました',
+      confirm: '確認',
+      cancel: 'キャンセル',
+    },
     about: {
       title: 'バージョン情報',
       version: 'バージョン',
@@ -931,6 +1020,37 @@ export const messages = {
         restoreHook: 'Restaurar hook',
       },
     },
+    secrets: {
+      title: 'Chiffrement des données',
+      keyFileMode: 'Fichier de clé',
+      passphraseMode: 'Phrase secrète',
+      locked: 'Verrouillé',
+      rotationPending: 'Rotation en attente',
+      rotate: 'Renouveler la clé',
+      setPassphrase: 'Définir une phrase secrète',
+      changePassphrase: 'Changer la phrase secrète',
+      removePassphrase: 'Supprimer la phrase secrète',
+      passphrase: 'Phrase secrète',
+      newPassphrase: 'Nouvelle phrase secrète',
+      confirmPassphrase: 'Confirmer la phrase secrète',
+      passphraseHint:
+        'Une fois définie, la phrase secrète est demandée à chaque démarrage pour utiliser les jetons et le proxy enregistrés. Une phrase oubliée ne peut pas être récupérée',
+      passphraseRequired: 'Veuillez saisir la phrase secrète',
+      passphraseMismatch: 'Les phrases secrètes ne correspondent pas',
+      passphraseSet: 'Phrase secrète définie',
+      passphraseRemoved: 'Phrase secrète supprimée',
+      rotateSuccess: 'Clé renouvelée',
+      rotateFailed: 'Échec du renouvellement de la clé',
+      unlockTitle: 'Déverrouiller les données chiffrées',
+      unlockMessage:
+        'Les jetons et le proxy enregistrés sont protégés par une phrase secrète. Saisissez-la pour les déverrouiller',
+      unlock: 'Déverrouiller',
+      later: 'Plus tard',
+      unlockSuccess: 'Déverrouillé',
+      unlockFailed: 'Échec du déverrouillage',
+      confirm: 'Confirmer',
+      cancel: 'Annuler',
+    },
     about: {
       title: 'À propos',
       version: 'Version',
@@ -1179,6 +1299,37 @@ export const messages = {
         restoreHook: 'Restaurar hook',
       },
     },
+    secrets: {
+      title: 'Datenverschlüsselung',
+      keyFileMode: 'Schlüsseldatei',
+      passphraseMode: 'Passphrase',
+      locked: 'Gesperrt',
+      rotationPending: 'Rotation ausstehend',
+      rotate: 'Schlüssel rotieren',
+      setPassphrase: 'Passphrase festlegen',
+      changePassphrase: 'Passphrase ändern',
+      removePassphrase: 'Passphrase entfernen',
+      passphrase: 'Passphrase',
+      newPassphrase: 'Neue Passphrase',
+      confirmPassphrase: 'Passphrase bestätigen',
+      passphraseHint:
+        'Danach wird die Passphrase bei jedem Start benötigt, um gespeicherte Tokens und Proxy-Einstellungen zu verwenden. Eine vergessene Passphrase kann nicht wiederhergestellt werden',
+      passphraseRequired: 'Bitte Passphrase eingeben',
+      passphraseMismatch: 'Die Passphrasen stimmen nicht überein',
+      passphraseSet: 'Passphrase festgelegt',
+      passphraseRemoved: 'Passphrase entfernt',
+      rotateSuccess: 'Schlüssel rotiert',
+      rotateFailed: 'Schlüsselrotation fehlgeschlagen',
+      unlockTitle: 'Verschlüsselte Daten entsperren',
+      unlockMessage:
+        'Gespeicherte Tokens und Proxy-Einstellungen sind mit einer Passphrase geschützt. Zum Entsperren eingeben',
+      unlock: 'Entsperren',
+      later: 'Später',
+      unlockSuccess: 'Entsperrt',
+      unlockFailed: 'Entsperren fehlgeschlagen',
+      confirm: 'Bestätigen',
+      cancel: 'Abbrechen',
+    },
     about: {
       title: 'Über',
       version: 'Version',
@@ -1426,6 +1577,35 @@ export const messages = {
         restoreHook: '훅 복원',
       },
     },
+    secrets: {
+      title: '데이터 암호화',
+      keyFileMode: '키 파일',
+      passphraseMode: '암호문',
+      locked: '잠김',
+      rotationPending: '교체 미완료',
+      rotate: '키 교체',
+      setPassphrase: '암호문 설정',
+      changePassphrase: '암호문 변경',
+      removePassphrase: '암호문 제거',
+      passphrase: '암호문',
+      newPassphrase: '새 암호문',
+      confirmPassphrase: '암호문 확인',
+      passphraseHint: '설정하면 저장된 토큰과 프록시 설정을 사용하려면 시작할 때마다 암호문이 필요합니다. 잊어버린 암호문은 복구할 수 없습니다',
+      passphraseRequired: '암호문을 입력하세요',
+      passphraseMismatch: '암호문이 일치하지 않습니다',
+      passphraseSet: '암호문이 설정되었습니다',
+      passphraseRemoved: '암호문이 제거되었습니다',
+      rotateSuccess: '키가 교체되었습니다',
+      rotateFailed: '키 교체에 실패했습니다',
+      unlockTitle: '암호화된 데이터 잠금 해제',
+      unlockMessage: '저장된 토큰과 프록시 설정이 암호문으로 보호되어 있습니다. 입력하여 잠금을 해제하세요',
+      unlock: '잠금 해제',
+      later: '나중에',
+      unlockSuccess: '잠금이 해제되었습니다',
+      unlockFailed: '잠금 해제에 실패했습니다',
+      confirm: '확인',
+      cancel: '취소',
+    },
     about: {
       title: '정보',
       version: '버전',
@@ -1672,6 +1852,37 @@ export const messages = {
         restoreHook: 'Restaurar hook',
       },
     },
+    secrets: {
+      title: 'Шифрование данных',
+      keyFileMode: 'Файл ключа',
+      passphraseMode: 'Парольная фраза',
+      locked: 'Заблокировано',
+      rotationPending: 'Смена ключа не завершена',
+      rotate: 'Сменить ключ',
+      setPassphrase: 'Задать парольную фразу',
+      changePassphrase: 'Изменить парольную фразу',
+      removePassphrase: 'Удалить парольную фразу',
+      passphrase: 'Парольная фраза',
+      newPassphrase: 'Новая парольная фраза',
+      confirmPassphrase: 'Подтвердите парольную фразу',
+      passphraseHint:
+        'После установки парольная фраза нужна при каждом запуске для доступа к сохранённым токенам и настройкам прокси. Забытую фразу восстановить нельзя',
+      passphraseRequired: 'Введите парольную фразу',
+      passphraseMismatch: 'Парольные фразы не совпадают',
+      passphraseSet: 'Парольная фраза задана',
+      passphraseRemoved: 'Парольная фраза удалена',
+      rotateSuccess: 'Ключ сменён',
+      rotateFailed: 'Не удалось сменить ключ',
+      unlockTitle: 'Разблокировка зашифрованных данных',
+      unlockMessage:
+        'Сохранённые токены и настройки прокси защищены парольной фразой. Введите её для разблокировки',
+      unlock: 'Разблокировать',
+      later: 'Позже',
+      unlockSuccess: 'Разблокировано',
+      unlockFailed: 'Не удалось разблокировать',
+      confirm: 'Подтвердить',
+      cancel: 'Отмена',
+    },
     about: {
       title: 'О программе',
       version: 'Версия',
@@ -1920,6 +2131,37 @@ export const messages = {
         restoreHook: 'Restaurar hook',
       },
     },
+    secrets: {
+      title: 'Cifrado de datos',
+      keyFileMode: 'Archivo de clave',
+      passphraseMode: 'Frase de contraseña',
+      locked: 'Bloqueado',
+      rotationPending: 'Rotación pendiente',
+      rotate: 'Rotar clave',
+      setPassphrase: 'Establecer frase de contraseña',
+      changePassphrase: 'Cambiar frase de contraseña',
+      removePassphrase: 'Quitar frase de contraseña',
+      passphrase: 'Frase de contraseña',
+      newPassphrase: 'Nueva frase de contraseña',
+      confirmPassphrase: 'Confirmar frase de contraseña',
+      passphraseHint:
+        'Una vez establecida, la frase se pedirá en cada inicio para usar los tokens y el proxy guardados. Una frase olvidada no se puede recuperar',
+      passphraseRequired: 'Ingresá la frase de contraseña',
+      passphraseMismatch: 'Las frases de contraseña no coinciden',
+      passphraseSet: 'Frase de contraseña establecida',
+      passphraseRemoved: 'Frase de contraseña eliminada',
+      rotateSuccess: 'Clave rotada',
+      rotateFailed: 'No se pudo rotar la clave',
+      unlockTitle: 'Desbloquear datos cifrados',
+      unlockMessage:
+        'Los tokens y el proxy guardados están protegidos con una frase de contraseña. Ingresala para desbloquear',
+      unlock: 'Desbloquear',
+      later: 'Más tarde',
+      unlockSuccess: 'Desbloqueado',
+      unlockFailed: 'No se pudo desbloquear',
+      confirm: 'Confirmar',
+      cancel: 'Cancelar',
+    },
     about: {
       title: 'Acerca de',
       version: 'Versión',
@@ -14,6 +14,7 @@ import {
   getAccount,
   saveHistoryRecord,
   findCursorPath,
+  isAppError,
 } from '@/api'
 import type { UsageInfo, MachineInfo } from '@/api/types'
 import type { HistoryAccount } from '@/types/history'
@@ -372,7 +373,7 @@ export const useCursorStore = defineStore('cursor', () => {
     } catch (error) {
       console.error('检查Hook状态失败:', error)
       // 如果是找不到main.js文件，不更新状态
-      if (error instanceof Error && error.message.includes('MAIN_JS_NOT_FOUND')) {
+      if (isAppError(error) && error.code === 'MAIN_JS_NOT_FOUND') {
         console.log('检测到main.js文件路径问题')
         // 保持现有状态
         return hookStatus.value
@@ -1,8 +1,8 @@
 import { defineStore } from 'pinia'
 import { ref, computed } from 'vue'
 import { check } from '@tauri-apps/plugin-updater'
-import { relaunch } from '@tauri-apps/plugin-process'
 import { open } from '@tauri-apps/plugin-shell'
+import { getVersionStatus, restartApp } from '../api'
 
 export const useUpdaterStore = defineStore('updater', () => {
   // 状态
@@ -17,6 +17,9 @@ export const useUpdaterStore = defineStore('updater', () => {
   const updateNotes = ref('')
   const error = ref<string | null>(null)
   const isWebView2Update = ref(false)
+  // 当前版本低于远程配置要求的最低版本
+  const forceUpdate = ref(false)
+  const minVersion = ref('')
 
   // 计算属性
   const isUpdating = computed(() => isChecking.value || isDownloading.value || isInstalling.value)
@@ -97,6 +100,22 @@ export const useUpdaterStore = defineStore('updater', () => {
     }
   }
 
+  // 检查当前版本是否满足最低版本要求，不满足时强制更新
+  async function checkVersionStatus() {
+    try {
+      const status = await getVersionStatus()
+      forceUpdate.value = status.requirement === 'required'
+      minVersion.value = status.min_version || ''
+    } catch (err) {
+      console.error('获取版本状态失败:', err)
+      return
+    }
+
+    if (forceUpdate.value) {
+      await checkForUpdates()
+    }
+  }
+
   // 下载并安装更新
   async function installUpdate(update: any) {
     try {
@@ -139,7 +158,7 @@ export const useUpdaterStore = defineStore('updater', () => {
       await new Promise((resolve) => setTimeout(resolve, 1000))
 
       // Windows会自动重启，其他平台需要手动重启
-      await relaunch()
+      await restartApp()
     } catch (err) {
       isDownloading.value = false
       isInstalling.value = false
@@ -207,9 +226,12 @@ export const useUpdaterStore = defineStore('updater', () => {
     isUpdating,
     progressPercentage,
     isWebView2Update,
+    forceUpdate,
+    minVersion,
 
     // 方法
     checkForUpdates,
+    checkVersionStatus,
     openOfficialWebsite,
   }
 })
@@ -155,6 +155,16 @@ export const useUserStore = defineStore('user', () => {
     }
   }
 
+  /**
+   * 登录失效，后端已清除令牌，只需重置本地状态
+   */
+  function handleSessionExpired() {
+    userInfo.value = null
+    isLoggedIn.value = false
+    loginError.value = ''
+    window.dispatchEvent(new CustomEvent('user-logout'))
+  }
+
   /**
    * 激活码兑换
    */
@@ -240,6 +250,7 @@ export const useUserStore = defineStore('user', () => {
     login,
     register,
     logout,
+    handleSessionExpired,
     activateCode,
     changePassword,
     resetPassword,